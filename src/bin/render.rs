@@ -0,0 +1,264 @@
+// same test layout as the library
+#![cfg_attr(test, allow(clippy::unused_unit, clippy::useless_vec))]
+
+use std::{
+    env,
+    io::{self, Write},
+    process::ExitCode,
+    thread,
+    time::Instant,
+};
+
+use ray::{
+    model::{
+        render_settings::{Integrator, RenderSettings},
+        sampling::Sampling,
+    },
+    parsers::scene_file::SceneFile,
+    viewer::{
+        canvas::Canvas,
+        to_file::{ImageFormat, ToFile},
+    },
+};
+
+const USAGE: &str = "usage: render <scene.yml> <output.(png|ppm)> [options]
+  --width <pixels>     overrides the camera's width
+  --height <pixels>    overrides the camera's height
+  --depth <bounces>    reflection and refraction depth (default 3)
+  --samples <count>    samples per pixel (default 1)
+  --sampling <kind>    regular, jittered or adaptive[:threshold] (default regular)
+  --seed <number>      seeds the random sampling (default 0)
+  --integrator <kind>  whitted or path, path tracing needs many samples (default whitted)
+  --glossy <count>     rays averaged by rough reflections and refractions (default 8)
+  --cutoff <weight>    skips bounces weighing less than this in the pixel (default 0)
+  --no-shadows         lights everything as if nothing was in the way
+  --threads <count>    render threads (default: all cores)";
+
+const DEFAULT_THRESHOLD: f64 = 0.05;
+
+#[derive(PartialEq, Debug)]
+struct Args {
+    scene: String,
+    output: String,
+    width: Option<usize>,
+    height: Option<usize>,
+    settings: RenderSettings,
+}
+
+fn parse_args<I: Iterator<Item = String>>(mut args: I) -> Result<Args, String> {
+    let mut positional = Vec::new();
+    let mut width = None;
+    let mut height = None;
+    let threads = thread::available_parallelism().map_or(1, |n| n.get());
+    let mut settings = RenderSettings::default().threads(threads);
+    while let Some(arg) = args.next() {
+        if !arg.starts_with("--") {
+            positional.push(arg);
+            continue;
+        }
+        if arg == "--no-shadows" {
+            settings = settings.shadows(false);
+            continue;
+        }
+        let value = args
+            .next()
+            .ok_or_else(|| format!("missing value for {arg}"))?;
+        let invalid = || format!("invalid value for {arg}: {value}");
+        let number = || {
+            value
+                .parse::<usize>()
+                .ok()
+                .filter(|n| *n > 0 || arg == "--depth")
+                .ok_or_else(invalid)
+        };
+        match arg.as_str() {
+            "--width" => width = Some(number()?),
+            "--height" => height = Some(number()?),
+            "--depth" => {
+                let depth =
+                    u8::try_from(number()?).map_err(|_| format!("depth too large: {value}"))?;
+                settings = settings.max_depth(depth);
+            }
+            "--samples" => settings = settings.samples(number()?),
+            "--threads" => settings = settings.threads(number()?),
+            "--glossy" => settings = settings.glossy_samples(number()?),
+            "--seed" => settings = settings.seed(value.parse().map_err(|_| invalid())?),
+            "--sampling" => {
+                settings = settings.sampling(parse_sampling(&value).ok_or_else(invalid)?)
+            }
+            "--integrator" => {
+                let integrator = match value.as_str() {
+                    "whitted" => Integrator::Whitted,
+                    "path" => Integrator::Path,
+                    _ => return Err(invalid()),
+                };
+                settings = settings.integrator(integrator);
+            }
+            "--cutoff" => {
+                let cutoff = value
+                    .parse::<f64>()
+                    .ok()
+                    .filter(|c| (0. ..=1.).contains(c))
+                    .ok_or_else(invalid)?;
+                settings = settings.min_contribution(cutoff);
+            }
+            _ => return Err(format!("unknown option: {arg}")),
+        }
+    }
+    match <[String; 2]>::try_from(positional) {
+        Ok([scene, output]) => Ok(Args {
+            scene,
+            output,
+            width,
+            height,
+            settings,
+        }),
+        Err(_) => Err("expected a scene file and an output path".to_string()),
+    }
+}
+
+// adaptive takes an optional threshold after a colon
+fn parse_sampling(s: &str) -> Option<Sampling> {
+    match s.split_once(':') {
+        None if s == "regular" => Some(Sampling::Regular),
+        None if s == "jittered" => Some(Sampling::Jittered),
+        None if s == "adaptive" => Some(Sampling::Adaptive {
+            threshold: DEFAULT_THRESHOLD,
+        }),
+        Some(("adaptive", t)) => t
+            .parse()
+            .ok()
+            .filter(|t: &f64| *t >= 0.)
+            .map(|threshold| Sampling::Adaptive { threshold }),
+        _ => None,
+    }
+}
+
+fn run(args: Args) -> Result<(), String> {
+    // checked first so that a typo doesn't waste a whole render
+    let format = ImageFormat::from_path(&args.output)
+        .ok_or_else(|| format!("{}: unsupported image extension", args.output))?;
+    let start = Instant::now();
+    let scene = SceneFile::from_file(&args.scene).map_err(|e| format!("{}: {e}", args.scene))?;
+    let settings = args.settings.background(scene.background);
+    let camera = match (args.width, args.height) {
+        (None, None) => scene.camera,
+        (w, h) => scene.camera.resize(
+            w.unwrap_or(scene.camera.hsize),
+            h.unwrap_or(scene.camera.vsize),
+        ),
+    };
+    eprintln!(
+        "loaded {} in {:.2?}, rendering {}x{} with {} {:?} sample(s) and {:?} on {} thread(s)",
+        args.scene,
+        start.elapsed(),
+        camera.hsize,
+        camera.vsize,
+        settings.samples,
+        settings.sampling,
+        settings.integrator,
+        settings.threads
+    );
+
+    let render_start = Instant::now();
+    let mut canvas = Canvas::black(camera.hsize, camera.vsize);
+    canvas.render_with(&camera, &scene.world, &settings, |done, total| {
+        eprint!("\rrendering {:3}%", done * 100 / total);
+        let _ = io::stderr().flush();
+    });
+    eprintln!("\rrendered in {:.2?}", render_start.elapsed());
+
+    canvas
+        .to_file_as(&args.output, format)
+        .map_err(|e| format!("{}: {e}", args.output))?;
+    eprintln!("written {} in {:.2?} overall", args.output, start.elapsed());
+    Ok(())
+}
+
+fn main() -> ExitCode {
+    let res = parse_args(env::args().skip(1))
+        .map_err(|e| format!("{e}\n{USAGE}"))
+        .and_then(run);
+    match res {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("{e}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(s: &str) -> Result<Args, String> {
+        parse_args(s.split_whitespace().map(String::from))
+    }
+
+    #[test]
+    fn parse_args_options() -> () {
+        let res = args(
+            "--samples 4 scene.yml --depth 0 out.png --width 10 --threads 3 --no-shadows --cutoff 0.1 \
+             --sampling adaptive:0.2 --seed 42 --integrator path --glossy 16",
+        )
+        .unwrap();
+        assert_eq!(res.scene, "scene.yml");
+        assert_eq!(res.output, "out.png");
+        assert_eq!((res.width, res.height), (Some(10), None));
+        assert_eq!(
+            res.settings,
+            RenderSettings::default()
+                .samples(4)
+                .max_depth(0)
+                .threads(3)
+                .shadows(false)
+                .min_contribution(0.1)
+                .sampling(Sampling::Adaptive { threshold: 0.2 })
+                .seed(42)
+                .integrator(Integrator::Path)
+                .glossy_samples(16)
+        );
+    }
+
+    #[test]
+    fn parse_sampling_kinds() -> () {
+        vec![
+            ("regular", Some(Sampling::Regular)),
+            ("jittered", Some(Sampling::Jittered)),
+            (
+                "adaptive",
+                Some(Sampling::Adaptive {
+                    threshold: DEFAULT_THRESHOLD,
+                }),
+            ),
+            ("adaptive:0", Some(Sampling::Adaptive { threshold: 0. })),
+            ("jittered:1", None),
+            ("adaptive:x", None),
+        ]
+        .iter()
+        .for_each(|(s, exp)| assert_eq!(parse_sampling(s), *exp, "{s}"));
+    }
+
+    #[test]
+    fn parse_args_errors() -> () {
+        vec![
+            "scene.yml",
+            "scene.yml out.png extra.png",
+            "scene.yml out.png --width",
+            "scene.yml out.png --width 0",
+            "scene.yml out.png --samples -1",
+            "scene.yml out.png --depth 256",
+            "scene.yml out.png --cutoff 2",
+            "scene.yml out.png --cutoff",
+            "scene.yml out.png --sampling random",
+            "scene.yml out.png --sampling adaptive:-1",
+            "scene.yml out.png --seed -1",
+            "scene.yml out.png --integrator photon",
+            "scene.yml out.png --glossy 0",
+            "scene.yml out.png --colour 1",
+        ]
+        .iter()
+        .for_each(|s| assert!(args(s).is_err(), "{s}"));
+    }
+}
@@ -1,5 +1,13 @@
+// tests follow the `fn name() -> () { vec![..].iter().for_each(..) }` layout throughout
+// and spell out rounded expected values rather than std constants
+#![cfg_attr(
+    test,
+    allow(clippy::unused_unit, clippy::useless_vec, clippy::approx_constant)
+)]
+
 pub mod math;
 pub mod model;
+pub mod parsers;
 pub mod patterns;
 pub mod shapes;
 pub mod viewer;
@@ -1,12 +1,20 @@
-use std::{f64::consts::FRAC_PI_3, rc::Rc};
+use std::{
+    f64::consts::FRAC_PI_3,
+    sync::Arc,
+    thread,
+    time::{Duration, Instant},
+};
 
 use pixels::{Error, Pixels, SurfaceTexture};
 use ray::{
-    math::{colour::Colour, matrix::Matrix4x4, point::Point, vector::Vector},
-    model::{camera::Camera, material::Material, point_light::PointLight, world::World},
+    math::{colour::Colour, matrix::Matrix4x4, point::Point},
+    model::{
+        camera::Camera, material::Material, point_light::PointLight,
+        render_settings::RenderSettings, world::World,
+    },
     patterns::pattern::Pattern,
     shapes::shape::Shape,
-    viewer::{canvas::Canvas, drawable::Drawable, to_file::ToFile},
+    viewer::{drawable::Drawable, fly::FlyCamera, progressive::Progressive, to_file::ToFile},
 };
 use winit::{
     dpi::LogicalSize,
@@ -16,6 +24,11 @@ use winit::{
 };
 use winit_input_helper::WinitInputHelper;
 
+// radians per pixel the mouse moves
+const LOOK_SPEED: f64 = 0.005;
+// fov factor per scroll wheel notch
+const ZOOM_SPEED: f64 = 0.9;
+
 fn main() -> Result<(), Error> {
     let width = 500;
     let height = 250;
@@ -55,34 +68,30 @@ fn main() -> Result<(), Error> {
         Colour::hex("4f", "3b", "3b").unwrap_or(Colour::WHITE),
         Colour::hex("3b", "4f", "3b").unwrap_or(Colour::BLACK),
         Matrix4x4::scaling(0.1, 0.1, 0.1),
-    ).unwrap();
+    )
+    .unwrap();
 
     let walls = Shape::new_cube(Matrix4x4::scaling(18., 15., 18.))
         .unwrap()
         .material(Material::default().pattern(stripe_pattern));
 
-    let world = World::new(
-        vec![Rc::new(floor_and_roof), Rc::new(walls)],
-        vec![PointLight::new(Point::new(-8., 8., -8.), Colour::WHITE)],
-    );
-
-    let vt = {
-        let to = Point::new(0., 1., 0.);
-        let up = Vector::new(0., 1., 0.);
-        move |eye: Point| -> Matrix4x4 { Matrix4x4::view_transform(eye, to, up) }
-    };
+    let world = Arc::new(World::new(
+        vec![Arc::new(floor_and_roof), Arc::new(walls)],
+        vec![PointLight::new(Point::new(-8., 8., -8.), Colour::WHITE).into()],
+    ));
 
-    let mut eye = Point::new(0., 1.5, -7.);
-    let mut camera = Camera::new(width_usize, height_usize, FRAC_PI_3)
-        .transform(vt(eye))
-        .unwrap();
+    let mut fly =
+        FlyCamera::new(Point::new(0., 1.5, -7.), Point::new(0., 1., 0.), FRAC_PI_3).unwrap();
+    let mut camera = fly.camera(Camera::new(width_usize, height_usize, FRAC_PI_3));
 
-    let mut canvas = Canvas::black(camera.hsize, camera.vsize);
-    canvas.render(&camera, &world);
+    let threads = thread::available_parallelism().map_or(1, |n| n.get());
+    let settings = RenderSettings::default().threads(threads);
+    let mut progressive = Progressive::new(camera.hsize, camera.vsize);
+    progressive.restart(&camera, &world, &settings);
 
     event_loop.run(move |event, _, control_flow| {
         if let Event::RedrawRequested(_) = event {
-            canvas.draw(pixels.frame_mut());
+            progressive.draw(pixels.frame_mut());
             if let Err(err) = pixels.render() {
                 println!("pixels.render {}", err);
                 *control_flow = ControlFlow::Exit;
@@ -92,30 +101,67 @@ fn main() -> Result<(), Error> {
         if input.update(&event) {
             // Close events
             if input.key_pressed(VirtualKeyCode::Escape) || input.close_requested() {
-                let path = "result_world.ppm";
-                match canvas.to_file(path) {
+                let path = "result_world.png";
+                // the file gets the full render, not the preview being refined
+                progressive.wait();
+                match progressive.canvas().to_file(path) {
                     Ok(()) => println!("successfully written {}", path),
                     Err(err) => println!("error writing {}", err),
                 }
 
                 *control_flow = ControlFlow::Exit;
                 return;
-            } else if input.key_pressed(VirtualKeyCode::W) {
-                eye = Point::new(eye.x, eye.y + 0.5, eye.z);
-            } else if input.key_pressed(VirtualKeyCode::S) {
-                eye = Point::new(eye.x, eye.y - 0.5, eye.z);
-            } else if input.key_pressed(VirtualKeyCode::A) {
-                eye = Point::new(eye.x - 0.5, eye.y, eye.z);
-            } else if input.key_pressed(VirtualKeyCode::D) {
-                eye = Point::new(eye.x + 0.5, eye.y, eye.z);
-            } else if input.key_pressed(VirtualKeyCode::Q) {
-                eye = Point::new(eye.x, eye.y, eye.z - 0.5);
-            } else if input.key_pressed(VirtualKeyCode::E) {
-                eye = Point::new(eye.x, eye.y, eye.z + 0.5);
             }
-            camera = camera.transform(vt(eye)).unwrap();
-            canvas.render(&camera, &world);
-            window.request_redraw();
+            let previous = fly;
+            // WASD along the view, QE down and up, faster with shift and slower with control
+            let step = if input.held_shift() {
+                2.
+            } else if input.held_control() {
+                0.1
+            } else {
+                0.5
+            };
+            [
+                (VirtualKeyCode::W, 1., 0., 0.),
+                (VirtualKeyCode::S, -1., 0., 0.),
+                (VirtualKeyCode::A, 0., -1., 0.),
+                (VirtualKeyCode::D, 0., 1., 0.),
+                (VirtualKeyCode::Q, 0., 0., -1.),
+                (VirtualKeyCode::E, 0., 0., 1.),
+            ]
+            .iter()
+            .filter(|(key, ..)| input.key_pressed_os(*key))
+            .for_each(|(_, forward, right, up)| {
+                fly = fly.fly(forward * step, right * step, up * step);
+            });
+            // looking around while the left button is held
+            if input.mouse_held(0) {
+                let (dx, dy) = input.mouse_diff();
+                fly = fly.look(dx as f64 * LOOK_SPEED, -dy as f64 * LOOK_SPEED);
+            }
+            let scroll = input.scroll_diff();
+            if scroll != 0. {
+                fly = fly.zoom(ZOOM_SPEED.powf(scroll as f64));
+            }
+            // ready to paste in a scene file's camera
+            if input.key_pressed(VirtualKeyCode::P) {
+                println!("{fly}");
+            }
+            // moving drops the render in flight, the window keeps showing it until the preview
+            if fly != previous {
+                camera = fly.camera(camera);
+                progressive.restart(&camera, &world, &settings);
+            }
+            // checked before the last pixels are picked up so that they get drawn
+            let done = progressive.is_done();
+            if progressive.take_changed() {
+                window.request_redraw();
+            }
+            *control_flow = if done {
+                ControlFlow::Wait
+            } else {
+                ControlFlow::WaitUntil(Instant::now() + Duration::from_millis(30))
+            };
         }
     });
 }
@@ -37,6 +37,16 @@ impl Colour {
         let s = T::to_f64(&scale).unwrap_or(0.0);
         (f(self.r, s), f(self.g, s), f(self.b, s))
     }
+
+    // perceived brightness, https://en.wikipedia.org/wiki/Relative_luminance
+    pub fn luminance(&self) -> f64 {
+        0.2126 * self.r + 0.7152 * self.g + 0.0722 * self.b
+    }
+
+    // f applied to each channel
+    pub fn map<F: Fn(f64) -> f64>(&self, f: F) -> Colour {
+        Colour::new(f(self.r), f(self.g), f(self.b))
+    }
 }
 
 impl Add<Colour> for Colour {
@@ -81,6 +91,8 @@ impl IntoIterator for Colour {
 
 #[cfg(test)]
 mod tests {
+    use crate::math::round::Round;
+
     use super::*;
 
     #[test]
@@ -124,6 +136,19 @@ mod tests {
         assert_eq!(b, 0);
     }
 
+    #[test]
+    fn luminance() -> () {
+        assert_eq!(vec![Colour::WHITE.luminance()].rounded(5), vec![1.]);
+        assert_eq!(Colour::BLACK.luminance(), 0.);
+        assert_eq!(Colour::new(0., 1., 0.).luminance(), 0.7152);
+    }
+
+    #[test]
+    fn map() -> () {
+        let c = Colour::new(1., 0.5, 0.);
+        assert_eq!(c.map(|c| c * 2.), Colour::new(2., 1., 0.));
+    }
+
     #[test]
     fn mul_colour() -> () {
         let c1 = Colour::new(1.0, 0.2, 0.4);
@@ -17,7 +17,7 @@ impl<A: MatrixSub<Output = B>, B: MatrixDet> MatrixCofactor for A {
         let minor = self.minor(r, c);
         //let sign = (-1i32).pow(r as u32 + c as u32);
         //sign as f64 * minor
-        if (r + c) % 2 == 0 {
+        if (r + c).is_multiple_of(2) {
             minor
         } else {
             -minor
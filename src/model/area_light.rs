@@ -0,0 +1,151 @@
+use crate::math::{colour::Colour, point::Point, vector::Vector};
+
+// a rectangle of usteps by vsteps cells, each one lighting like a point light
+#[derive(PartialEq, Debug, Copy, Clone)]
+pub struct AreaLight {
+    pub corner: Point,
+    // edges of a single cell
+    pub uvec: Vector,
+    pub usteps: usize,
+    pub vvec: Vector,
+    pub vsteps: usize,
+    pub intensity: Colour,
+    // samples a random point in each cell rather than its centre
+    pub jitter: bool,
+}
+
+impl AreaLight {
+    // full_uvec and full_vvec are the edges of the whole rectangle
+    pub fn new(
+        corner: Point,
+        full_uvec: Vector,
+        usteps: usize,
+        full_vvec: Vector,
+        vsteps: usize,
+        intensity: Colour,
+    ) -> Option<Self> {
+        (usteps > 0 && vsteps > 0).then(|| Self {
+            corner,
+            uvec: full_uvec * (1. / usteps as f64),
+            usteps,
+            vvec: full_vvec * (1. / vsteps as f64),
+            vsteps,
+            intensity,
+            jitter: false,
+        })
+    }
+
+    pub fn jitter(mut self, j: bool) -> Self {
+        self.jitter = j;
+        self
+    }
+
+    pub fn samples(&self) -> usize {
+        self.usteps * self.vsteps
+    }
+
+    pub fn centre(&self) -> Point {
+        self.corner + self.uvec * (self.usteps as f64 / 2.) + self.vvec * (self.vsteps as f64 / 2.)
+    }
+
+    // ou and ov locate the point inside the cell, from 0 to 1
+    pub fn point_on(&self, u: usize, v: usize, ou: f64, ov: f64) -> Point {
+        self.corner + self.uvec * (u as f64 + ou) + self.vvec * (v as f64 + ov)
+    }
+
+    // one point per cell, jittered points only depend on the lit point so that
+    // shadows and shading agree and renders stay reproducible
+    pub fn points(&self, lit: Point) -> Vec<Point> {
+        (0..self.vsteps)
+            .flat_map(|v| (0..self.usteps).map(move |u| (u, v)))
+            .map(|(u, v)| {
+                if self.jitter {
+                    let h = hash(lit, u, v);
+                    self.point_on(u, v, unit(h), unit(mix(h)))
+                } else {
+                    self.point_on(u, v, 0.5, 0.5)
+                }
+            })
+            .collect()
+    }
+}
+
+// splitmix64 finaliser
+fn mix(mut z: u64) -> u64 {
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    z ^ (z >> 31)
+}
+
+fn hash(p: Point, u: usize, v: usize) -> u64 {
+    let cell = ((v as u64) << 32) | u as u64;
+    [p.x, p.y, p.z]
+        .iter()
+        .fold(mix(cell), |h, c| mix(h ^ c.to_bits()))
+}
+
+// uniform in [0, 1) from the top 53 bits
+fn unit(h: u64) -> f64 {
+    (h >> 11) as f64 / (1u64 << 53) as f64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn light() -> AreaLight {
+        AreaLight::new(
+            Point::ORIGIN,
+            Vector::new(2., 0., 0.),
+            4,
+            Vector::new(0., 0., 1.),
+            2,
+            Colour::WHITE,
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn new() -> () {
+        let l = light();
+        assert_eq!(l.uvec, Vector::new(0.5, 0., 0.));
+        assert_eq!(l.vvec, Vector::new(0., 0., 0.5));
+        assert_eq!(l.samples(), 8);
+        assert_eq!(l.centre(), Point::new(1., 0., 0.5));
+        assert!(AreaLight::new(Point::ORIGIN, l.uvec, 0, l.vvec, 1, Colour::WHITE).is_none());
+    }
+
+    #[test]
+    fn point_on() -> () {
+        let l = light();
+        vec![
+            (0, 0, Point::new(0.25, 0., 0.25)),
+            (1, 0, Point::new(0.75, 0., 0.25)),
+            (0, 1, Point::new(0.25, 0., 0.75)),
+            (2, 0, Point::new(1.25, 0., 0.25)),
+            (3, 1, Point::new(1.75, 0., 0.75)),
+        ]
+        .iter()
+        .for_each(|(u, v, exp)| {
+            assert_eq!(l.point_on(*u, *v, 0.5, 0.5), *exp);
+            assert_eq!(l.points(Point::ORIGIN)[v * 4 + u], *exp);
+        });
+    }
+
+    #[test]
+    fn points_jittered() -> () {
+        let l = light().jitter(true);
+        let lit = Point::new(1., 2., 3.);
+        let points = l.points(lit);
+        assert_eq!(points.len(), 8);
+        // every point stays in its own cell
+        points.iter().enumerate().for_each(|(i, p)| {
+            let (u, v) = (i % 4, i / 4);
+            assert_eq!(((p.x * 2.) as usize, (p.z * 2.) as usize), (u, v));
+            assert_eq!(p.y, 0.);
+        });
+        assert_eq!(points, l.points(lit));
+        assert_ne!(points, l.points(Point::new(1., 2., 3.5)));
+        assert_ne!(points, light().points(lit));
+    }
+}
@@ -0,0 +1,148 @@
+use std::sync::Arc;
+
+use crate::{
+    math::{colour::Colour, point::Point, vector::Vector},
+    patterns::{
+        image::{Filter, Image},
+        uv_mapping::{Face, UvMapping},
+    },
+    viewer::canvas::Canvas,
+};
+
+// what rays that don't hit anything see, only their direction matters
+#[derive(PartialEq, Debug, Clone)]
+pub enum Background {
+    Solid(Colour),
+    // from straight down to straight up
+    Gradient { bottom: Colour, top: Colour },
+    // one image per face in the +x, -x, +y, -y, +z, -z order
+    CubeMap(Box<[Image; 6]>),
+    // a single panorama, longitude along u and latitude along v
+    Equirectangular(Image),
+}
+
+impl Background {
+    pub fn cube_map(faces: [Arc<Canvas>; 6], filter: Filter) -> Self {
+        Background::CubeMap(Box::new(
+            faces.map(|c| Image::new(c, UvMapping::Cube, filter)),
+        ))
+    }
+
+    pub fn equirectangular(canvas: Arc<Canvas>, filter: Filter) -> Self {
+        Background::Equirectangular(Image::new(canvas, UvMapping::Spherical, filter))
+    }
+
+    pub fn colour(&self, direction: Vector) -> Colour {
+        match self {
+            Background::Solid(c) => *c,
+            Background::Gradient { bottom, top } => {
+                let t = (direction.norm().y + 1.) / 2.;
+                *bottom * (1. - t) + *top * t
+            }
+            Background::CubeMap(faces) => {
+                // pushed onto the unit cube to pick the face
+                let d = direction;
+                let m = d.x.abs().max(d.y.abs()).max(d.z.abs());
+                let p = Point::new(d.x / m, d.y / m, d.z / m);
+                let face = Face::of(p);
+                // faces are seen from inside the cube, mirrored compared to from outside
+                let (u, v) = face.uv(p);
+                faces[face as usize].uv_at(1. - u, v)
+            }
+            Background::Equirectangular(image) => {
+                let (u, v) =
+                    UvMapping::Spherical.uv(Point::new(direction.x, direction.y, direction.z));
+                image.uv_at(u, v)
+            }
+        }
+    }
+}
+
+impl Default for Background {
+    fn default() -> Self {
+        Background::Solid(Colour::BLACK)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::math::round::Round;
+
+    use super::*;
+
+    fn solid(c: Colour) -> Arc<Canvas> {
+        Arc::new(Canvas::new(2, 2, c))
+    }
+
+    #[test]
+    fn colour_solid() -> () {
+        let b = Background::Solid(Colour::WHITE);
+        assert_eq!(b.colour(Vector::new(1., 2., 3.)), Colour::WHITE);
+        assert_eq!(Background::default().colour(Vector::Y), Colour::BLACK);
+    }
+
+    #[test]
+    fn colour_gradient() -> () {
+        let b = Background::Gradient {
+            bottom: Colour::BLACK,
+            top: Colour::new(0., 0., 1.),
+        };
+        vec![
+            (Vector::new(0., 1., 0.), 1.),
+            (Vector::new(0., -2., 0.), 0.),
+            (Vector::new(3., 0., 4.), 0.5),
+            (Vector::new(0., 1., 1.), 0.85355),
+        ]
+        .iter()
+        .for_each(|(d, exp)| {
+            assert_eq!(b.colour(*d).rounded(5), vec![0., 0., *exp]);
+        });
+    }
+
+    #[test]
+    fn colour_cube_map() -> () {
+        let colours = [
+            Colour::new(1., 0., 0.),
+            Colour::new(0., 1., 0.),
+            Colour::new(0., 0., 1.),
+            Colour::new(1., 1., 0.),
+            Colour::new(0., 1., 1.),
+            Colour::new(1., 0., 1.),
+        ];
+        let b = Background::cube_map(colours.map(solid), Filter::Nearest);
+        vec![
+            (Vector::new(1., 0.2, -0.3), colours[0]),
+            (Vector::new(-5., 1., 1.), colours[1]),
+            (Vector::new(0., 0.5, 0.), colours[2]),
+            (Vector::new(0.1, -1., 0.1), colours[3]),
+            (Vector::new(0., 0., 1.), colours[4]),
+            (Vector::new(0.3, 0.3, -0.9), colours[5]),
+        ]
+        .iter()
+        .for_each(|(d, exp)| assert_eq!(b.colour(*d), *exp));
+    }
+
+    #[test]
+    fn colour_cube_map_inside() -> () {
+        // the left half of the front face is red, looking at it from inside +x is on the right
+        let mut front = Canvas::black(2, 1);
+        front.update(0, 0, Colour::new(1., 0., 0.));
+        let mut faces = [(); 6].map(|_| solid(Colour::BLACK));
+        faces[Face::Front as usize] = Arc::new(front);
+        let b = Background::cube_map(faces, Filter::Nearest);
+        assert_eq!(b.colour(Vector::new(0.5, 0., 1.)), Colour::new(1., 0., 0.));
+        assert_eq!(b.colour(Vector::new(-0.5, 0., 1.)), Colour::BLACK);
+    }
+
+    #[test]
+    fn colour_equirectangular() -> () {
+        let mut panorama = Canvas::black(5, 3);
+        panorama.update(2, 0, Colour::WHITE);
+        panorama.update(0, 1, Colour::new(1., 0., 0.));
+        panorama.update(2, 1, Colour::new(0., 1., 0.));
+        let b = Background::equirectangular(Arc::new(panorama), Filter::Nearest);
+        assert_eq!(b.colour(Vector::new(0., 1., 0.)), Colour::WHITE);
+        assert_eq!(b.colour(Vector::new(0., 0., -1.)), Colour::new(1., 0., 0.));
+        assert_eq!(b.colour(Vector::new(0., 0., 1.)), Colour::new(0., 1., 0.));
+    }
+}
@@ -0,0 +1,255 @@
+use std::sync::Arc;
+
+use crate::shapes::{bounds::Bounds, shape::Shape, shape_kind::ShapeKind};
+
+use super::{intersection::Intersection, ray::Ray};
+
+// bounding volume hierarchy, rays only visit the shapes whose boxes they cross
+#[derive(PartialEq, Debug, Clone)]
+pub struct Bvh {
+    root: Option<BvhNode>,
+    // planes and infinite cylinders / cones can't be split, they're always checked
+    unbounded: Vec<Arc<Shape>>,
+}
+
+#[derive(PartialEq, Debug, Clone)]
+enum BvhNode {
+    Leaf {
+        bounds: Bounds,
+        shapes: Vec<Arc<Shape>>,
+    },
+    Split {
+        bounds: Bounds,
+        left: Box<BvhNode>,
+        right: Box<BvhNode>,
+    },
+}
+
+#[derive(PartialEq, Debug, Copy, Clone)]
+pub struct BvhStats {
+    pub nodes: usize,
+    pub leaves: usize,
+    pub depth: usize,
+    pub bounded: usize,
+    pub unbounded: usize,
+}
+
+impl Bvh {
+    const MAX_LEAF_SIZE: usize = 4;
+
+    pub fn new(shapes: &[Arc<Shape>]) -> Self {
+        let mut leaves = Vec::new();
+        shapes.iter().for_each(|s| Self::flatten(s, &mut leaves));
+        let (bounded, unbounded): (Vec<_>, Vec<_>) = leaves
+            .into_iter()
+            .map(|s| {
+                let b = s.bounds();
+                (s, b)
+            })
+            .partition(|(_, b)| b.is_finite());
+        Self {
+            root: if bounded.is_empty() {
+                None
+            } else {
+                Some(BvhNode::build(bounded))
+            },
+            unbounded: unbounded.into_iter().map(|(s, _)| s).collect(),
+        }
+    }
+
+    // group children are already in world space so they can be spread through the tree,
+    // csgs have to be intersected as a whole to be filtered
+    fn flatten(s: &Arc<Shape>, acc: &mut Vec<Arc<Shape>>) {
+        match &s.underlying {
+            ShapeKind::G(group) => group.children().iter().for_each(|c| Self::flatten(c, acc)),
+            _ => acc.push(Arc::clone(s)),
+        }
+    }
+
+    // r is in world space, the intersections are sorted
+    pub fn intersections(&self, r: &Ray) -> Vec<Intersection> {
+        let mut is: Vec<Intersection> = self
+            .unbounded
+            .iter()
+            .flat_map(|s| Intersection::intersections(Arc::clone(s), r))
+            .collect();
+        if let Some(root) = &self.root {
+            root.intersections(r, &mut is);
+        }
+        // safe if no NaN
+        is.sort_by(|a, b| a.t.partial_cmp(&b.t).unwrap());
+        is
+    }
+
+    pub fn stats(&self) -> BvhStats {
+        let mut stats = BvhStats {
+            nodes: 0,
+            leaves: 0,
+            depth: 0,
+            bounded: 0,
+            unbounded: self.unbounded.len(),
+        };
+        if let Some(root) = &self.root {
+            root.stats(1, &mut stats);
+        }
+        stats
+    }
+}
+
+impl BvhNode {
+    fn build(mut shapes: Vec<(Arc<Shape>, Bounds)>) -> Self {
+        let bounds = shapes
+            .iter()
+            .fold(Bounds::EMPTY, |acc, (_, b)| acc.merge(*b));
+        if shapes.len() <= Bvh::MAX_LEAF_SIZE {
+            return BvhNode::Leaf {
+                bounds,
+                shapes: shapes.into_iter().map(|(s, _)| s).collect(),
+            };
+        }
+
+        // median split along the axis where the centres are the most spread out
+        let centres = shapes
+            .iter()
+            .fold(Bounds::EMPTY, |acc, (_, b)| acc.include(b.centre()));
+        let axis = centres.largest_axis();
+        let key = |b: &Bounds| {
+            let c = b.centre();
+            match axis {
+                0 => c.x,
+                1 => c.y,
+                _ => c.z,
+            }
+        };
+        // safe if no NaN
+        shapes.sort_by(|(_, a), (_, b)| key(a).partial_cmp(&key(b)).unwrap());
+        let right = shapes.split_off(shapes.len() / 2);
+        BvhNode::Split {
+            bounds,
+            left: Box::new(BvhNode::build(shapes)),
+            right: Box::new(BvhNode::build(right)),
+        }
+    }
+
+    fn intersections(&self, r: &Ray, acc: &mut Vec<Intersection>) {
+        match self {
+            BvhNode::Leaf { bounds, shapes } => {
+                if bounds.intersects(r) {
+                    shapes.iter().for_each(|s| {
+                        acc.append(&mut Intersection::intersections(Arc::clone(s), r))
+                    });
+                }
+            }
+            BvhNode::Split {
+                bounds,
+                left,
+                right,
+            } => {
+                if bounds.intersects(r) {
+                    left.intersections(r, acc);
+                    right.intersections(r, acc);
+                }
+            }
+        }
+    }
+
+    fn stats(&self, depth: usize, acc: &mut BvhStats) {
+        acc.nodes += 1;
+        acc.depth = acc.depth.max(depth);
+        match self {
+            BvhNode::Leaf { shapes, .. } => {
+                acc.leaves += 1;
+                acc.bounded += shapes.len();
+            }
+            BvhNode::Split { left, right, .. } => {
+                left.stats(depth + 1, acc);
+                right.stats(depth + 1, acc);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::math::{matrix::Matrix4x4, point::Point, vector::Vector};
+
+    use super::*;
+
+    fn spheres(n: usize) -> Vec<Arc<Shape>> {
+        (0..n)
+            .map(|i| {
+                Arc::new(Shape::new_sphere(Matrix4x4::translation(i as f64 * 3., 0., 0.)).unwrap())
+            })
+            .collect()
+    }
+
+    #[test]
+    fn stats() -> () {
+        vec![
+            (0, 0, 0, 0),
+            (1, 1, 1, 1),
+            (4, 1, 1, 1),
+            (5, 3, 2, 2),
+            (16, 7, 4, 3),
+            (17, 9, 5, 4),
+        ]
+        .iter()
+        .for_each(|(n, nodes, leaves, depth)| {
+            let stats = Bvh::new(&spheres(*n)).stats();
+            assert_eq!(stats.nodes, *nodes);
+            assert_eq!(stats.leaves, *leaves);
+            assert_eq!(stats.depth, *depth);
+            assert_eq!(stats.bounded, *n);
+            assert_eq!(stats.unbounded, 0);
+        });
+    }
+
+    #[test]
+    fn stats_unbounded_and_groups() -> () {
+        let mut shapes = vec![
+            Arc::new(Shape::id_plane()),
+            Arc::new(Shape::id_cylinder()),
+            Arc::new(Shape::id_group(spheres(3))),
+        ];
+        shapes.append(&mut spheres(2));
+        let stats = Bvh::new(&shapes).stats();
+        assert_eq!(stats.unbounded, 2);
+        assert_eq!(stats.bounded, 5);
+    }
+
+    #[test]
+    fn intersections() -> () {
+        let shapes = spheres(20);
+        let bvh = Bvh::new(&shapes);
+        let r = Ray::new(Point::new(30., 0., -5.), Vector::new(0., 0., 1.));
+        let res = bvh.intersections(&r);
+        assert_eq!(res.len(), 2);
+        assert!(res.iter().all(|i| Arc::ptr_eq(&i.shape, &shapes[10])));
+        assert_eq!(res[0].t, 4.);
+        assert_eq!(res[1].t, 6.);
+    }
+
+    #[test]
+    fn intersections_match_linear() -> () {
+        let mut shapes = spheres(10);
+        shapes.push(Arc::new(
+            Shape::new_plane(Matrix4x4::translation(0., -1., 0.)).unwrap(),
+        ));
+        let bvh = Bvh::new(&shapes);
+        vec![
+            Ray::new(Point::new(-5., 0., 0.), Vector::new(1., 0., 0.)),
+            Ray::new(Point::new(12.5, 5., 0.), Vector::new(0., -1., 0.)),
+            Ray::new(Point::new(0., 5., -5.), Vector::new(0.3, -0.5, 0.8).norm()),
+            Ray::new(Point::new(0., 5., -5.), Vector::new(0., 1., 0.)),
+        ]
+        .iter()
+        .for_each(|r| {
+            let mut exp: Vec<Intersection> = shapes
+                .iter()
+                .flat_map(|s| Intersection::intersections(Arc::clone(s), r))
+                .collect();
+            exp.sort_by(|a, b| a.t.partial_cmp(&b.t).unwrap());
+            assert_eq!(bvh.intersections(r), exp);
+        });
+    }
+}
@@ -10,6 +10,10 @@ pub struct Camera {
     pub pixel_size: f64,
     pub half_width: f64,
     pub half_height: f64,
+    // diameter of the lens, 0 for a pinhole camera where everything is sharp
+    pub aperture: f64,
+    // distance to the plane in focus when the aperture isn't 0
+    pub focal_distance: f64,
 }
 
 impl Camera {
@@ -33,9 +37,46 @@ impl Camera {
             pixel_size,
             half_width,
             half_height,
+            aperture: 0.,
+            focal_distance: 1.,
         }
     }
 
+    // same field of view and orientation, different resolution
+    pub fn resize(self, hsize: usize, vsize: usize) -> Self {
+        Self {
+            transform: self.transform,
+            inv_t: self.inv_t,
+            aperture: self.aperture,
+            focal_distance: self.focal_distance,
+            ..Self::new(hsize, vsize, self.fov)
+        }
+    }
+
+    // same resolution and orientation, zoomed in or out
+    pub fn field_of_view(self, fov: f64) -> Self {
+        Self {
+            transform: self.transform,
+            inv_t: self.inv_t,
+            aperture: self.aperture,
+            focal_distance: self.focal_distance,
+            ..Self::new(self.hsize, self.vsize, fov)
+        }
+    }
+
+    pub fn aperture(mut self, a: f64) -> Self {
+        self.aperture = a.max(0.);
+        self
+    }
+
+    // none unless positive, the focal plane would be on or behind the lens
+    pub fn focal_distance(mut self, d: f64) -> Option<Self> {
+        (d > 0.).then(|| {
+            self.focal_distance = d;
+            self
+        })
+    }
+
     pub fn transform(mut self, t: Matrix4x4) -> Option<Self> {
         self.transform = t;
         t.invert().map(|inv_t| {
@@ -63,6 +104,44 @@ mod tests {
         assert_eq!((c.pixel_size * 10000.).round(), 100.);
     }
 
+    #[test]
+    fn resize() -> () {
+        let t = Matrix4x4::translation(0., -2., 5.);
+        let c = Camera::new(200, 125, FRAC_PI_2)
+            .transform(t)
+            .unwrap()
+            .aperture(0.2)
+            .focal_distance(4.)
+            .unwrap();
+        let res = c.resize(125, 200);
+        assert_eq!((res.aperture, res.focal_distance), (0.2, 4.));
+        assert_eq!((res.hsize, res.vsize), (125, 200));
+        assert_eq!(res.fov, FRAC_PI_2);
+        assert_eq!(res.transform, t);
+        assert_eq!(res.inv_t, c.inv_t);
+        assert_eq!(res.pixel_size, Camera::new(125, 200, FRAC_PI_2).pixel_size);
+    }
+
+    #[test]
+    fn field_of_view() -> () {
+        let t = Matrix4x4::translation(0., -2., 5.);
+        let c = Camera::new(200, 125, FRAC_PI_2)
+            .transform(t)
+            .unwrap()
+            .aperture(0.2);
+        let res = c.field_of_view(FRAC_PI_2 / 2.);
+        assert_eq!((res.hsize, res.vsize, res.aperture), (200, 125, 0.2));
+        assert_eq!((res.transform, res.inv_t), (t, c.inv_t));
+        assert_eq!(
+            res,
+            Camera::new(200, 125, FRAC_PI_2 / 2.)
+                .transform(t)
+                .unwrap()
+                .aperture(0.2)
+        );
+        assert!(res.pixel_size < c.pixel_size);
+    }
+
     #[test]
     fn new() -> () {
         let w = 160;
@@ -73,5 +152,16 @@ mod tests {
         assert_eq!(c.vsize, h);
         assert_eq!(c.fov, fov);
         assert_eq!(c.transform, Matrix4x4::ID);
+        assert_eq!((c.aperture, c.focal_distance), (0., 1.));
+        assert_eq!(c.aperture(-1.).aperture, 0.);
+    }
+
+    #[test]
+    fn focal_distance() -> () {
+        let c = Camera::new(160, 120, FRAC_PI_2);
+        assert_eq!(c.focal_distance(2.).map(|c| c.focal_distance), Some(2.));
+        vec![0., -1., f64::NAN]
+            .iter()
+            .for_each(|d| assert!(c.focal_distance(*d).is_none(), "{d}"));
     }
 }
@@ -1,6 +1,10 @@
+use rand::Rng;
+
 use crate::math::{point::Point, vector::Vector};
 
-use super::{intersection::Intersection, ray::Ray, refractive_indices::RefractiveIndices};
+use super::{
+    intersection::Intersection, ray::Ray, refractive_indices::RefractiveIndices, sampling,
+};
 
 #[derive(PartialEq, Debug, Clone)]
 pub struct Comp {
@@ -10,7 +14,9 @@ pub struct Comp {
     pub over_point: Point,
     pub under_point: Point,
     pub eye: Vector,
+    // used for shading, perturbed by the material's bump
     pub normal: Vector,
+    pub geometric_normal: Vector,
     pub reflect: Vector,
     pub inside: bool,
 }
@@ -21,15 +27,29 @@ impl Comp {
     pub fn new(intersection: Intersection, ray: Ray, is: &[Intersection]) -> Self {
         let point = ray.position(intersection.t);
         let eye = -ray.direction;
-        let mut normal = intersection.shape.normal_at(point);
-        let inside = if normal.dot(eye) < 0. {
+        // intersections only refer to leaf shapes, which always have a normal
+        let mut geometric_normal = intersection
+            .shape
+            .normal_at_uv(point, intersection.uv)
+            .unwrap_or(eye);
+        let mut normal = match &intersection.shape.material.bump {
+            Some(b) => b.normal(&intersection.shape, point, geometric_normal),
+            None => geometric_normal,
+        };
+        let inside = if geometric_normal.dot(eye) < 0. {
+            geometric_normal = -geometric_normal;
             normal = -normal;
             true
         } else {
             false
         };
-        let over_point = point + normal * Self::EPS;
-        let under_point = point - normal * Self::EPS;
+        // a steep bump can't turn the surface away from the eye
+        if normal.dot(eye) < 0. {
+            normal = geometric_normal;
+        }
+        // offset along the real surface so that bumps don't cause acne
+        let over_point = point + geometric_normal * Self::EPS;
+        let under_point = point - geometric_normal * Self::EPS;
         let reflect = ray.direction.reflect(normal);
         let indices = RefractiveIndices::new(&intersection, is).refract(eye, normal);
         Self {
@@ -40,27 +60,88 @@ impl Comp {
             under_point,
             eye,
             normal,
+            geometric_normal,
             reflect,
             inside,
         }
     }
+
+    // off a microfacet picked by roughness, the perfect reflection when smooth
+    pub fn reflected<R: Rng>(&self, roughness: f64, rng: &mut R) -> Vector {
+        if roughness <= 0. {
+            return self.reflect;
+        }
+        let facet = sampling::ggx_normal(self.normal, roughness * roughness, rng);
+        let direction = facet * (2. * self.eye.dot(facet)) - self.eye;
+        // facets facing away or reflecting into the surface fall back to the perfect one
+        if self.eye.dot(facet) > 0. && direction.dot(self.geometric_normal) > 0. {
+            direction
+        } else {
+            self.reflect
+        }
+    }
+
+    // through a microfacet picked by roughness, the perfect refraction when smooth
+    pub fn refracted<R: Rng>(&self, roughness: f64, rng: &mut R) -> Vector {
+        let through = |normal: Vector, indices: RefractiveIndices| {
+            normal * (indices.ratio * indices.cos1 - indices.cos2) - self.eye * indices.ratio
+        };
+        let perfect = through(self.normal, self.indices);
+        if roughness <= 0. {
+            return perfect;
+        }
+        let facet = sampling::ggx_normal(self.normal, roughness * roughness, rng);
+        let indices = self.indices.refract(self.eye, facet);
+        let direction = through(facet, indices);
+        if indices.cos1 > 0.
+            && !indices.total_internal_reflection()
+            && direction.dot(self.geometric_normal) < 0.
+        {
+            direction
+        } else {
+            perfect
+        }
+    }
 }
 
 #[cfg(test)]
 mod tests {
-    use std::{f64::consts::SQRT_2, rc::Rc};
+    use std::{f64::consts::SQRT_2, sync::Arc};
+
+    use rand::rngs::StdRng;
 
-    use crate::{math::matrix::Matrix4x4, model::material::Material, shapes::shape::Shape};
+    use crate::{
+        math::{colour::Colour, matrix::Matrix4x4, round::Round},
+        model::material::Material,
+        patterns::{bump::Bump, pattern::Pattern},
+        shapes::shape::Shape,
+    };
 
     use super::*;
 
+    #[test]
+    fn smooth_triangle_normal() -> () {
+        let s = Arc::new(Shape::id_smooth_triangle(
+            Point::new(0., 1., 0.),
+            Point::new(-1., 0., 0.),
+            Point::new(1., 0., 0.),
+            Vector::new(0., 1., 0.),
+            Vector::new(-1., 0., 0.),
+            Vector::new(1., 0., 0.),
+        ));
+        let r = Ray::new(Point::new(-0.2, 0.3, -3.), Vector::new(0., 0., 1.));
+        let i = Intersection::new_uv(s, 1., 0.45, 0.25);
+        let c = Comp::new(i.clone(), r, &vec![i]);
+        assert_eq!(c.normal.rounded(5), vec![-0.5547, 0.83205, 0., 0.]);
+    }
+
     #[test]
     fn under_point() -> () {
         let r = Ray::new(Point::new(0., 0., -5.), Vector::new(0., 0., 1.));
         let s = Shape::new_sphere(Matrix4x4::translation(0., 0., 1.))
             .unwrap()
             .material(Material::default().transparency(1.).refractive_index(1.));
-        let i = Intersection::new(Rc::new(s), 5.);
+        let i = Intersection::new(Arc::new(s), 5.);
         let c = Comp::new(i.clone(), r, &vec![i]);
         assert!(c.under_point.z > Comp::EPS / 2.);
         assert!(c.under_point.z > c.point.z);
@@ -71,7 +152,7 @@ mod tests {
         let s = Shape::id_plane();
         let s2 = SQRT_2 / 2.;
         let r = Ray::new(Point::new(0., 1., -1.), Vector::new(0., -s2, s2));
-        let i = Intersection::new(Rc::new(s), s2 * 2.);
+        let i = Intersection::new(Arc::new(s), s2 * 2.);
         let c = Comp::new(i.clone(), r, &vec![i]);
         assert_eq!(c.reflect, Vector::new(0., s2, s2));
     }
@@ -80,7 +161,7 @@ mod tests {
     fn over_point() -> () {
         let r = Ray::new(Point::new(0., 0., -5.), Vector::new(0., 0., 1.));
         let s = Shape::new_sphere(Matrix4x4::translation(0., 0., 1.)).unwrap_or(Shape::id_sphere());
-        let i = Intersection::new(Rc::new(s), 5.);
+        let i = Intersection::new(Arc::new(s), 5.);
         let c = Comp::new(i.clone(), r, &vec![i]);
         assert!(c.over_point.z < -Comp::EPS / 2.);
         assert!(c.point.z > c.over_point.z);
@@ -90,7 +171,7 @@ mod tests {
     fn inside() -> () {
         let r = Ray::new(Point::ORIGIN, Vector::new(0., 0., 1.));
         let s = Shape::id_sphere();
-        let i = Intersection::new(Rc::new(s), 1.);
+        let i = Intersection::new(Arc::new(s), 1.);
         let c = Comp::new(i.clone(), r, &vec![i]);
         assert_eq!(c.point, Point::new(0., 0., 1.));
         assert_eq!(c.eye, Vector::new(0., 0., -1.));
@@ -102,7 +183,7 @@ mod tests {
     fn not_inside() -> () {
         let r = Ray::new(Point::new(0., 0., -5.), Vector::new(0., 0., 1.));
         let s = Shape::id_sphere();
-        let i = Intersection::new(Rc::new(s), 4.);
+        let i = Intersection::new(Arc::new(s), 4.);
         let c = Comp::new(i.clone(), r, &vec![i]);
         assert!(!c.inside);
     }
@@ -110,8 +191,8 @@ mod tests {
     #[test]
     fn new() -> () {
         let r = Ray::new(Point::new(0., 0., -5.), Vector::new(0., 0., 1.));
-        let s = Rc::new(Shape::id_sphere());
-        let i = Intersection::new(Rc::clone(&s), 4.);
+        let s = Arc::new(Shape::id_sphere());
+        let i = Intersection::new(Arc::clone(&s), 4.);
         let it = i.t;
         let c = Comp::new(i.clone(), r, &vec![i]);
         assert_eq!(c.intersection.t, it);
@@ -120,4 +201,68 @@ mod tests {
         assert_eq!(c.eye, Vector::new(0., 0., -1.));
         assert_eq!(c.normal, Vector::new(0., 0., -1.));
     }
+
+    #[test]
+    fn reflected() -> () {
+        let s2 = SQRT_2 / 2.;
+        let r = Ray::new(Point::new(0., 1., -1.), Vector::new(0., -s2, s2));
+        let i = Intersection::new(Arc::new(Shape::id_plane()), s2 * 2.);
+        let c = Comp::new(i.clone(), r, &vec![i]);
+        let mut rng = sampling::pixel_rng(0, 0, 0);
+        assert_eq!(c.reflected(0., &mut rng), c.reflect);
+        let dirs: Vec<_> = (0..100).map(|_| c.reflected(0.5, &mut rng)).collect();
+        assert!(dirs.iter().all(|d| d.y > 0. && (d.len() - 1.).abs() < 1e-9));
+        assert!(dirs.iter().any(|d| *d != c.reflect));
+        // the rougher the further from the mirror
+        let mean = |roughness: f64, rng: &mut StdRng| {
+            (0..200)
+                .map(|_| c.reflected(roughness, rng).dot(c.reflect))
+                .sum::<f64>()
+                / 200.
+        };
+        assert!(mean(0.2, &mut rng) > mean(0.8, &mut rng));
+    }
+
+    #[test]
+    fn refracted() -> () {
+        let glass = Material::default().transparency(1.).refractive_index(1.5);
+        let s = Arc::new(Shape::id_plane().material(glass));
+        let r = Ray::new(Point::new(0., 1., -1.), Vector::new(0., -0.6, 0.8));
+        let i = Intersection::new(s, 1. / 0.6);
+        let c = Comp::new(i.clone(), r, &vec![i.clone(), i]);
+        let mut rng = sampling::pixel_rng(0, 0, 0);
+        let perfect = c.refracted(0., &mut rng);
+        assert_eq!(perfect.rounded(5), vec![0., -0.84591, 0.53333, 0.]);
+        let dirs: Vec<_> = (0..100).map(|_| c.refracted(0.5, &mut rng)).collect();
+        assert!(dirs.iter().all(|d| d.y < 0.));
+        assert!(dirs.iter().any(|d| *d != perfect));
+    }
+
+    #[test]
+    fn bumped_normal() -> () {
+        let bump = Bump::height(Pattern::id_gradient(Colour::BLACK, Colour::WHITE), 0.5);
+        let s = Shape::id_plane().material(Material::default().bump(bump));
+        let r = Ray::new(Point::new(0.5, 1., 0.3), Vector::new(0., -1., 0.));
+        let i = Intersection::new(Arc::new(s), 1.);
+        let c = Comp::new(i.clone(), r, &vec![i]);
+        assert_eq!(c.normal.rounded(5), vec![-0.44721, 0.89443, 0., 0.]);
+        assert_eq!(c.geometric_normal, Vector::new(0., 1., 0.));
+        // the offsets stay along the geometric normal
+        assert_eq!(c.over_point.rounded(5), vec![0.5, 0.00001, 0.3, 1.]);
+        assert_eq!(c.under_point.rounded(5), vec![0.5, -0.00001, 0.3, 1.]);
+        assert_eq!(c.reflect.rounded(5), vec![-0.8, 0.6, 0., 0.]);
+    }
+
+    #[test]
+    fn bumped_normal_inside() -> () {
+        let s = Shape::id_sphere()
+            .material(Material::default().bump(Bump::new_noise(0.1, 1.).unwrap()));
+        let r = Ray::new(Point::ORIGIN, Vector::new(0.48, 0.6, 0.64));
+        let i = Intersection::new(Arc::new(s), 1.);
+        let c = Comp::new(i.clone(), r, &vec![i]);
+        assert!(c.inside);
+        assert_eq!(c.geometric_normal.rounded(5), vec![-0.48, -0.6, -0.64, 0.]);
+        assert!(c.normal != c.geometric_normal);
+        assert!(c.normal.dot(c.eye) >= 0.);
+    }
 }
@@ -0,0 +1,31 @@
+use crate::math::{colour::Colour, vector::Vector};
+
+// infinitely far away like the sun, every ray of light is parallel
+#[derive(PartialEq, Debug, Copy, Clone)]
+pub struct DirectionalLight {
+    // where the light travels to, normalised
+    pub direction: Vector,
+    pub intensity: Colour,
+}
+
+impl DirectionalLight {
+    pub fn new(direction: Vector, intensity: Colour) -> Option<Self> {
+        (direction.len() > 0.).then(|| Self {
+            direction: direction.norm(),
+            intensity,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn new() -> () {
+        let l = DirectionalLight::new(Vector::new(0., -3., 0.), Colour::WHITE).unwrap();
+        assert_eq!(l.direction, Vector::new(0., -1., 0.));
+        assert_eq!(l.intensity, Colour::WHITE);
+        assert!(DirectionalLight::new(Vector::new(0., 0., 0.), Colour::WHITE).is_none());
+    }
+}
@@ -1,25 +1,63 @@
-use std::rc::Rc;
+use std::sync::Arc;
 
-use crate::shapes::{intersect::Intersect, shape::Shape};
+use crate::shapes::{intersect::Intersect, shape::Shape, shape_kind::ShapeKind};
 
 use super::ray::Ray;
 
 #[derive(PartialEq, Debug, Clone)]
 pub struct Intersection {
-    pub shape: Rc<Shape>,
+    pub shape: Arc<Shape>,
     pub t: f64,
+    // where on the surface the hit happened, for shapes reporting it
+    pub uv: Option<(f64, f64)>,
+    // outermost csg the shape is part of, refraction treats it as a single solid
+    pub container: Option<Arc<Shape>>,
 }
 
 impl Intersection {
-    pub fn new(shape: Rc<Shape>, t: f64) -> Self {
-        Self { shape, t }
+    pub fn new(shape: Arc<Shape>, t: f64) -> Self {
+        Self {
+            shape,
+            t,
+            uv: None,
+            container: None,
+        }
     }
 
-    pub fn intersections(shape: Rc<Shape>, r: &Ray) -> Vec<Intersection> {
+    pub fn new_uv(shape: Arc<Shape>, t: f64, u: f64, v: f64) -> Self {
+        Self {
+            shape,
+            t,
+            uv: Some((u, v)),
+            container: None,
+        }
+    }
+
+    pub fn intersections(shape: Arc<Shape>, r: &Ray) -> Vec<Intersection> {
+        // children already carry the group's or csg's transform
+        match &shape.underlying {
+            ShapeKind::G(group) => return group.intersections(r),
+            ShapeKind::Csg(csg) => {
+                return csg
+                    .intersections(r)
+                    .into_iter()
+                    .map(|i| Intersection {
+                        container: Some(Arc::clone(&shape)),
+                        ..i
+                    })
+                    .collect()
+            }
+            _ => (),
+        }
         let t_ray = r.transform(shape.inv_t);
-        let ts = shape.underlying.intersect(&t_ray);
+        let ts = shape.underlying.intersect_uv(&t_ray);
         ts.iter()
-            .map(|t| Intersection::new(Rc::clone(&shape), *t))
+            .map(|(t, uv)| Intersection {
+                shape: Arc::clone(&shape),
+                t: *t,
+                uv: *uv,
+                container: None,
+            })
             .collect()
     }
 }
@@ -53,15 +91,53 @@ impl IntersectionHit for Vec<Intersection> {
 
 #[cfg(test)]
 mod tests {
-    use crate::math::{matrix::Matrix4x4, point::Point, vector::Vector};
+    use crate::{
+        math::{matrix::Matrix4x4, point::Point, vector::Vector},
+        shapes::csg::CsgOp,
+    };
 
     use super::*;
 
+    #[test]
+    fn intersections_smooth_triangle_uv() -> () {
+        let s = Arc::new(Shape::id_smooth_triangle(
+            Point::new(0., 1., 0.),
+            Point::new(-1., 0., 0.),
+            Point::new(1., 0., 0.),
+            Vector::new(0., 1., 0.),
+            Vector::new(-1., 0., 0.),
+            Vector::new(1., 0., 0.),
+        ));
+        let r = Ray::new(Point::new(-0.2, 0.3, -2.), Vector::new(0., 0., 1.));
+        let res = Intersection::intersections(s, &r);
+        assert_eq!(res.len(), 1);
+        let (u, v) = res[0].uv.unwrap();
+        assert_eq!(((u * 100.).round(), (v * 100.).round()), (45., 25.));
+    }
+
+    #[test]
+    fn intersections_csg_container() -> () {
+        let inner = Arc::new(Shape::id_csg(
+            CsgOp::Union,
+            Arc::new(Shape::id_sphere()),
+            Arc::new(Shape::id_cube()),
+        ));
+        let outer = Arc::new(Shape::id_csg(
+            CsgOp::Union,
+            inner,
+            Arc::new(Shape::new_sphere(Matrix4x4::translation(0., 0., 5.)).unwrap()),
+        ));
+        let r = Ray::new(Point::new(0., 0., -5.), Vector::new(0., 0., 1.));
+        let res = Intersection::intersections(Arc::clone(&outer), &r);
+        assert_eq!(res.len(), 4);
+        assert!(res.iter().all(|i| i.container == Some(Arc::clone(&outer))));
+    }
+
     #[test]
     fn intersections_translated_shape() -> () {
         let r = Ray::new(Point::new(0., 0., -5.), Vector::new(0., 0., 1.));
         let s = Shape::new_sphere(Matrix4x4::translation(5., 0., 0.)).unwrap_or(Shape::id_sphere());
-        let res = Intersection::intersections(Rc::new(s), &r);
+        let res = Intersection::intersections(Arc::new(s), &r);
         assert_eq!(res, vec![]);
     }
 
@@ -69,54 +145,55 @@ mod tests {
     fn intersections_scaled_shape() -> () {
         let r = Ray::new(Point::new(0., 0., -5.), Vector::new(0., 0., 1.));
         let s = Shape::new_sphere(Matrix4x4::scaling(2., 2., 2.)).unwrap_or(Shape::id_sphere());
-        let res = Intersection::intersections(Rc::new(s), &r);
+        let res = Intersection::intersections(Arc::new(s), &r);
         assert_eq!(res[0].t, 3.);
         assert_eq!(res[1].t, 7.);
     }
 
     #[test]
     fn hit_smallest_non_neg() -> () {
-        let s = Rc::new(Shape::id_sphere());
-        let i1 = Intersection::new(Rc::clone(&s), 5.);
-        let i2 = Intersection::new(Rc::clone(&s), 7.);
-        let i3 = Intersection::new(Rc::clone(&s), -3.);
-        let i4 = Intersection::new(Rc::clone(&s), 2.);
+        let s = Arc::new(Shape::id_sphere());
+        let i1 = Intersection::new(Arc::clone(&s), 5.);
+        let i2 = Intersection::new(Arc::clone(&s), 7.);
+        let i3 = Intersection::new(Arc::clone(&s), -3.);
+        let i4 = Intersection::new(Arc::clone(&s), 2.);
         let is = vec![i1, i2, i3, i4.clone()];
         assert_eq!(is.hit(), Some(i4));
     }
 
     #[test]
     fn hit_all_neg() -> () {
-        let s = Rc::new(Shape::id_sphere());
-        let i1 = Intersection::new(Rc::clone(&s), -1.);
-        let i2 = Intersection::new(Rc::clone(&s), -2.);
+        let s = Arc::new(Shape::id_sphere());
+        let i1 = Intersection::new(Arc::clone(&s), -1.);
+        let i2 = Intersection::new(Arc::clone(&s), -2.);
         let is = vec![i1, i2];
         assert_eq!(is.hit(), None);
     }
 
     #[test]
     fn hit_some_neg() -> () {
-        let s = Rc::new(Shape::id_sphere());
-        let i1 = Intersection::new(Rc::clone(&s), -1.);
-        let i2 = Intersection::new(Rc::clone(&s), 1.);
+        let s = Arc::new(Shape::id_sphere());
+        let i1 = Intersection::new(Arc::clone(&s), -1.);
+        let i2 = Intersection::new(Arc::clone(&s), 1.);
         let is = vec![i1, i2.clone()];
         assert_eq!(is.hit(), Some(i2));
     }
 
     #[test]
     fn hit_all_pos() -> () {
-        let s = Rc::new(Shape::id_sphere());
-        let i1 = Intersection::new(Rc::clone(&s), 1.);
-        let i2 = Intersection::new(Rc::clone(&s), 2.);
+        let s = Arc::new(Shape::id_sphere());
+        let i1 = Intersection::new(Arc::clone(&s), 1.);
+        let i2 = Intersection::new(Arc::clone(&s), 2.);
         let is = vec![i1.clone(), i2];
         assert_eq!(is.hit(), Some(i1));
     }
 
     #[test]
     fn new() -> () {
-        let s = Rc::new(Shape::id_sphere());
-        let i = Intersection::new(Rc::clone(&s), 0.);
+        let s = Arc::new(Shape::id_sphere());
+        let i = Intersection::new(Arc::clone(&s), 0.);
         assert_eq!(i.t, 0.);
         assert_eq!(i.shape, s);
+        assert_eq!(i.uv, None);
     }
 }
@@ -0,0 +1,245 @@
+use crate::math::{colour::Colour, point::Point, vector::Vector};
+
+use super::{
+    area_light::AreaLight, directional_light::DirectionalLight, point_light::PointLight,
+    spot_light::SpotLight,
+};
+
+#[derive(PartialEq, Debug, Copy, Clone)]
+pub enum Light {
+    Point(PointLight),
+    Area(AreaLight),
+    Directional(DirectionalLight),
+    Spot(SpotLight),
+}
+
+// light reaching a point from a single direction
+#[derive(PartialEq, Debug, Copy, Clone)]
+pub struct LightSample {
+    // from the lit point towards the light, normalised
+    pub direction: Vector,
+    // infinite for directional lights
+    pub distance: f64,
+    // after attenuation and falloff
+    pub intensity: Colour,
+}
+
+impl LightSample {
+    fn towards(p: Point, position: Point, intensity: Colour) -> Self {
+        let to_light = position - p;
+        Self {
+            direction: to_light.norm(),
+            distance: to_light.len(),
+            intensity,
+        }
+    }
+}
+
+// 1 / (constant + linear * d + quadratic * d²)
+#[derive(PartialEq, Debug, Copy, Clone)]
+pub struct Attenuation {
+    pub constant: f64,
+    pub linear: f64,
+    pub quadratic: f64,
+}
+
+impl Attenuation {
+    pub fn new(constant: f64, linear: f64, quadratic: f64) -> Self {
+        Self {
+            constant,
+            linear,
+            quadratic,
+        }
+    }
+
+    pub fn factor(&self, distance: f64) -> f64 {
+        let d = self.constant + self.linear * distance + self.quadratic * distance * distance;
+        if d > 0. {
+            (1. / d).min(1.)
+        } else {
+            1.
+        }
+    }
+}
+
+impl Light {
+    // unattenuated, used for the ambient term
+    pub fn intensity(&self) -> Colour {
+        match self {
+            Light::Point(l) => l.intensity,
+            Light::Area(l) => l.intensity,
+            Light::Directional(l) => l.intensity,
+            Light::Spot(l) => l.intensity,
+        }
+    }
+
+    // directions the light is sampled from when lighting p
+    pub fn samples(&self, p: Point) -> Vec<LightSample> {
+        let attenuated = |a: Option<Attenuation>, s: LightSample| LightSample {
+            intensity: s.intensity * a.map_or(1., |a| a.factor(s.distance)),
+            ..s
+        };
+        match self {
+            Light::Point(l) => vec![attenuated(
+                l.attenuation,
+                LightSample::towards(p, l.position, l.intensity),
+            )],
+            Light::Area(l) => l
+                .points(p)
+                .into_iter()
+                .map(|position| LightSample::towards(p, position, l.intensity))
+                .collect(),
+            Light::Directional(l) => vec![LightSample {
+                direction: -l.direction,
+                distance: f64::INFINITY,
+                intensity: l.intensity,
+            }],
+            Light::Spot(l) => {
+                let s = LightSample::towards(p, l.position, l.intensity);
+                let falloff = l.falloff(-s.direction);
+                vec![attenuated(
+                    l.attenuation,
+                    LightSample {
+                        intensity: s.intensity * falloff,
+                        ..s
+                    },
+                )]
+            }
+        }
+    }
+}
+
+impl From<PointLight> for Light {
+    fn from(l: PointLight) -> Self {
+        Light::Point(l)
+    }
+}
+
+impl From<AreaLight> for Light {
+    fn from(l: AreaLight) -> Self {
+        Light::Area(l)
+    }
+}
+
+impl From<DirectionalLight> for Light {
+    fn from(l: DirectionalLight) -> Self {
+        Light::Directional(l)
+    }
+}
+
+impl From<SpotLight> for Light {
+    fn from(l: SpotLight) -> Self {
+        Light::Spot(l)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::f64::consts::FRAC_PI_4;
+
+    use crate::math::round::Round;
+
+    use super::*;
+
+    #[test]
+    fn samples_point() -> () {
+        let p = Point::new(0., 0., 3.);
+        let point = Light::from(PointLight::new(p, Colour::WHITE));
+        assert_eq!(
+            point.samples(Point::ORIGIN),
+            vec![LightSample {
+                direction: Vector::new(0., 0., 1.),
+                distance: 3.,
+                intensity: Colour::WHITE,
+            }]
+        );
+        assert_eq!(point.intensity(), Colour::WHITE);
+
+        let attenuated = Light::from(
+            PointLight::new(p, Colour::WHITE).attenuation(Attenuation::new(1., 0., 1.)),
+        );
+        assert_eq!(
+            attenuated.samples(Point::ORIGIN)[0].intensity,
+            Colour::new(0.1, 0.1, 0.1)
+        );
+        assert_eq!(attenuated.intensity(), Colour::WHITE);
+    }
+
+    #[test]
+    fn samples_area() -> () {
+        let area = AreaLight::new(
+            Point::ORIGIN,
+            Vector::new(1., 0., 0.),
+            2,
+            Vector::new(0., 1., 0.),
+            3,
+            Colour::new(0.5, 0.5, 0.5),
+        )
+        .unwrap();
+        let samples = Light::from(area).samples(Point::new(1., 2., 3.));
+        assert_eq!(samples.len(), 6);
+        assert!(samples
+            .iter()
+            .all(|s| s.intensity == area.intensity && s.direction.z < 0.));
+    }
+
+    #[test]
+    fn samples_directional() -> () {
+        let sun =
+            Light::from(DirectionalLight::new(Vector::new(0., -2., 0.), Colour::WHITE).unwrap());
+        vec![Point::ORIGIN, Point::new(100., -3., 7.)]
+            .iter()
+            .for_each(|p| {
+                assert_eq!(
+                    sun.samples(*p),
+                    vec![LightSample {
+                        direction: Vector::new(0., 1., 0.),
+                        distance: f64::INFINITY,
+                        intensity: Colour::WHITE,
+                    }]
+                );
+            });
+    }
+
+    #[test]
+    fn samples_spot() -> () {
+        let spot = Light::from(
+            SpotLight::new(
+                Point::new(0., 10., 0.),
+                Vector::new(0., -1., 0.),
+                FRAC_PI_4 / 2.,
+                FRAC_PI_4,
+                Colour::WHITE,
+            )
+            .unwrap(),
+        );
+        vec![
+            (Point::ORIGIN, 1.),
+            (Point::new(2., 0., 0.), 1.),
+            (Point::new(20., 0., 0.), 0.),
+            (Point::new(0., 20., 0.), 0.),
+        ]
+        .iter()
+        .for_each(|(p, exp)| {
+            let s = spot.samples(*p)[0];
+            assert_eq!(s.intensity.rounded(5), vec![*exp; 3]);
+        });
+        // between the inner and outer cones
+        let s = spot.samples(Point::new(10. * (3. * FRAC_PI_4 / 4.).tan(), 0., 0.))[0];
+        assert_eq!(s.intensity.rounded(5), vec![0.60975; 3]);
+    }
+
+    #[test]
+    fn attenuation_factor() -> () {
+        vec![
+            (Attenuation::new(1., 0., 0.), 10., 1.),
+            (Attenuation::new(1., 1., 0.), 1., 0.5),
+            (Attenuation::new(0., 0., 1.), 2., 0.25),
+            // never brighter than the light itself
+            (Attenuation::new(0., 0., 1.), 0.5, 1.),
+            (Attenuation::new(0., 0., 0.), 3., 1.),
+        ]
+        .iter()
+        .for_each(|(a, d, exp)| assert_eq!(a.factor(*d), *exp));
+    }
+}
@@ -1,24 +1,34 @@
-use std::rc::Rc;
+use std::sync::Arc;
 
 use crate::{
     math::{colour::Colour, point::Point, vector::Vector},
-    patterns::pattern::Pattern,
+    patterns::{bump::Bump, pattern::Pattern},
     shapes::shape::Shape,
 };
 
-use super::point_light::PointLight;
+use super::{light::Light, pbr::Pbr};
 
 #[derive(PartialEq, Debug, Clone)]
 pub struct Material {
     pub colour: Colour,
     ambient: f64,
-    diffuse: f64,
+    pub diffuse: f64,
     specular: f64,
     shininess: f64,
     pub reflective: f64,
     pub transparency: f64,
     pub refractive_index: f64,
+    // per unit of distance travelled inside, by channel, thick glass gets darker
+    pub absorption: Colour,
+    // spreads reflected and refracted rays, 0 for mirrors and clear glass
+    pub roughness: f64,
     pattern: Option<Pattern>,
+    // applied to the shading normal only
+    pub bump: Option<Bump>,
+    // light given off by the surface itself, lights the scene when path tracing
+    pub emissive: Colour,
+    // replaces the phong diffuse, specular and reflective when set
+    pub pbr: Option<Pbr>,
 }
 
 impl Material {
@@ -32,40 +42,59 @@ impl Material {
             reflective: 0.,
             transparency: 0.,
             refractive_index: 1.,
+            absorption: Colour::BLACK,
+            roughness: 0.,
             pattern: None,
+            bump: None,
+            emissive: Colour::BLACK,
+            pbr: None,
         }
     }
 
     // https://en.wikipedia.org/wiki/Phong_reflection_model
+    // intensity is the fraction of the light reaching p, 0 when fully in shadow
     pub fn lightning(
         &self,
-        shape: Rc<Shape>,
-        light: PointLight,
+        shape: Arc<Shape>,
+        light: Light,
         p: Point,
         eye: Vector,
         normal: Vector,
-        in_shadow: bool,
+        intensity: f64,
     ) -> Colour {
-        let colour = match &self.pattern {
-            Some(pat) => pat.at_shape(shape, p),
-            None => self.colour,
-        };
-
-        let effective_colour = colour * light.intensity;
+        let ambient = self.colour_at(Arc::clone(&shape), p) * light.intensity() * self.ambient;
+        ambient + self.direct(shape, light, p, eye, normal, intensity)
+    }
 
-        let ambient = effective_colour * self.ambient;
-        if in_shadow {
-            ambient
-        } else {
+    // lightning without the ambient term, path tracing computes the indirect light instead
+    pub fn direct(
+        &self,
+        shape: Arc<Shape>,
+        light: Light,
+        p: Point,
+        eye: Vector,
+        normal: Vector,
+        intensity: f64,
+    ) -> Colour {
+        if intensity <= 0. {
+            return Colour::BLACK;
+        }
+        let colour = self.colour_at(shape, p);
+        // diffuse and specular are averaged over the light's samples
+        let samples = light.samples(p);
+        let sum = samples.iter().fold(Colour::BLACK, |acc, sample| {
             let mut diffuse = Colour::BLACK;
             let mut specular = Colour::BLACK;
 
-            let light_norm = (light.position - p).norm();
+            let light_norm = sample.direction;
+            if let Some(pbr) = &self.pbr {
+                return acc + sample.intensity * pbr.reflected(colour, normal, light_norm, eye);
+            }
             let light_normal_cos = light_norm.dot(normal);
 
             // light and normal are on the same side
             if light_normal_cos >= 0. {
-                diffuse = effective_colour * self.diffuse * light_normal_cos;
+                diffuse = colour * sample.intensity * self.diffuse * light_normal_cos;
 
                 let light_normal_reflected = (-light_norm).reflect(normal);
                 let reflect_eye_cos = light_normal_reflected.dot(eye);
@@ -75,25 +104,22 @@ impl Material {
                     specular = Colour::BLACK;
                 } else {
                     let factor = reflect_eye_cos.powf(self.shininess);
-                    specular = light.intensity * self.specular * factor;
+                    specular = sample.intensity * self.specular * factor;
                 }
             }
 
-            ambient + diffuse + specular
-        }
+            acc + diffuse + specular
+        });
 
-        // specular
-        //let light_reflected_v = (-light_norm).reflect(normal);
-        //let reflected_eye_cos = light_reflected_v.dot(eye);
-        //let specular = if reflected_eye_cos <= 0. {
-        //    Colour::BLACK
-        //} else {
-        //    effective_colour * self.specular * reflected_eye_cos.powf(self.shininess)
-        //};
+        sum * (intensity / samples.len() as f64)
+    }
 
-        //// diffuse
-        //let light_normal_cos = light_norm.dot(normal);
-        //let diffuse = effective_colour * self.diffuse * light_normal_cos;
+    // the pattern at p if there's one
+    pub fn colour_at(&self, shape: Arc<Shape>, p: Point) -> Colour {
+        match &self.pattern {
+            Some(pat) => pat.at_shape(shape, p),
+            None => self.colour,
+        }
     }
 
     pub fn colour(mut self, c: Colour) -> Self {
@@ -136,10 +162,40 @@ impl Material {
         self
     }
 
+    pub fn absorption(mut self, a: Colour) -> Self {
+        self.absorption = a.map(f64::abs);
+        self
+    }
+
+    // the pbr model shares it
+    pub fn roughness(mut self, r: f64) -> Self {
+        self.roughness = r.clamp(0., 1.);
+        if let Some(pbr) = self.pbr {
+            self.pbr = Some(Pbr::new(pbr.metallic, self.roughness));
+        }
+        self
+    }
+
     pub fn pattern(mut self, p: Pattern) -> Self {
         self.pattern = Some(p);
         self
     }
+
+    pub fn emissive(mut self, c: Colour) -> Self {
+        self.emissive = c;
+        self
+    }
+
+    pub fn bump(mut self, b: Bump) -> Self {
+        self.bump = Some(b);
+        self
+    }
+
+    pub fn pbr(mut self, p: Pbr) -> Self {
+        self.pbr = Some(p);
+        self.roughness = p.roughness;
+        self
+    }
 }
 
 impl Default for Material {
@@ -153,7 +209,12 @@ impl Default for Material {
             reflective: 0.,
             transparency: 0.,
             refractive_index: 1.,
+            absorption: Colour::BLACK,
+            roughness: 0.,
             pattern: None,
+            bump: None,
+            emissive: Colour::BLACK,
+            pbr: None,
         }
     }
 }
@@ -162,13 +223,16 @@ impl Default for Material {
 mod tests {
     use std::f64::consts::SQRT_2;
 
-    use crate::math::round::Round;
+    use crate::{
+        math::round::Round,
+        model::{area_light::AreaLight, point_light::PointLight},
+    };
 
     use super::*;
 
     #[test]
     fn lightning_with_pattern() -> () {
-        let s = Rc::new(Shape::id_sphere());
+        let s = Arc::new(Shape::id_sphere());
         let m = Material::default()
             .ambient(1.)
             .diffuse(0.)
@@ -176,23 +240,23 @@ mod tests {
             .pattern(Pattern::id_stripe(Colour::WHITE, Colour::BLACK));
         let eye = Vector::new(0., 0., -1.);
         let normal = Vector::new(0., 0., -1.);
-        let light = PointLight::new(Point::new(0., 0., -10.), Colour::WHITE);
+        let light = Light::from(PointLight::new(Point::new(0., 0., -10.), Colour::WHITE));
         let c1 = m.lightning(
-            Rc::clone(&s),
+            Arc::clone(&s),
             light,
             Point::new(0.9, 0., 0.),
             eye,
             normal,
-            false,
+            1.,
         );
         assert_eq!(c1, Colour::WHITE);
         let c2 = m.lightning(
-            Rc::clone(&s),
+            Arc::clone(&s),
             light,
             Point::new(1.1, 0., 0.),
             eye,
             normal,
-            false,
+            1.,
         );
         assert_eq!(c2, Colour::BLACK);
     }
@@ -203,8 +267,8 @@ mod tests {
         let p = Point::ORIGIN;
         let eye = Vector::new(0., 0., -1.);
         let normal = Vector::new(0., 0., -1.);
-        let light = PointLight::new(Point::new(0., 0., -10.), Colour::WHITE);
-        let res = m.lightning(Rc::new(Shape::id_sphere()), light, p, eye, normal, true);
+        let light = Light::from(PointLight::new(Point::new(0., 0., -10.), Colour::WHITE));
+        let res = m.lightning(Arc::new(Shape::id_sphere()), light, p, eye, normal, 0.);
         assert_eq!(res, Colour::new(0.1, 0.1, 0.1));
     }
 
@@ -214,8 +278,8 @@ mod tests {
         let p = Point::ORIGIN;
         let eye = Vector::new(0., 0., -1.);
         let normal = Vector::new(0., 0., -1.);
-        let light = PointLight::new(Point::new(0., 0., -10.), Colour::WHITE);
-        let res = m.lightning(Rc::new(Shape::id_sphere()), light, p, eye, normal, false);
+        let light = Light::from(PointLight::new(Point::new(0., 0., -10.), Colour::WHITE));
+        let res = m.lightning(Arc::new(Shape::id_sphere()), light, p, eye, normal, 1.);
         assert_eq!(res.rounded(5), vec![1.9, 1.9, 1.9]);
     }
 
@@ -226,8 +290,8 @@ mod tests {
         let s2 = SQRT_2 / 2.;
         let eye = Vector::new(0., s2, -s2);
         let normal = Vector::new(0., 0., -1.);
-        let light = PointLight::new(Point::new(0., 0., -10.), Colour::WHITE);
-        let res = m.lightning(Rc::new(Shape::id_sphere()), light, p, eye, normal, false);
+        let light = Light::from(PointLight::new(Point::new(0., 0., -10.), Colour::WHITE));
+        let res = m.lightning(Arc::new(Shape::id_sphere()), light, p, eye, normal, 1.);
         assert_eq!(res, Colour::WHITE);
     }
 
@@ -237,8 +301,8 @@ mod tests {
         let p = Point::ORIGIN;
         let eye = Vector::new(0., 0., -1.);
         let normal = eye;
-        let light = PointLight::new(Point::new(0., 10., -10.), Colour::WHITE);
-        let res = m.lightning(Rc::new(Shape::id_sphere()), light, p, eye, normal, false);
+        let light = Light::from(PointLight::new(Point::new(0., 10., -10.), Colour::WHITE));
+        let res = m.lightning(Arc::new(Shape::id_sphere()), light, p, eye, normal, 1.);
         assert_eq!(res.rounded(4), vec![0.7364, 0.7364, 0.7364]);
     }
 
@@ -249,8 +313,8 @@ mod tests {
         let s2 = SQRT_2 / 2.;
         let eye = Vector::new(0., -s2, -s2);
         let normal = Vector::new(0., 0., -1.);
-        let light = PointLight::new(Point::new(0., 10., -10.), Colour::WHITE);
-        let res = m.lightning(Rc::new(Shape::id_sphere()), light, p, eye, normal, false);
+        let light = Light::from(PointLight::new(Point::new(0., 10., -10.), Colour::WHITE));
+        let res = m.lightning(Arc::new(Shape::id_sphere()), light, p, eye, normal, 1.);
         assert_eq!(res.rounded(4), vec![1.6364, 1.6364, 1.6364]);
     }
 
@@ -260,11 +324,128 @@ mod tests {
         let p = Point::ORIGIN;
         let eye = Vector::new(0., 0., -1.);
         let normal = Vector::new(0., 0., -1.);
-        let light = PointLight::new(Point::new(0., 0., 10.), Colour::WHITE);
-        let res = m.lightning(Rc::new(Shape::id_sphere()), light, p, eye, normal, false);
+        let light = Light::from(PointLight::new(Point::new(0., 0., 10.), Colour::WHITE));
+        let res = m.lightning(Arc::new(Shape::id_sphere()), light, p, eye, normal, 1.);
         assert_eq!(res.rounded(5), vec![0.1, 0.1, 0.1]);
     }
 
+    #[test]
+    fn lightning_intensity() -> () {
+        let m = Material::default();
+        let p = Point::ORIGIN;
+        let eye = Vector::new(0., 0., -1.);
+        let normal = Vector::new(0., 0., -1.);
+        let light = Light::from(PointLight::new(Point::new(0., 0., -10.), Colour::WHITE));
+        vec![(1., 1.9), (0.5, 1.), (0., 0.1)]
+            .iter()
+            .for_each(|(intensity, exp)| {
+                let res = m.lightning(
+                    Arc::new(Shape::id_sphere()),
+                    light,
+                    p,
+                    eye,
+                    normal,
+                    *intensity,
+                );
+                assert_eq!(res.rounded(5), vec![*exp; 3]);
+            });
+    }
+
+    #[test]
+    fn direct() -> () {
+        let m = Material::default();
+        let eye = Vector::new(0., 0., -1.);
+        let normal = Vector::new(0., 0., -1.);
+        let light = Light::from(PointLight::new(Point::new(0., 0., -10.), Colour::WHITE));
+        let s = Arc::new(Shape::id_sphere());
+        // no ambient, even in shadow
+        vec![(1., 1.8), (0.5, 0.9), (0., 0.)]
+            .iter()
+            .for_each(|(intensity, exp)| {
+                let res = m.direct(
+                    Arc::clone(&s),
+                    light,
+                    Point::ORIGIN,
+                    eye,
+                    normal,
+                    *intensity,
+                );
+                assert_eq!(res.rounded(5), vec![*exp; 3]);
+            });
+    }
+
+    #[test]
+    fn direct_pbr() -> () {
+        let m = Material::default().pbr(Pbr::new(0., 1.)).diffuse(0.);
+        let eye = Vector::new(0., 0., -1.);
+        let normal = Vector::new(0., 0., -1.);
+        let light = Light::from(PointLight::new(Point::new(0., 0., -10.), Colour::WHITE));
+        let s = Arc::new(Shape::id_sphere());
+        // phong's diffuse and specular are ignored, the ambient is kept
+        vec![(1., 0.97), (0.5, 0.485), (0., 0.)]
+            .iter()
+            .for_each(|(intensity, exp)| {
+                let res = m.direct(
+                    Arc::clone(&s),
+                    light,
+                    Point::ORIGIN,
+                    eye,
+                    normal,
+                    *intensity,
+                );
+                assert_eq!(res.rounded(5), vec![*exp; 3]);
+            });
+        let res = m.lightning(s, light, Point::ORIGIN, eye, normal, 1.);
+        assert_eq!(res.rounded(5), vec![1.07; 3]);
+    }
+
+    #[test]
+    fn colour_at() -> () {
+        let s = Arc::new(Shape::id_sphere());
+        let plain = Material::default().colour(Colour::new(1., 0., 0.));
+        assert_eq!(
+            plain.colour_at(Arc::clone(&s), Point::ORIGIN),
+            Colour::new(1., 0., 0.)
+        );
+        let striped = plain.pattern(Pattern::id_stripe(Colour::WHITE, Colour::BLACK));
+        assert_eq!(
+            striped.colour_at(Arc::clone(&s), Point::new(1.5, 0., 0.)),
+            Colour::BLACK
+        );
+    }
+
+    #[test]
+    fn lightning_area_light() -> () {
+        let light = Light::from(
+            AreaLight::new(
+                Point::new(-0.5, -0.5, -5.),
+                Vector::new(1., 0., 0.),
+                2,
+                Vector::new(0., 1., 0.),
+                2,
+                Colour::WHITE,
+            )
+            .unwrap(),
+        );
+        let m = Material::default()
+            .ambient(0.1)
+            .diffuse(0.9)
+            .specular(0.)
+            .colour(Colour::WHITE);
+        let eye = Point::new(0., 0., -5.);
+        vec![
+            (Point::new(0., 0., -1.), 0.9965),
+            (Point::new(0., 0.7071, -0.7071), 0.6232),
+        ]
+        .iter()
+        .for_each(|(p, exp)| {
+            let eye_v = (eye - *p).norm();
+            let normal = Vector::new(p.x, p.y, p.z);
+            let res = m.lightning(Arc::new(Shape::id_sphere()), light, *p, eye_v, normal, 1.);
+            assert_eq!(res.rounded(4), vec![*exp; 3]);
+        });
+    }
+
     #[test]
     fn refractive_index() -> () {
         let m = Material::default();
@@ -292,6 +473,27 @@ mod tests {
         assert_eq!(new_m.reflective, r);
     }
 
+    #[test]
+    fn absorption() -> () {
+        let m = Material::default();
+        assert_eq!(m.absorption, Colour::BLACK);
+        let new_m = m.absorption(Colour::new(-1., 0.5, 0.));
+        assert_eq!(new_m.absorption, Colour::new(1., 0.5, 0.));
+    }
+
+    #[test]
+    fn roughness() -> () {
+        let m = Material::default();
+        assert_eq!(m.roughness, 0.);
+        assert_eq!(m.clone().roughness(2.).roughness, 1.);
+        // kept the same as the pbr one
+        let pbr = m.pbr(Pbr::new(1., 0.3));
+        assert_eq!(pbr.roughness, 0.3);
+        let rough = pbr.roughness(0.6);
+        assert_eq!(rough.pbr, Some(Pbr::new(1., 0.6)));
+        assert_eq!(rough.roughness, 0.6);
+    }
+
     #[test]
     fn shininess() -> () {
         let m = Material::default();
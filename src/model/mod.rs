@@ -1,8 +1,17 @@
+pub mod area_light;
+pub mod background;
+pub mod bvh;
 pub mod camera;
 pub mod comp;
+pub mod directional_light;
 pub mod intersection;
+pub mod light;
 pub mod material;
+pub mod pbr;
 pub mod point_light;
 pub mod ray;
 pub mod refractive_indices;
+pub mod render_settings;
+pub mod sampling;
+pub mod spot_light;
 pub mod world;
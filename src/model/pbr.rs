@@ -0,0 +1,202 @@
+use std::f64::consts::PI;
+
+use rand::Rng;
+
+use crate::math::{colour::Colour, vector::Vector};
+
+use super::sampling;
+
+// metal / roughness model, the base colour is the material's colour or pattern
+// https://learnopengl.com/PBR/Theory
+#[derive(PartialEq, Debug, Copy, Clone)]
+pub struct Pbr {
+    // 0 for dielectrics, 1 for metals
+    pub metallic: f64,
+    // 0 is a perfect mirror, squared into the ggx alpha
+    pub roughness: f64,
+}
+
+impl Pbr {
+    // reflectance of dielectrics seen straight on
+    const DIELECTRIC_F0: f64 = 0.04;
+    // ggx degenerates on perfectly smooth surfaces
+    const MIN_ALPHA: f64 = 0.001;
+
+    pub fn new(metallic: f64, roughness: f64) -> Self {
+        Self {
+            metallic: metallic.clamp(0., 1.),
+            roughness: roughness.clamp(0., 1.),
+        }
+    }
+
+    fn alpha(&self) -> f64 {
+        (self.roughness * self.roughness).max(Self::MIN_ALPHA)
+    }
+
+    // fresnel-schlick, cos is between the eye and the (micro) surface normal
+    pub fn fresnel(&self, base: Colour, cos: f64) -> Colour {
+        let dielectric = Colour::new(1., 1., 1.) * Self::DIELECTRIC_F0;
+        let f0 = dielectric * (1. - self.metallic) + base * self.metallic;
+        f0 + (Colour::WHITE - f0) * (1. - cos.clamp(0., 1.)).powi(5)
+    }
+
+    // ggx / trowbridge-reitz, how many microfacets face along the half vector
+    pub fn distribution(&self, n_h: f64) -> f64 {
+        let a2 = self.alpha() * self.alpha();
+        let d = n_h * n_h * (a2 - 1.) + 1.;
+        a2 / (PI * d * d)
+    }
+
+    // smith with schlick-ggx, how many of those aren't hidden by the others
+    pub fn geometry(&self, n_l: f64, n_v: f64) -> f64 {
+        let k = self.alpha() / 2.;
+        let g1 = |cos: f64| cos / (cos * (1. - k) + k);
+        g1(n_l) * g1(n_v)
+    }
+
+    // light and eye go away from the surface, includes the cosine of the light
+    // scaled by pi so that a white dielectric lights like a phong diffuse of 1
+    pub fn reflected(&self, base: Colour, normal: Vector, light: Vector, eye: Vector) -> Colour {
+        let n_l = normal.dot(light);
+        let n_v = normal.dot(eye);
+        if n_l <= 0. || n_v <= 0. {
+            return Colour::BLACK;
+        }
+        let h = (light + eye).norm();
+        let f = self.fresnel(base, eye.dot(h));
+        let specular =
+            f * (self.distribution(normal.dot(h)) * self.geometry(n_l, n_v) / (4. * n_l * n_v));
+        let diffuse = (Colour::WHITE - f) * base * ((1. - self.metallic) / PI);
+        (diffuse + specular) * (PI * n_l)
+    }
+
+    // a direction around the mirror one, spread by roughness, and how much light it brings
+    // microfacet normals are drawn following the distribution
+    pub fn sample_reflection<R: Rng>(
+        &self,
+        base: Colour,
+        normal: Vector,
+        eye: Vector,
+        rng: &mut R,
+    ) -> (Vector, Colour) {
+        let h = sampling::ggx_normal(normal, self.alpha(), rng);
+        let v_h = eye.dot(h);
+        let light = h * (2. * v_h) - eye;
+        let (n_l, n_v, n_h) = (normal.dot(light), normal.dot(eye), normal.dot(h));
+        if n_l <= 0. || n_v <= 0. || v_h <= 0. {
+            return (light, Colour::BLACK);
+        }
+        let weight = self.geometry(n_l, n_v) * v_h / (n_v * n_h);
+        (light, self.fresnel(base, v_h) * weight)
+    }
+}
+
+impl Default for Pbr {
+    fn default() -> Self {
+        Self::new(0., 0.5)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::f64::consts::SQRT_2;
+
+    use crate::{math::round::Round, model::sampling::pixel_rng};
+
+    use super::*;
+
+    #[test]
+    fn new() -> () {
+        assert_eq!(Pbr::new(2., -1.), Pbr::new(1., 0.));
+        assert_eq!(Pbr::default(), Pbr::new(0., 0.5));
+    }
+
+    #[test]
+    fn fresnel() -> () {
+        let gold = Colour::new(1., 0.8, 0.3);
+        vec![
+            (Pbr::new(0., 0.5), 1., vec![0.04; 3]),
+            (Pbr::new(1., 0.5), 1., vec![1., 0.8, 0.3]),
+            (Pbr::new(0.5, 0.5), 1., vec![0.52, 0.42, 0.17]),
+            // everything reflects at grazing angles
+            (Pbr::new(0., 0.5), 0., vec![1.; 3]),
+            (Pbr::new(1., 0.5), 0., vec![1.; 3]),
+        ]
+        .iter()
+        .for_each(|(p, cos, exp)| assert_eq!(p.fresnel(gold, *cos).rounded(5), *exp));
+    }
+
+    #[test]
+    fn distribution() -> () {
+        let rough = Pbr::new(0., 1.);
+        // alpha of 1 spreads the microfacets uniformly
+        vec![1., 0.5, 0.].iter().for_each(|n_h| {
+            assert_eq!(
+                vec![rough.distribution(*n_h)].rounded(5),
+                vec![1. / PI].rounded(5)
+            )
+        });
+        let smooth = Pbr::new(0., 0.2);
+        assert!(smooth.distribution(1.) > rough.distribution(1.));
+        assert!(smooth.distribution(0.5) < rough.distribution(0.5));
+    }
+
+    #[test]
+    fn geometry() -> () {
+        let p = Pbr::new(0., 0.5);
+        assert_eq!(vec![p.geometry(1., 1.)].rounded(5), vec![1.]);
+        assert!(p.geometry(0.1, 1.) < p.geometry(0.5, 1.));
+        assert_eq!(p.geometry(0., 1.), 0.);
+    }
+
+    #[test]
+    fn reflected() -> () {
+        let n = Vector::new(0., 1., 0.);
+        let eye = Vector::new(0., 1., 0.);
+        let white = Colour::WHITE;
+        // behind the surface
+        let p = Pbr::new(0., 0.5);
+        assert_eq!(
+            p.reflected(white, n, Vector::new(0., -1., 0.), eye),
+            Colour::BLACK
+        );
+        // a rough white dielectric is mostly diffuse, straight on fresnel takes a little
+        let rough = Pbr::new(0., 1.).reflected(white, n, n, eye);
+        assert_eq!(rough.rounded(5), vec![0.97; 3]);
+        // metals have no diffuse, away from the highlight they go dark
+        let metal = Pbr::new(1., 0.2);
+        let s2 = SQRT_2 / 2.;
+        let light = Vector::new(s2, s2, 0.);
+        let highlight = metal.reflected(white, n, light, Vector::new(-s2, s2, 0.));
+        let off = metal.reflected(white, n, light, eye);
+        assert!(highlight.into_iter().all(|c| c > 1.));
+        assert!(off.into_iter().all(|c| c < 0.05));
+    }
+
+    #[test]
+    fn sample_reflection() -> () {
+        let n = Vector::new(0., 1., 0.);
+        let s2 = SQRT_2 / 2.;
+        let eye = Vector::new(-s2, s2, 0.);
+        let mirror = Vector::new(s2, s2, 0.);
+        let mut rng = pixel_rng(0, 0, 0);
+        // smooth metals reflect like mirrors
+        let (d, w) = Pbr::new(1., 0.).sample_reflection(Colour::WHITE, n, eye, &mut rng);
+        assert!(d.dot(mirror) > 0.999);
+        assert_eq!(w.rounded(2), vec![1.; 3]);
+        // rough ones spread the reflections around it
+        let rough = Pbr::new(1., 0.6);
+        let dirs: Vec<_> = (0..100)
+            .map(|_| rough.sample_reflection(Colour::WHITE, n, eye, &mut rng))
+            .filter(|(_, w)| *w != Colour::BLACK)
+            .map(|(d, _)| d)
+            .collect();
+        assert!(dirs.len() > 50);
+        assert!(dirs
+            .iter()
+            .all(|d| d.dot(n) > 0. && (d.len() - 1.).abs() < 1e-9));
+        assert!(dirs.iter().any(|d| d.dot(mirror) < 0.95));
+        let mean = dirs.iter().fold(Vector::new(0., 0., 0.), |acc, d| acc + *d);
+        assert!(mean.norm().dot(mirror) > 0.9);
+    }
+}
@@ -1,9 +1,13 @@
 use crate::math::{colour::Colour, point::Point};
 
+use super::light::Attenuation;
+
 #[derive(PartialEq, Debug, Copy, Clone)]
 pub struct PointLight {
     pub position: Point,
     pub intensity: Colour,
+    // none keeps the same intensity at any distance
+    pub attenuation: Option<Attenuation>,
 }
 
 impl PointLight {
@@ -11,8 +15,14 @@ impl PointLight {
         Self {
             position: p,
             intensity: i,
+            attenuation: None,
         }
     }
+
+    pub fn attenuation(mut self, a: Attenuation) -> Self {
+        self.attenuation = Some(a);
+        self
+    }
 }
 
 #[cfg(test)]
@@ -26,5 +36,6 @@ mod tests {
         let pl = PointLight::new(p, i);
         assert_eq!(pl.position, p);
         assert_eq!(pl.intensity, i);
+        assert_eq!(pl.attenuation, None);
     }
 }
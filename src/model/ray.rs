@@ -1,3 +1,5 @@
+use std::f64::consts::{FRAC_PI_2, FRAC_PI_4};
+
 use crate::math::{matrix::Matrix4x4, point::Point, vector::Vector};
 
 use super::camera::Camera;
@@ -14,13 +16,12 @@ impl Ray {
     }
 
     pub fn for_pixel(camera: &Camera, px: usize, py: usize) -> Self {
-        let pxf = px as f64;
-        let pyf = py as f64;
-        let x_offset = (pxf + 0.5) * camera.pixel_size;
-        let y_offset = (pyf + 0.5) * camera.pixel_size;
+        Self::for_pixel_offset(camera, px, py, 0.5, 0.5)
+    }
 
-        let world_x = camera.half_width - x_offset;
-        let world_y = camera.half_height - y_offset;
+    // ox and oy locate the sample inside the pixel, from 0 to 1, 0.5 being the centre
+    pub fn for_pixel_offset(camera: &Camera, px: usize, py: usize, ox: f64, oy: f64) -> Self {
+        let (world_x, world_y) = Self::canvas_point(camera, px, py, ox, oy);
 
         let pixel = camera.inv_t * Point::new(world_x, world_y, -1.);
         let origin = camera.inv_t * Point::ORIGIN;
@@ -28,6 +29,39 @@ impl Ray {
         Self::new(origin, direction)
     }
 
+    // lu and lv locate the ray's origin on the lens, from 0 to 1, 0.5 being the centre,
+    // every ray for a given pixel offset converges on the focal plane
+    pub fn for_pixel_lens(
+        camera: &Camera,
+        px: usize,
+        py: usize,
+        ox: f64,
+        oy: f64,
+        lu: f64,
+        lv: f64,
+    ) -> Self {
+        if camera.aperture <= 0. {
+            return Self::for_pixel_offset(camera, px, py, ox, oy);
+        }
+        let (world_x, world_y) = Self::canvas_point(camera, px, py, ox, oy);
+
+        // the canvas is at z = -1, scaling it moves it to the focal plane
+        let fd = camera.focal_distance;
+        let focus = camera.inv_t * Point::new(world_x * fd, world_y * fd, -fd);
+        let (dx, dy) = concentric_disc(lu, lv);
+        let radius = camera.aperture / 2.;
+        let origin = camera.inv_t * Point::new(dx * radius, dy * radius, 0.);
+        let direction = (focus - origin).norm();
+        Self::new(origin, direction)
+    }
+
+    // in camera space, on the canvas one unit in front of the eye
+    fn canvas_point(camera: &Camera, px: usize, py: usize, ox: f64, oy: f64) -> (f64, f64) {
+        let x_offset = (px as f64 + ox) * camera.pixel_size;
+        let y_offset = (py as f64 + oy) * camera.pixel_size;
+        (camera.half_width - x_offset, camera.half_height - y_offset)
+    }
+
     pub fn position(&self, t: f64) -> Point {
         self.origin + self.direction * t
     }
@@ -40,14 +74,99 @@ impl Ray {
     }
 }
 
+// Shirley's concentric mapping from the unit square to the unit disc, it keeps
+// evenly spread samples evenly spread and maps the centre to the centre
+fn concentric_disc(u: f64, v: f64) -> (f64, f64) {
+    let a = 2. * u - 1.;
+    let b = 2. * v - 1.;
+    if a == 0. && b == 0. {
+        return (0., 0.);
+    }
+    let (r, theta) = if a.abs() > b.abs() {
+        (a, FRAC_PI_4 * (b / a))
+    } else {
+        (b, FRAC_PI_2 - FRAC_PI_4 * (a / b))
+    };
+    (r * theta.cos(), r * theta.sin())
+}
+
 #[cfg(test)]
 mod tests {
-    use std::f64::consts::{FRAC_PI_2, FRAC_PI_4, SQRT_2};
+    use std::f64::consts::SQRT_2;
 
     use crate::math::round::Round;
 
     use super::*;
 
+    #[test]
+    fn for_pixel_offset() -> () {
+        let c = Camera::new(201, 101, FRAC_PI_2);
+        assert_eq!(
+            Ray::for_pixel_offset(&c, 100, 50, 0.5, 0.5),
+            Ray::for_pixel(&c, 100, 50)
+        );
+        let corner = Ray::for_pixel_offset(&c, 0, 0, 0., 0.);
+        assert_eq!(
+            corner.direction.rounded(5),
+            Vector::new(1., 101. / 201., -1.).norm().rounded(5)
+        );
+    }
+
+    #[test]
+    fn for_pixel_lens_pinhole() -> () {
+        let c = Camera::new(201, 101, FRAC_PI_2);
+        assert_eq!(
+            Ray::for_pixel_lens(&c, 10, 20, 0.3, 0.6, 0.1, 0.9),
+            Ray::for_pixel_offset(&c, 10, 20, 0.3, 0.6)
+        );
+    }
+
+    #[test]
+    fn for_pixel_lens() -> () {
+        let t = Matrix4x4::view_transform(
+            Point::new(0., 0., -5.),
+            Point::ORIGIN,
+            Vector::new(0., 1., 0.),
+        );
+        let c = Camera::new(201, 101, FRAC_PI_2)
+            .transform(t)
+            .unwrap()
+            .aperture(0.5)
+            .focal_distance(5.)
+            .unwrap();
+        let pinhole = Ray::for_pixel(&c, 100, 50);
+        let centre = Ray::for_pixel_lens(&c, 100, 50, 0.5, 0.5, 0.5, 0.5);
+        assert_eq!(centre.origin, pinhole.origin);
+        assert_eq!(centre.direction.rounded(5), pinhole.direction.rounded(5));
+        // rays leave from all over the lens and meet on the focal plane
+        vec![(0., 0.), (1., 0.5), (0.25, 0.9), (0.5, 1.)]
+            .iter()
+            .for_each(|(lu, lv)| {
+                let r = Ray::for_pixel_lens(&c, 100, 50, 0.5, 0.5, *lu, *lv);
+                assert_eq!(r.origin.z, -5.);
+                assert!((r.origin - pinhole.origin).len() <= 0.25 + 1e-9);
+                let t = (Point::ORIGIN - r.origin).len();
+                assert_eq!(r.position(t).rounded(5), Point::ORIGIN.rounded(5));
+            });
+        assert_ne!(
+            Ray::for_pixel_lens(&c, 100, 50, 0.5, 0.5, 0., 0.).origin,
+            pinhole.origin
+        );
+    }
+
+    #[test]
+    fn concentric_disc() -> () {
+        assert_eq!(super::concentric_disc(0.5, 0.5), (0., 0.));
+        vec![(0., 0.), (1., 0.), (0., 1.), (1., 1.), (0.5, 1.), (1., 0.3)]
+            .iter()
+            .for_each(|(u, v)| {
+                let (x, y) = super::concentric_disc(*u, *v);
+                assert_eq!(vec![(x * x + y * y).sqrt()].rounded(5), vec![1.]);
+            });
+        let (x, y) = super::concentric_disc(0.75, 0.5);
+        assert_eq!(vec![x, y].rounded(5), vec![0.5, 0.]);
+    }
+
     #[test]
     fn for_pixel_camera_transformed() -> () {
         let t = Matrix4x4::translation(0., -2., 5.).rotate_y(FRAC_PI_4);
@@ -1,6 +1,9 @@
-use std::rc::Rc;
+use std::sync::Arc;
 
-use crate::{math::vector::Vector, shapes::shape::Shape};
+use crate::{
+    math::{colour::Colour, vector::Vector},
+    shapes::shape::Shape,
+};
 
 use super::intersection::Intersection;
 
@@ -12,6 +15,8 @@ pub struct RefractiveIndices {
     pub cos1: f64,
     pub cos2: f64,
     sin2_2: f64,
+    // of the material the ray went through to reach the intersection, black outside of any
+    pub absorption: Colour,
 }
 
 impl RefractiveIndices {
@@ -23,39 +28,51 @@ impl RefractiveIndices {
             cos1: 0.,
             cos2: 0.,
             sin2_2: 0.,
+            absorption: Colour::BLACK,
         }
     }
 
     pub fn new(hit: &Intersection, is: &[Intersection]) -> Self {
         let mut n1 = 0.;
         let mut n2 = 0.;
-        let mut containers: Vec<Rc<Shape>> = Vec::new();
+        let mut absorption = Colour::BLACK;
+        // the solids the ray is in, a csg counts as one, with the shape it was entered through
+        // whose material fills it
+        let mut containers: Vec<(Arc<Shape>, Arc<Shape>)> = Vec::new();
+        let is_hit = |i: &Intersection| Arc::ptr_eq(&i.shape, &hit.shape) && i.t == hit.t;
         for i in is {
-            if i == hit {
+            if is_hit(i) {
                 match containers.last() {
                     None => n1 = 1.,
-                    Some(last) => n1 = last.material.refractive_index,
+                    Some((_, leaf)) => {
+                        n1 = leaf.material.refractive_index;
+                        absorption = leaf.material.absorption;
+                    }
                 };
             }
 
-            let i_shape = Rc::clone(&i.shape);
+            let solid = Arc::clone(i.container.as_ref().unwrap_or(&i.shape));
 
-            match containers.iter().position(|s| *s == i_shape) {
-                None => containers.push(i_shape),
+            // by identity, equal solids built separately are still different solids
+            match containers.iter().position(|(s, _)| Arc::ptr_eq(s, &solid)) {
+                None => containers.push((solid, Arc::clone(&i.shape))),
                 Some(idx) => {
                     containers.remove(idx);
                 }
             }
 
-            if i == hit {
+            if is_hit(i) {
                 match containers.last() {
                     None => n2 = 1.,
-                    Some(last) => n2 = last.material.refractive_index,
+                    Some((_, leaf)) => n2 = leaf.material.refractive_index,
                 };
                 break;
             }
         }
-        Self::from(n1, n2)
+        Self {
+            absorption,
+            ..Self::from(n1, n2)
+        }
     }
 
     // https://en.wikipedia.org/wiki/Snell%27s_law
@@ -71,6 +88,12 @@ impl RefractiveIndices {
         self
     }
 
+    // https://en.wikipedia.org/wiki/Beer%E2%80%93Lambert_law
+    // what's left of the light after distance through the material
+    pub fn transmittance(&self, distance: f64) -> Colour {
+        self.absorption.map(|a| (-a * distance).exp())
+    }
+
     pub fn total_internal_reflection(&self) -> bool {
         self.sin2_2 > 1.
     }
@@ -96,8 +119,9 @@ mod tests {
     use std::f64::consts::SQRT_2;
 
     use crate::{
-        math::{matrix::Matrix4x4, point::Point},
+        math::{matrix::Matrix4x4, point::Point, round::Round},
         model::{comp::Comp, material::Material, ray::Ray},
+        shapes::csg::CsgOp,
     };
 
     use super::*;
@@ -108,9 +132,9 @@ mod tests {
 
     #[test]
     fn reflectance_small_angle() -> () {
-        let s = Rc::new(glass());
+        let s = Arc::new(glass());
         let r = Ray::new(Point::new(0., 0.99, -2.), Vector::Z);
-        let is = vec![Intersection::new(Rc::clone(&s), 1.8589)];
+        let is = vec![Intersection::new(Arc::clone(&s), 1.8589)];
         let c = Comp::new(is[0].clone(), r, &is);
         let res = c.indices.reflectance();
         assert_eq!((res * 100000.).round(), 0.48873 * 100000.);
@@ -118,11 +142,11 @@ mod tests {
 
     #[test]
     fn reflectance_perpendicular() -> () {
-        let s = Rc::new(glass());
+        let s = Arc::new(glass());
         let r = Ray::new(Point::ORIGIN, Vector::Y);
         let is = vec![
-            Intersection::new(Rc::clone(&s), -1.),
-            Intersection::new(Rc::clone(&s), 1.),
+            Intersection::new(Arc::clone(&s), -1.),
+            Intersection::new(Arc::clone(&s), 1.),
         ];
         let c = Comp::new(is[1].clone(), r, &is);
         let res = c.indices.reflectance();
@@ -131,12 +155,12 @@ mod tests {
 
     #[test]
     fn reflectance_tir() -> () {
-        let s = Rc::new(glass());
+        let s = Arc::new(glass());
         let s2 = SQRT_2 / 2.;
         let r = Ray::new(Point::new(0., 0., s2), Vector::Y);
         let is = vec![
-            Intersection::new(Rc::clone(&s), -s2),
-            Intersection::new(Rc::clone(&s), s2),
+            Intersection::new(Arc::clone(&s), -s2),
+            Intersection::new(Arc::clone(&s), s2),
         ];
         let c = Comp::new(is[1].clone(), r, &is);
         let res = c.indices.reflectance();
@@ -156,6 +180,113 @@ mod tests {
         assert!(not_tir.sin2_2 < 1.);
     }
 
+    #[test]
+    fn absorption() -> () {
+        let tint = |c: Colour, n: f64| {
+            Material::default()
+                .transparency(1.)
+                .refractive_index(n)
+                .absorption(c)
+        };
+        let red = Colour::new(0., 1., 1.);
+        let blue = Colour::new(1., 1., 0.);
+        let a = Arc::new(
+            Shape::new_sphere(Matrix4x4::scaling(2., 2., 2.))
+                .unwrap()
+                .material(tint(red, 1.5)),
+        );
+        let b = Arc::new(
+            Shape::new_sphere(Matrix4x4::translation(0., 0., -0.25))
+                .unwrap()
+                .material(tint(blue, 2.)),
+        );
+        let r = Ray::new(Point::new(0., 0., -4.), Vector::new(0., 0., 1.));
+        let is = vec![
+            Intersection::new(Arc::clone(&a), 2.),
+            Intersection::new(Arc::clone(&b), 2.75),
+            Intersection::new(Arc::clone(&b), 4.75),
+            Intersection::new(Arc::clone(&a), 6.),
+        ];
+        vec![(0usize, Colour::BLACK), (1, red), (2, blue), (3, red)]
+            .iter()
+            .for_each(|(idx, exp)| {
+                let c = Comp::new(is[*idx].clone(), r, &is);
+                assert_eq!(c.indices.absorption, *exp);
+            });
+    }
+
+    #[test]
+    fn transmittance() -> () {
+        let indices = RefractiveIndices {
+            absorption: Colour::new(0., 1., 2.),
+            ..RefractiveIndices::from(1.5, 1.)
+        };
+        assert_eq!(indices.transmittance(0.), Colour::WHITE);
+        assert_eq!(
+            indices.transmittance(0.5).rounded(5),
+            vec![1., 0.60653, 0.36788]
+        );
+        // nothing is absorbed outside
+        assert_eq!(
+            RefractiveIndices::from(1., 1.5).transmittance(10.),
+            Colour::WHITE
+        );
+    }
+
+    #[test]
+    fn refractive_indices_csg_children() -> () {
+        // only the children are glass, the csg itself keeps the default material
+        let glass = |n: f64| {
+            Material::default()
+                .transparency(1.)
+                .refractive_index(n)
+                .absorption(Colour::new(0., n, 0.))
+        };
+        let csg = Arc::new(Shape::id_csg(
+            CsgOp::Union,
+            Arc::new(Shape::id_sphere().material(glass(1.5))),
+            Arc::new(
+                Shape::new_sphere(Matrix4x4::translation(0., 0., 1.5))
+                    .unwrap()
+                    .material(glass(2.)),
+            ),
+        ));
+        let r = Ray::new(Point::new(0., 0., -4.), Vector::new(0., 0., 1.));
+        let is = Intersection::intersections(Arc::clone(&csg), &r);
+        assert_eq!(is.len(), 2);
+        let enter = Comp::new(is[0].clone(), r, &is);
+        assert_eq!(enter.indices.ratio, 1. / 1.5);
+        assert_eq!(enter.indices.absorption, Colour::BLACK);
+        // leaves through the other child, still inside the material it came in through
+        let exit = Comp::new(is[1].clone(), r, &is);
+        assert_eq!(exit.indices.ratio, 1.5);
+        assert_eq!(exit.indices.absorption, Colour::new(0., 1.5, 0.));
+    }
+
+    #[test]
+    fn refractive_indices_equal_solids() -> () {
+        let a = Arc::new(glass());
+        let b = Arc::new(glass());
+        let r = Ray::new(Point::new(0., 0., -4.), Vector::new(0., 0., 1.));
+        let is = vec![
+            Intersection::new(Arc::clone(&a), 3.),
+            Intersection::new(Arc::clone(&b), 3.),
+            Intersection::new(Arc::clone(&a), 5.),
+            Intersection::new(Arc::clone(&b), 5.),
+        ];
+        vec![
+            (0usize, 1., 1.5),
+            (1, 1.5, 1.5),
+            (2, 1.5, 1.5),
+            (3, 1.5, 1.),
+        ]
+        .iter()
+        .for_each(|(idx, n1, n2)| {
+            let c = Comp::new(is[*idx].clone(), r, &is);
+            assert_eq!(c.indices.ratio, *n1 / *n2, "{idx}");
+        });
+    }
+
     #[test]
     fn refractive_indices() -> () {
         vec![
@@ -168,29 +299,29 @@ mod tests {
         ]
         .iter()
         .for_each(|(idx, n1, n2)| {
-            let a = Rc::new(
+            let a = Arc::new(
                 Shape::new_sphere(Matrix4x4::scaling(2., 2., 2.))
                     .unwrap()
                     .material(Material::default().transparency(1.).refractive_index(1.5)),
             );
-            let b = Rc::new(
+            let b = Arc::new(
                 Shape::new_sphere(Matrix4x4::translation(0., 0., -0.25))
                     .unwrap()
                     .material(Material::default().transparency(1.).refractive_index(2.)),
             );
-            let c = Rc::new(
+            let c = Arc::new(
                 Shape::new_sphere(Matrix4x4::translation(0., 0., 0.25))
                     .unwrap()
                     .material(Material::default().transparency(1.).refractive_index(2.5)),
             );
             let r = Ray::new(Point::new(0., 0., -4.), Vector::new(1., 0., 0.));
             let is = vec![
-                Intersection::new(Rc::clone(&a), 2.),
-                Intersection::new(Rc::clone(&b), 2.75),
-                Intersection::new(Rc::clone(&c), 3.25),
-                Intersection::new(Rc::clone(&b), 4.75),
-                Intersection::new(Rc::clone(&c), 5.25),
-                Intersection::new(Rc::clone(&a), 6.),
+                Intersection::new(Arc::clone(&a), 2.),
+                Intersection::new(Arc::clone(&b), 2.75),
+                Intersection::new(Arc::clone(&c), 3.25),
+                Intersection::new(Arc::clone(&b), 4.75),
+                Intersection::new(Arc::clone(&c), 5.25),
+                Intersection::new(Arc::clone(&a), 6.),
             ];
             let c = Comp::new(is[*idx].clone(), r, &is);
             assert_eq!(c.indices.ratio, *n1 / *n2);
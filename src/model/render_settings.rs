@@ -0,0 +1,100 @@
+use super::{background::Background, sampling::Sampling};
+
+// how the light reaching the camera is computed
+#[derive(PartialEq, Debug, Copy, Clone)]
+pub enum Integrator {
+    // phong lighting plus perfect reflection and refraction, ambient stands for indirect light
+    Whitted,
+    // monte carlo paths bouncing off diffuse surfaces, noisy unless there are enough samples
+    Path,
+}
+
+#[derive(PartialEq, Debug, Clone)]
+pub struct RenderSettings {
+    // how many times rays can bounce through reflection and refraction,
+    // path tracing goes on after that with russian roulette
+    pub max_depth: u8,
+    // what the rays that don't hit anything see
+    pub background: Background,
+    pub shadows: bool,
+    // reflected and refracted rays weighing less than this in the pixel are not traced
+    pub min_contribution: f64,
+    // per pixel, laid out on the largest square grid that fits
+    pub samples: usize,
+    pub sampling: Sampling,
+    // the same seed always gives the same image, whatever the number of threads
+    pub seed: u64,
+    pub threads: usize,
+    pub integrator: Integrator,
+    // rays averaged by rough reflections and refractions, fewer on deeper bounces
+    pub glossy_samples: usize,
+}
+
+impl Default for RenderSettings {
+    fn default() -> Self {
+        Self {
+            max_depth: 3,
+            background: Background::default(),
+            shadows: true,
+            min_contribution: 0.,
+            samples: 1,
+            sampling: Sampling::Regular,
+            seed: 0,
+            threads: 1,
+            integrator: Integrator::Whitted,
+            glossy_samples: 8,
+        }
+    }
+}
+
+impl RenderSettings {
+    pub fn max_depth(mut self, d: u8) -> Self {
+        self.max_depth = d;
+        self
+    }
+
+    pub fn background(mut self, b: Background) -> Self {
+        self.background = b;
+        self
+    }
+
+    pub fn shadows(mut self, s: bool) -> Self {
+        self.shadows = s;
+        self
+    }
+
+    pub fn min_contribution(mut self, c: f64) -> Self {
+        self.min_contribution = c;
+        self
+    }
+
+    pub fn samples(mut self, s: usize) -> Self {
+        self.samples = s;
+        self
+    }
+
+    pub fn threads(mut self, t: usize) -> Self {
+        self.threads = t;
+        self
+    }
+
+    pub fn sampling(mut self, s: Sampling) -> Self {
+        self.sampling = s;
+        self
+    }
+
+    pub fn seed(mut self, s: u64) -> Self {
+        self.seed = s;
+        self
+    }
+
+    pub fn integrator(mut self, i: Integrator) -> Self {
+        self.integrator = i;
+        self
+    }
+
+    pub fn glossy_samples(mut self, g: usize) -> Self {
+        self.glossy_samples = g;
+        self
+    }
+}
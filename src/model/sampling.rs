@@ -0,0 +1,242 @@
+use std::f64::consts::PI;
+
+use rand::{rngs::StdRng, Rng, SeedableRng};
+
+use crate::math::{colour::Colour, vector::Vector};
+
+#[derive(PartialEq, Debug, Copy, Clone)]
+pub enum Sampling {
+    // centres of the cells of the largest square grid that fits in the sample count
+    Regular,
+    // one random point in each cell of that same grid
+    Jittered,
+    // a jittered 2x2 pass, refined with the full jittered grid when its samples
+    // differ by more than threshold on any channel
+    // the neighbouring samples compared are the pixel's own, not those of the pixels
+    // around it, so that pixels stay independent of each other and of the render order
+    Adaptive { threshold: f64 },
+}
+
+impl Sampling {
+    // offsets inside a pixel, from 0 to 1, of the samples to average
+    // counts that aren't square get the rest of their samples anywhere in the pixel
+    pub fn offsets<R: Rng>(&self, samples: usize, rng: &mut R) -> Vec<(f64, f64)> {
+        let samples = samples.max(1);
+        let side = (samples as f64).sqrt() as usize;
+        let step = 1. / side as f64;
+        let jitter = !matches!(self, Sampling::Regular);
+        let mut offsets: Vec<_> = (0..side)
+            .flat_map(|j| (0..side).map(move |i| (i as f64, j as f64)))
+            .map(|(i, j)| {
+                let (ox, oy) = if jitter {
+                    (rng.gen::<f64>(), rng.gen::<f64>())
+                } else {
+                    (0.5, 0.5)
+                };
+                ((i + ox) * step, (j + oy) * step)
+            })
+            .collect();
+        offsets.extend((side * side..samples).map(|_| (rng.gen::<f64>(), rng.gen::<f64>())));
+        offsets
+    }
+
+    // average of sample called at each offset of a pixel, sample gets the generator
+    // too for anything else it has to pick at random, like a point on the lens
+    pub fn colour<R: Rng, F: Fn(f64, f64, &mut R) -> Colour>(
+        &self,
+        samples: usize,
+        rng: &mut R,
+        sample: F,
+    ) -> Colour {
+        let trace = |offsets: Vec<(f64, f64)>, rng: &mut R| -> Vec<Colour> {
+            offsets
+                .iter()
+                .map(|(ox, oy)| sample(*ox, *oy, rng))
+                .collect()
+        };
+        let colours = match self {
+            Sampling::Adaptive { threshold } if samples > 4 => {
+                let mut first = trace(Sampling::Jittered.offsets(4, rng), rng);
+                if spread(&first) > *threshold {
+                    first.extend(trace(Sampling::Jittered.offsets(samples, rng), rng));
+                }
+                first
+            }
+            Sampling::Adaptive { .. } => trace(Sampling::Jittered.offsets(samples, rng), rng),
+            _ => trace(self.offsets(samples, rng), rng),
+        };
+        let sum = colours.iter().fold(Colour::BLACK, |acc, c| acc + *c);
+        sum * (1. / colours.len() as f64)
+    }
+}
+
+// largest difference on a single channel between two of the colours
+fn spread(colours: &[Colour]) -> f64 {
+    colours
+        .iter()
+        .flat_map(|a| colours.iter().map(move |b| *a - *b))
+        .flat_map(|d| d.into_iter().map(f64::abs))
+        .fold(0., f64::max)
+}
+
+// each pixel gets its own generator so that images don't depend on the render order
+pub fn pixel_rng(seed: u64, x: usize, y: usize) -> StdRng {
+    let pixel = ((y as u64) << 32) | x as u64;
+    StdRng::seed_from_u64(seed.wrapping_mul(0x9e37_79b9_7f4a_7c15) ^ pixel)
+}
+
+// a random direction around normal, more likely close to it following the cosine of the angle
+// https://www.pbr-book.org/3ed-2018/Monte_Carlo_Integration/2D_Sampling_with_Multidimensional_Transformations
+pub fn cosine_hemisphere<R: Rng>(normal: Vector, rng: &mut R) -> Vector {
+    let (u1, u2): (f64, f64) = (rng.gen(), rng.gen());
+    let r = u1.sqrt();
+    let (sin, cos) = (2. * PI * u2).sin_cos();
+    around(normal, r * cos, r * sin, (1. - u1).sqrt())
+}
+
+// the normal of a microfacet of a rough surface, alpha is the roughness squared
+// https://www.pbr-book.org/3ed-2018/Reflection_Models/Microfacet_Models
+pub fn ggx_normal<R: Rng>(normal: Vector, alpha: f64, rng: &mut R) -> Vector {
+    let (u1, u2): (f64, f64) = (rng.gen(), rng.gen());
+    let a2 = alpha * alpha;
+    let cos = ((1. - u1) / (1. + (a2 - 1.) * u1)).sqrt();
+    let sin = (1. - cos * cos).sqrt();
+    let (sin_phi, cos_phi) = (2. * PI * u2).sin_cos();
+    around(normal, sin * cos_phi, sin * sin_phi, cos)
+}
+
+// from coordinates in a frame where normal is z, the other two axes are arbitrary
+pub fn around(normal: Vector, x: f64, y: f64, z: f64) -> Vector {
+    let other = if normal.x.abs() > 0.9 {
+        Vector::Y
+    } else {
+        Vector::X
+    };
+    let tangent = normal.cross(other).norm();
+    let bitangent = normal.cross(tangent);
+    (tangent * x + bitangent * y + normal * z).norm()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::Cell;
+
+    use super::*;
+
+    #[test]
+    fn offsets_regular() -> () {
+        let mut rng = pixel_rng(0, 0, 0);
+        vec![
+            (0, vec![(0.5, 0.5)]),
+            (1, vec![(0.5, 0.5)]),
+            (
+                4,
+                vec![(0.25, 0.25), (0.75, 0.25), (0.25, 0.75), (0.75, 0.75)],
+            ),
+        ]
+        .iter()
+        .for_each(|(samples, exp)| {
+            assert_eq!(Sampling::Regular.offsets(*samples, &mut rng), *exp);
+        });
+        // the grid takes what fits, the rest is spread at random
+        vec![(3, 1), (10, 9)].iter().for_each(|(samples, grid)| {
+            let offsets = Sampling::Regular.offsets(*samples, &mut rng);
+            assert_eq!(offsets.len(), *samples);
+            assert_eq!(offsets[..*grid], Sampling::Regular.offsets(*grid, &mut rng));
+            assert!(offsets
+                .iter()
+                .all(|(ox, oy)| (0. ..1.).contains(ox) && (0. ..1.).contains(oy)));
+        });
+    }
+
+    #[test]
+    fn offsets_jittered() -> () {
+        let offsets = Sampling::Jittered.offsets(16, &mut pixel_rng(1, 2, 3));
+        assert_eq!(offsets.len(), 16);
+        // one sample per cell of the 4x4 grid
+        offsets.iter().enumerate().for_each(|(k, (ox, oy))| {
+            assert_eq!(((ox * 4.) as usize, (oy * 4.) as usize), (k % 4, k / 4));
+        });
+        assert_ne!(
+            offsets,
+            Sampling::Regular.offsets(16, &mut pixel_rng(1, 2, 3))
+        );
+    }
+
+    #[test]
+    fn pixel_rng_reproducible() -> () {
+        let offsets = |seed, x, y| Sampling::Jittered.offsets(4, &mut pixel_rng(seed, x, y));
+        assert_eq!(offsets(7, 1, 2), offsets(7, 1, 2));
+        assert_ne!(offsets(7, 1, 2), offsets(8, 1, 2));
+        assert_ne!(offsets(7, 1, 2), offsets(7, 2, 1));
+    }
+
+    #[test]
+    fn colour_adaptive() -> () {
+        let calls = Cell::new(0);
+        let uniform = |_: f64, _: f64, _: &mut StdRng| {
+            calls.set(calls.get() + 1);
+            Colour::WHITE
+        };
+        let adaptive = Sampling::Adaptive { threshold: 0.1 };
+        let res = adaptive.colour(16, &mut pixel_rng(0, 0, 0), uniform);
+        assert_eq!((res, calls.get()), (Colour::WHITE, 4));
+
+        // half the pixel is white, so the first pass disagrees and gets refined
+        calls.set(0);
+        let edge = |ox: f64, _: f64, _: &mut StdRng| {
+            calls.set(calls.get() + 1);
+            if ox < 0.5 {
+                Colour::WHITE
+            } else {
+                Colour::BLACK
+            }
+        };
+        let res = adaptive.colour(16, &mut pixel_rng(0, 0, 0), edge);
+        assert_eq!(calls.get(), 20);
+        assert_eq!(res, Colour::new(0.5, 0.5, 0.5));
+    }
+
+    #[test]
+    fn cosine_hemisphere_around_normal() -> () {
+        let mut rng = pixel_rng(0, 0, 0);
+        vec![
+            Vector::Y,
+            Vector::new(1., 0., 0.),
+            Vector::new(0., -0.6, 0.8),
+        ]
+        .iter()
+        .for_each(|n| {
+            let dirs: Vec<_> = (0..1000).map(|_| cosine_hemisphere(*n, &mut rng)).collect();
+            assert!(dirs
+                .iter()
+                .all(|d| d.dot(*n) >= 0. && (d.len() - 1.).abs() < 1e-9));
+            // the mean of the cosine is 2/3 with this distribution
+            let mean = dirs.iter().map(|d| d.dot(*n)).sum::<f64>() / dirs.len() as f64;
+            assert!((mean - 2. / 3.).abs() < 0.03, "{mean}");
+        });
+    }
+
+    #[test]
+    fn ggx_normal_spread() -> () {
+        let mut rng = pixel_rng(0, 0, 0);
+        let n = Vector::new(0., -0.6, 0.8);
+        let mean_cos = |alpha: f64, rng: &mut StdRng| {
+            (0..1000)
+                .map(|_| ggx_normal(n, alpha, rng).dot(n))
+                .sum::<f64>()
+                / 1000.
+        };
+        assert!(mean_cos(0.0001, &mut rng) > 0.9999);
+        let (smooth, rough) = (mean_cos(0.1, &mut rng), mean_cos(0.8, &mut rng));
+        assert!(smooth > rough && rough > 0.);
+    }
+
+    #[test]
+    fn colour_regular() -> () {
+        let res = Sampling::Regular.colour(4, &mut pixel_rng(0, 0, 0), |ox, oy, _| {
+            Colour::new(ox, oy, 1.)
+        });
+        assert_eq!(res, Colour::new(0.5, 0.5, 1.));
+    }
+}
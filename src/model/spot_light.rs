@@ -0,0 +1,109 @@
+use std::f64::consts::PI;
+
+use crate::math::{colour::Colour, point::Point, vector::Vector};
+
+use super::light::Attenuation;
+
+// a point light restricted to a cone, fading out between the inner and outer angles
+#[derive(PartialEq, Debug, Copy, Clone)]
+pub struct SpotLight {
+    pub position: Point,
+    // axis of the cone, normalised
+    pub direction: Vector,
+    // angles are measured from the axis, cosines are kept to compare with dot products
+    cos_inner: f64,
+    cos_outer: f64,
+    pub intensity: Colour,
+    pub attenuation: Option<Attenuation>,
+}
+
+impl SpotLight {
+    pub fn new(
+        position: Point,
+        direction: Vector,
+        inner: f64,
+        outer: f64,
+        intensity: Colour,
+    ) -> Option<Self> {
+        (direction.len() > 0. && 0. <= inner && inner <= outer && outer <= PI).then(|| Self {
+            position,
+            direction: direction.norm(),
+            cos_inner: inner.cos(),
+            cos_outer: outer.cos(),
+            intensity,
+            attenuation: None,
+        })
+    }
+
+    pub fn attenuation(mut self, a: Attenuation) -> Self {
+        self.attenuation = Some(a);
+        self
+    }
+
+    // from 1 inside the inner cone to 0 outside the outer one, d goes from the light
+    pub fn falloff(&self, d: Vector) -> f64 {
+        let cos = d.dot(self.direction);
+        if cos >= self.cos_inner {
+            1.
+        } else if cos <= self.cos_outer {
+            0.
+        } else {
+            let t = (cos - self.cos_outer) / (self.cos_inner - self.cos_outer);
+            t * t * (3. - 2. * t)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::f64::consts::{FRAC_PI_2, FRAC_PI_4};
+
+    use crate::math::round::Round;
+
+    use super::*;
+
+    #[test]
+    fn new() -> () {
+        let p = Point::new(0., 1., 0.);
+        let d = Vector::new(0., -1., 0.);
+        assert!(SpotLight::new(p, d, 0., FRAC_PI_4, Colour::WHITE).is_some());
+        vec![
+            (d, FRAC_PI_4, 0.1),
+            (d, -0.1, FRAC_PI_4),
+            (d, 0., 4.),
+            (Vector::new(0., 0., 0.), 0., FRAC_PI_4),
+        ]
+        .iter()
+        .for_each(|(d, inner, outer)| {
+            assert!(SpotLight::new(p, *d, *inner, *outer, Colour::WHITE).is_none());
+        });
+    }
+
+    #[test]
+    fn falloff() -> () {
+        let l = SpotLight::new(
+            Point::ORIGIN,
+            Vector::new(0., 0., 2.),
+            FRAC_PI_4 / 2.,
+            FRAC_PI_4,
+            Colour::WHITE,
+        )
+        .unwrap();
+        vec![
+            (Vector::new(0., 0., 1.), 1.),
+            (Vector::new(0., 0.1, 1.).norm(), 1.),
+            (Vector::new(0., 1., 1.).norm(), 0.),
+            (Vector::new(1., 0., 0.), 0.),
+            (Vector::new(0., 0., -1.), 0.),
+        ]
+        .iter()
+        .for_each(|(d, exp)| assert_eq!(l.falloff(*d), *exp));
+        // smoothed, a bit more than half way in angle
+        let between = l.falloff(Vector::new(0., (3. * FRAC_PI_4 / 4.).tan(), 1.).norm());
+        assert_eq!(vec![between].rounded(5), vec![0.60975]);
+        // a cone wider than a half space lights behind too
+        let wide = SpotLight::new(Point::ORIGIN, Vector::Z, FRAC_PI_2, PI, Colour::WHITE).unwrap();
+        assert!(wide.falloff(Vector::new(0., 0., -1.)) == 0.);
+        assert!(wide.falloff(Vector::new(0., 1., -1.).norm()) > 0.);
+    }
+}
@@ -1,4 +1,6 @@
-use std::rc::Rc;
+use std::sync::Arc;
+
+use rand::Rng;
 
 use crate::{
     math::{colour::Colour, matrix::Matrix4x4, point::Point},
@@ -6,53 +8,208 @@ use crate::{
 };
 
 use super::{
+    bvh::{Bvh, BvhStats},
     comp::Comp,
     intersection::{Intersection, IntersectionHit},
+    light::{Light, LightSample},
     material::Material,
     point_light::PointLight,
     ray::Ray,
+    render_settings::{Integrator, RenderSettings},
+    sampling,
 };
 
 pub struct World {
-    shapes: Vec<Rc<Shape>>,
-    lights: Vec<PointLight>,
+    shapes: Vec<Arc<Shape>>,
+    lights: Vec<Light>,
+    bvh: Option<Bvh>,
 }
 
 impl World {
-    pub fn new(shapes: Vec<Rc<Shape>>, lights: Vec<PointLight>) -> Self {
-        Self { shapes, lights }
+    pub fn new(shapes: Vec<Arc<Shape>>, lights: Vec<Light>) -> Self {
+        Self {
+            shapes,
+            lights,
+            bvh: None,
+        }
     }
 
-    pub fn shapes(mut self, shapes: Vec<Rc<Shape>>) -> Self {
+    pub fn shapes(mut self, shapes: Vec<Arc<Shape>>) -> Self {
         self.shapes = shapes;
-        self
+        self.rebuild_bvh()
     }
 
-    pub fn add_shape(mut self, shape: Rc<Shape>) -> Self {
+    pub fn add_shape(mut self, shape: Arc<Shape>) -> Self {
         self.shapes.push(shape);
+        self.rebuild_bvh()
+    }
+
+    // without it every shape is checked against every ray
+    pub fn build_bvh(mut self) -> Self {
+        self.bvh = Some(Bvh::new(&self.shapes));
         self
     }
 
-    pub fn lights(mut self, lights: Vec<PointLight>) -> Self {
+    // keeps an already built bvh in sync with the shapes
+    fn rebuild_bvh(self) -> Self {
+        if self.bvh.is_some() {
+            self.build_bvh()
+        } else {
+            self
+        }
+    }
+
+    pub fn bvh_stats(&self) -> Option<BvhStats> {
+        self.bvh.as_ref().map(|bvh| bvh.stats())
+    }
+
+    pub fn lights(mut self, lights: Vec<Light>) -> Self {
         self.lights = lights;
         self
     }
 
-    pub fn colour_at(&self, r: &Ray, remaining: u8) -> Colour {
+    // deterministic, every call draws the same random numbers from the settings' seed,
+    // renders go through sample_colour_at with a per pixel rng
+    pub fn colour_at(&self, r: &Ray, s: &RenderSettings) -> Colour {
+        self.sample_colour_at(r, s, &mut sampling::pixel_rng(s.seed, 0, 0))
+    }
+
+    // one sample with the settings' integrator, rng picks glossy reflections and paths
+    pub fn sample_colour_at<R: Rng>(&self, r: &Ray, s: &RenderSettings, rng: &mut R) -> Colour {
+        match s.integrator {
+            Integrator::Whitted => self.trace(r, s, s.max_depth, 1., rng),
+            Integrator::Path => self.trace_path(r, s, rng),
+        }
+    }
+
+    // weight is how much the ray counts in the final pixel, used to stop tracing early
+    fn trace<R: Rng>(
+        &self,
+        r: &Ray,
+        s: &RenderSettings,
+        remaining: u8,
+        weight: f64,
+        rng: &mut R,
+    ) -> Colour {
         let is = self.intersect(r);
         if let Some(hit) = is.hit() {
             let c = Comp::new(hit, *r, &is);
-            self.shade_hit(&c, remaining)
+            // the light coming back is absorbed on the way if the ray was inside something
+            let inside = c
+                .indices
+                .transmittance(c.intersection.t * r.direction.len());
+            self.shade_hit(&c, s, remaining, weight, rng) * inside
         } else {
-            Colour::BLACK
+            s.background.colour(r.direction)
+        }
+    }
+
+    // https://en.wikipedia.org/wiki/Path_tracing
+    // lights are sampled directly at every bounce, emissive surfaces are found by the paths
+    fn trace_path<R: Rng>(&self, r: &Ray, s: &RenderSettings, rng: &mut R) -> Colour {
+        let mut ray = *r;
+        // how much of the light coming along ray reaches the camera
+        let mut throughput = Colour::WHITE;
+        let mut colour = Colour::BLACK;
+        let mut bounces = 0;
+        loop {
+            let is = self.intersect(&ray);
+            let c = match is.hit() {
+                Some(hit) => Comp::new(hit, ray, &is),
+                None => return colour + throughput * s.background.colour(ray.direction),
+            };
+            throughput = throughput
+                * c.indices
+                    .transmittance(c.intersection.t * ray.direction.len());
+            let shape = &c.intersection.shape;
+            let mat = &shape.material;
+            let direct = self.lights.iter().fold(Colour::BLACK, |acc, light| {
+                let intensity = if s.shadows {
+                    self.intensity_at(c.over_point, light)
+                } else {
+                    1.
+                };
+                acc + mat.direct(
+                    Arc::clone(shape),
+                    *light,
+                    c.over_point,
+                    c.eye,
+                    c.normal,
+                    intensity,
+                )
+            });
+            colour = colour + throughput * (mat.emissive + direct);
+
+            // long paths are cut at random, the ones kept count for those cut
+            if bounces >= s.max_depth {
+                let survival = throughput.into_iter().fold(0., f64::max).clamp(0.05, 0.95);
+                if rng.gen::<f64>() >= survival {
+                    return colour;
+                }
+                throughput = throughput * (1. / survival);
+            }
+            bounces = bounces.saturating_add(1);
+
+            // the next bounce is picked in proportion to what the material does
+            let base = mat.colour_at(Arc::clone(shape), c.over_point);
+            let (mut reflect, mut refract, diffuse) = match &mat.pbr {
+                Some(pbr) => {
+                    let fresnel = pbr.fresnel(base, c.eye.dot(c.normal)).luminance();
+                    let rest = (1. - fresnel) * (1. - pbr.metallic);
+                    (
+                        fresnel,
+                        rest * mat.transparency,
+                        rest * (1. - mat.transparency),
+                    )
+                }
+                None if mat.reflective > 0. && mat.transparency > 0. => {
+                    let reflectance = c.indices.reflectance();
+                    (
+                        reflectance * mat.reflective,
+                        (1. - reflectance) * mat.transparency,
+                        mat.diffuse,
+                    )
+                }
+                None => (mat.reflective, mat.transparency, mat.diffuse),
+            };
+            if c.indices.total_internal_reflection() {
+                (reflect, refract) = (reflect + refract, 0.);
+            }
+            let total = reflect + refract + diffuse;
+            if total <= 0. || throughput.into_iter().all(|c| c <= 0.) {
+                return colour;
+            }
+            let pick = rng.gen::<f64>() * total;
+            ray = if pick < reflect {
+                match &mat.pbr {
+                    // the sample's weight replaces the average one used to pick it
+                    Some(pbr) => {
+                        let (direction, weight) = pbr.sample_reflection(base, c.normal, c.eye, rng);
+                        throughput = throughput * weight * (1. / reflect);
+                        Ray::new(c.over_point, direction)
+                    }
+                    None => Ray::new(c.over_point, c.reflected(mat.roughness, rng)),
+                }
+            } else if pick < reflect + refract {
+                Ray::new(c.under_point, c.refracted(mat.roughness, rng))
+            } else {
+                // lambertian, the cosine is in the distribution of the directions
+                throughput = throughput * base;
+                Ray::new(c.over_point, sampling::cosine_hemisphere(c.normal, rng))
+            };
+            // divided by the probability of the pick, out of total
+            throughput = throughput * total;
         }
     }
 
     fn intersect(&self, r: &Ray) -> Vec<Intersection> {
+        if let Some(bvh) = &self.bvh {
+            return bvh.intersections(r);
+        }
         let mut is: Vec<Intersection> = Vec::new();
         // TODO: rework, shouldn't need to clone shapes
         for shape in self.shapes.as_slice() {
-            let mut inners = Intersection::intersections(Rc::clone(shape), r);
+            let mut inners = Intersection::intersections(Arc::clone(shape), r);
             is.append(&mut inners);
         }
         // safe if no NaN
@@ -66,65 +223,153 @@ impl World {
         //filtered
     }
 
-    fn shade_hit(&self, c: &Comp, remaining: u8) -> Colour {
+    fn shade_hit<R: Rng>(
+        &self,
+        c: &Comp,
+        s: &RenderSettings,
+        remaining: u8,
+        weight: f64,
+        rng: &mut R,
+    ) -> Colour {
         let shape = &c.intersection.shape;
-        let surface = self.lights.iter().fold(Colour::BLACK, |acc, light| {
-            let is_shadowed = self.is_shadowed(c.over_point, light);
-            acc + shape.material.lightning(
-                Rc::clone(shape),
-                *light,
-                c.over_point,
-                c.eye,
-                c.normal,
-                is_shadowed,
-            )
-        });
-        let reflected = self.reflected_colour(c, remaining);
-        let refracted = self.refracted_colour(c, remaining);
+        let surface = self
+            .lights
+            .iter()
+            .fold(shape.material.emissive, |acc, light| {
+                let intensity = if s.shadows {
+                    self.intensity_at(c.over_point, light)
+                } else {
+                    1.
+                };
+                acc + shape.material.lightning(
+                    Arc::clone(shape),
+                    *light,
+                    c.over_point,
+                    c.eye,
+                    c.normal,
+                    intensity,
+                )
+            });
 
         let mat = &shape.material;
-        if mat.reflective > 0. && mat.transparency > 0. {
+        if let Some(pbr) = &mat.pbr {
+            // what fresnel doesn't reflect and metals don't absorb goes through
+            let base = mat.colour_at(Arc::clone(shape), c.over_point);
+            let through =
+                (Colour::WHITE - pbr.fresnel(base, c.eye.dot(c.normal))) * (1. - pbr.metallic);
+            let reflected = self.reflected_colour(c, s, remaining, weight, rng);
+            let refracted =
+                self.refracted_colour(c, s, remaining, weight * through.luminance(), rng);
+            surface + reflected + refracted * through
+        } else if mat.reflective > 0. && mat.transparency > 0. {
             let reflectance = c.indices.reflectance();
+            let reflected = self.reflected_colour(c, s, remaining, weight * reflectance, rng);
+            let refracted =
+                self.refracted_colour(c, s, remaining, weight * (1. - reflectance), rng);
             surface + reflected * reflectance + refracted * (1. - reflectance)
         } else {
+            let reflected = self.reflected_colour(c, s, remaining, weight, rng);
+            let refracted = self.refracted_colour(c, s, remaining, weight, rng);
             surface + reflected + refracted
         }
     }
 
-    fn reflected_colour(&self, c: &Comp, remaining: u8) -> Colour {
-        if remaining < 1 {
-            Colour::BLACK
+    // rough materials average several rays, each spread by the roughness
+    // pbr materials weigh theirs by fresnel
+    fn reflected_colour<R: Rng>(
+        &self,
+        c: &Comp,
+        s: &RenderSettings,
+        remaining: u8,
+        weight: f64,
+        rng: &mut R,
+    ) -> Colour {
+        let mat = &c.intersection.shape.material;
+        if remaining < 1 || (mat.pbr.is_none() && mat.reflective == 0.) {
+            return Colour::BLACK;
+        }
+        // pbr reflections only know their strength once sampled, fresnel keeps it under 1
+        let expected = if mat.pbr.is_some() {
+            1.
         } else {
-            let reflective = c.intersection.shape.material.reflective;
-            if reflective == 0. {
-                Colour::BLACK
+            mat.reflective
+        };
+        let samples = Self::glossy_samples(mat.roughness, s, weight * expected);
+        let sum = (0..samples).fold(Colour::BLACK, |acc, _| {
+            // strength is how much of the weight goes on, reflective what colours the result
+            let (direction, reflective, strength) = match &mat.pbr {
+                Some(pbr) => {
+                    let base = mat.colour_at(Arc::clone(&c.intersection.shape), c.over_point);
+                    let (direction, reflective) = pbr.sample_reflection(base, c.normal, c.eye, rng);
+                    (direction, reflective, reflective.luminance())
+                }
+                None => (
+                    c.reflected(mat.roughness, rng),
+                    Colour::WHITE * mat.reflective,
+                    mat.reflective,
+                ),
+            };
+            // each sample only counts for its share of the average
+            let weight = weight * strength / samples as f64;
+            if strength == 0. || weight < s.min_contribution {
+                acc
             } else {
-                let reflect_ray = Ray::new(c.over_point, c.reflect);
-                let c = self.colour_at(&reflect_ray, remaining - 1);
-                c * reflective
+                let reflect_ray = Ray::new(c.over_point, direction);
+                acc + self.trace(&reflect_ray, s, remaining - 1, weight, rng) * reflective
             }
-        }
+        });
+        sum * (1. / samples as f64)
     }
 
-    fn refracted_colour(&self, c: &Comp, remaining: u8) -> Colour {
-        if remaining < 1 || c.indices.total_internal_reflection() {
+    fn refracted_colour<R: Rng>(
+        &self,
+        c: &Comp,
+        s: &RenderSettings,
+        remaining: u8,
+        weight: f64,
+        rng: &mut R,
+    ) -> Colour {
+        let mat = &c.intersection.shape.material;
+        let transparency = mat.transparency;
+        let weight = weight * transparency;
+        if remaining < 1
+            || c.indices.total_internal_reflection()
+            || transparency == 0.
+            || weight < s.min_contribution
+        {
             Colour::BLACK
         } else {
-            let transparency = c.intersection.shape.material.transparency;
-            let direction = c.normal * (c.indices.ratio * c.indices.cos1 - c.indices.cos2)
-                - c.eye * c.indices.ratio;
-            let refract_ray = Ray::new(c.under_point, direction);
-            self.colour_at(&refract_ray, remaining - 1) * transparency
+            let samples = Self::glossy_samples(mat.roughness, s, weight);
+            let weight = weight / samples as f64;
+            let sum = (0..samples).fold(Colour::BLACK, |acc, _| {
+                let refract_ray = Ray::new(c.under_point, c.refracted(mat.roughness, rng));
+                acc + self.trace(&refract_ray, s, remaining - 1, weight, rng)
+            });
+            sum * (transparency / samples as f64)
         }
     }
 
+    // the settings' count for rays that fully count in the pixel, fewer for the others
+    fn glossy_samples(roughness: f64, s: &RenderSettings, weight: f64) -> usize {
+        if roughness > 0. {
+            ((s.glossy_samples as f64 * weight).ceil() as usize).max(1)
+        } else {
+            1
+        }
+    }
+
+    // fraction of the light's samples visible from p
+    fn intensity_at(&self, p: Point, light: &Light) -> f64 {
+        let samples = light.samples(p);
+        let lit = samples.iter().filter(|l| !self.is_shadowed(p, l)).count();
+        lit as f64 / samples.len() as f64
+    }
+
     // this rule hinders readability
     #[allow(clippy::match_like_matches_macro)]
-    fn is_shadowed(&self, p: Point, light: &PointLight) -> bool {
-        let point_to_light = light.position - p;
-        let dist = point_to_light.len();
-        let direction = point_to_light.norm();
-        let ray = Ray::new(p, direction);
+    fn is_shadowed(&self, p: Point, sample: &LightSample) -> bool {
+        let dist = sample.distance;
+        let ray = Ray::new(p, sample.direction);
         // might work with filtering mat.transparency = 1 but transparent object cast shadows?
         let intersections_casting_shadows: Vec<Intersection> = self
             .intersect(&ray)
@@ -150,23 +395,35 @@ impl Default for World {
             Shape::new_sphere(Matrix4x4::scaling(0.5, 0.5, 0.5)).unwrap_or(Shape::id_sphere());
 
         Self {
-            shapes: vec![Rc::new(sphere1), Rc::new(sphere2)],
-            lights: vec![light],
+            shapes: vec![Arc::new(sphere1), Arc::new(sphere2)],
+            lights: vec![light.into()],
+            bvh: None,
         }
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use std::{f64::consts::SQRT_2, rc::Rc};
+    use std::{f64::consts::SQRT_2, sync::Arc};
 
     use crate::{
         math::{round::Round, vector::Vector},
+        model::{
+            area_light::AreaLight, background::Background, directional_light::DirectionalLight,
+            pbr::Pbr, spot_light::SpotLight,
+        },
         patterns::{pattern::Pattern, pattern_kind::PatternKind},
+        shapes::{csg::CsgOp, shape_kind::ShapeKind},
     };
 
+    use rand::{rngs::StdRng, RngCore};
+
     use super::*;
 
+    fn rng() -> StdRng {
+        sampling::pixel_rng(0, 0, 0)
+    }
+
     fn m() -> Material {
         Material::default()
             .colour(Colour::new(0.8, 1., 0.6))
@@ -180,7 +437,7 @@ mod tests {
 
     #[test]
     fn shade_hit_reflectance() -> () {
-        let p = Rc::new(
+        let p = Arc::new(
             Shape::new_plane(Matrix4x4::translation(0., -1., 0.))
                 .unwrap()
                 .material(
@@ -190,7 +447,7 @@ mod tests {
                         .reflective(0.5),
                 ),
         );
-        let s = Rc::new(
+        let s = Arc::new(
             Shape::new_sphere(Matrix4x4::translation(0., -3.5, -0.5))
                 .unwrap()
                 .material(
@@ -199,23 +456,23 @@ mod tests {
                         .ambient(0.5),
                 ),
         );
-        let w = World::default().add_shape(Rc::clone(&p)).add_shape(s);
+        let w = World::default().add_shape(Arc::clone(&p)).add_shape(s);
         let s2 = SQRT_2 / 2.;
         let r = Ray::new(Point::new(0., 0., -3.), Vector::new(0., -s2, s2));
-        let i = Intersection::new(Rc::clone(&p), s2 * 2.);
+        let i = Intersection::new(Arc::clone(&p), s2 * 2.);
         let c = Comp::new(i.clone(), r, &vec![i]);
-        let res = w.shade_hit(&c, 5);
+        let res = w.shade_hit(&c, &RenderSettings::default(), 5, 1., &mut rng());
         assert_eq!(res.rounded(5), vec![0.93391, 0.69643, 0.69243]);
     }
 
     #[test]
     fn shade_hit_transparent_mat() -> () {
-        let p = Rc::new(
+        let p = Arc::new(
             Shape::new_plane(Matrix4x4::translation(0., -1., 0.))
                 .unwrap()
                 .material(Material::default().transparency(0.5).refractive_index(1.5)),
         );
-        let s = Rc::new(
+        let s = Arc::new(
             Shape::new_sphere(Matrix4x4::translation(0., -3.5, -0.5))
                 .unwrap()
                 .material(
@@ -224,97 +481,162 @@ mod tests {
                         .ambient(0.5),
                 ),
         );
-        let w = World::default().add_shape(Rc::clone(&p)).add_shape(s);
+        let w = World::default().add_shape(Arc::clone(&p)).add_shape(s);
         let s2 = SQRT_2 / 2.;
         let r = Ray::new(Point::new(0., 0., -3.), Vector::new(0., -s2, s2));
-        let i = Intersection::new(Rc::clone(&p), s2 * 2.);
+        let i = Intersection::new(Arc::clone(&p), s2 * 2.);
         let c = Comp::new(i.clone(), r, &vec![i]);
-        let res = w.shade_hit(&c, 5);
+        let res = w.shade_hit(&c, &RenderSettings::default(), 5, 1., &mut rng());
         assert_eq!(res.rounded(5), vec![0.93643, 0.68643, 0.68643]);
     }
 
     #[test]
     fn refracted_colour_refracted_ray() -> () {
-        let a = Rc::new(
+        let a = Arc::new(
             Shape::id_sphere().material(
                 Material::default()
                     .ambient(1.)
                     .pattern(Pattern::id(PatternKind::Test)),
             ),
         );
-        let b = Rc::new(b().material(Material::default().transparency(1.).refractive_index(1.5)));
-        let w = World::default().shapes(vec![Rc::clone(&a), Rc::clone(&b)]);
+        let b = Arc::new(b().material(Material::default().transparency(1.).refractive_index(1.5)));
+        let w = World::default().shapes(vec![Arc::clone(&a), Arc::clone(&b)]);
         let r = Ray::new(Point::new(0., 0., 0.1), Vector::new(0., 1., 0.));
         let is = vec![
-            Intersection::new(Rc::clone(&a), -0.9899),
-            Intersection::new(Rc::clone(&b), -0.4899),
-            Intersection::new(Rc::clone(&b), 0.4899),
-            Intersection::new(Rc::clone(&a), 0.9899),
+            Intersection::new(Arc::clone(&a), -0.9899),
+            Intersection::new(Arc::clone(&b), -0.4899),
+            Intersection::new(Arc::clone(&b), 0.4899),
+            Intersection::new(Arc::clone(&a), 0.9899),
         ];
         let c = Comp::new(is[2].clone(), r, &is);
-        let res = w.refracted_colour(&c, 5);
+        let res = w.refracted_colour(&c, &RenderSettings::default(), 5, 1., &mut rng());
         assert_eq!(res.rounded(5), vec![0., 0.99887, 0.04722]);
     }
 
+    #[test]
+    fn refracted_colour_rough() -> () {
+        let a = Arc::new(
+            Shape::id_sphere().material(
+                Material::default()
+                    .ambient(1.)
+                    .pattern(Pattern::id(PatternKind::Test)),
+            ),
+        );
+        let frosted = Material::default()
+            .transparency(1.)
+            .refractive_index(1.5)
+            .roughness(0.3);
+        let b = Arc::new(b().material(frosted));
+        let w = World::default().shapes(vec![Arc::clone(&a), Arc::clone(&b)]);
+        let r = Ray::new(Point::new(0., 0., 0.1), Vector::new(0., 1., 0.));
+        let is = vec![
+            Intersection::new(Arc::clone(&a), -0.9899),
+            Intersection::new(Arc::clone(&b), -0.4899),
+            Intersection::new(Arc::clone(&b), 0.4899),
+            Intersection::new(Arc::clone(&a), 0.9899),
+        ];
+        let c = Comp::new(is[2].clone(), r, &is);
+        let mut rng = rng();
+        // every sample goes through a different part of the sphere behind
+        let s = RenderSettings::default().glossy_samples(1);
+        let res: Vec<_> = (0..10)
+            .map(|_| w.refracted_colour(&c, &s, 5, 1., &mut rng))
+            .collect();
+        assert!(res.iter().any(|c| *c != res[0]));
+        // averaging more of them converges
+        let s = s.glossy_samples(200);
+        let a = w.refracted_colour(&c, &s, 5, 1., &mut rng);
+        let b = w.refracted_colour(&c, &s, 5, 1., &mut rng);
+        assert!((a - b).into_iter().all(|d| d.abs() < 0.05), "{a:?} {b:?}");
+    }
+
+    #[test]
+    fn colour_at_absorption() -> () {
+        // clear glass that doesn't bend rays, only the absorbed light changes
+        let glass = Material::default()
+            .ambient(0.)
+            .diffuse(0.)
+            .specular(0.)
+            .transparency(1.)
+            .absorption(Colour::new(0., 1., 2.));
+        let r = Ray::new(Point::new(0., 0., -5.), Vector::new(0., 0., 1.));
+        let s = RenderSettings::default().background(Background::Solid(Colour::WHITE));
+        let mut rng = rng();
+        vec![
+            (1., vec![1., 0.13534, 0.01832]),
+            (0.5, vec![1., 0.36788, 0.13534]),
+        ]
+        .iter()
+        .for_each(|(radius, exp)| {
+            let sphere = Shape::new_sphere(Matrix4x4::scaling(*radius, *radius, *radius))
+                .unwrap()
+                .material(glass.clone());
+            let w = World::new(vec![Arc::new(sphere)], vec![]);
+            assert_eq!(w.colour_at(&r, &s).rounded(5), *exp);
+            let path = s.clone().integrator(Integrator::Path);
+            assert_eq!(w.sample_colour_at(&r, &path, &mut rng).rounded(5), *exp);
+        });
+    }
+
     #[test]
     fn refracted_colour_tir() -> () {
-        let a = Rc::new(
+        let a = Arc::new(
             Shape::id_sphere().material(Material::default().transparency(1.).refractive_index(1.5)),
         );
-        let b = Rc::new(b());
-        let w = World::default().shapes(vec![Rc::clone(&a), Rc::clone(&b)]);
+        let b = Arc::new(b());
+        let w = World::default().shapes(vec![Arc::clone(&a), Arc::clone(&b)]);
         let s2 = SQRT_2 / 2.;
         let r = Ray::new(Point::new(0., 0., s2), Vector::new(0., 1., 0.));
         let is = vec![
-            Intersection::new(Rc::clone(&a), -s2),
-            Intersection::new(Rc::clone(&a), s2),
+            Intersection::new(Arc::clone(&a), -s2),
+            Intersection::new(Arc::clone(&a), s2),
         ];
         let c = Comp::new(is[1].clone(), r, &is);
-        let res = w.refracted_colour(&c, 1);
+        let res = w.refracted_colour(&c, &RenderSettings::default(), 1, 1., &mut rng());
         assert_eq!(res, Colour::BLACK);
     }
 
     #[test]
     fn refracted_colour_exhausted() -> () {
-        let a = Rc::new(
+        let a = Arc::new(
             Shape::id_sphere().material(Material::default().transparency(1.).refractive_index(1.5)),
         );
-        let w = World::default().shapes(vec![Rc::clone(&a), Rc::new(b())]);
+        let w = World::default().shapes(vec![Arc::clone(&a), Arc::new(b())]);
         let r = Ray::new(Point::new(0., 0., -5.), Vector::new(0., 0., 1.));
         let is = vec![Intersection::new(a, 4.)];
         let c = Comp::new(is[0].clone(), r, &is);
-        let res = w.refracted_colour(&c, 0);
+        let res = w.refracted_colour(&c, &RenderSettings::default(), 0, 1., &mut rng());
         assert_eq!(res, Colour::BLACK);
     }
 
     #[test]
     fn refracted_colour_nontransparent_mat() -> () {
         let w = World::default();
-        let s = Rc::new(Shape::id_sphere());
+        let s = Arc::new(Shape::id_sphere());
         let r = Ray::new(Point::new(0., 0., -5.), Vector::new(0., 0., 1.));
         let is = vec![
-            Intersection::new(Rc::clone(&s), 4.),
-            Intersection::new(Rc::clone(&s), 6.),
+            Intersection::new(Arc::clone(&s), 4.),
+            Intersection::new(Arc::clone(&s), 6.),
         ];
         let c = Comp::new(is[0].clone(), r, &is);
-        let res = w.refracted_colour(&c, 1);
+        let res = w.refracted_colour(&c, &RenderSettings::default(), 1, 1., &mut rng());
         assert_eq!(res, Colour::BLACK);
     }
 
     #[test]
     fn shade_hit_reflective() -> () {
         let m = Material::default().reflective(0.5);
-        let s = Rc::new(
+        let s = Arc::new(
             Shape::new_plane(Matrix4x4::translation(0., -1., 0.))
                 .unwrap()
                 .material(m),
         );
-        let w = World::default().add_shape(Rc::clone(&s));
+        let w = World::default().add_shape(Arc::clone(&s));
         let s2 = SQRT_2 / 2.;
         let r = Ray::new(Point::new(0., 0., -3.), Vector::new(0., -s2, s2));
         let i = Intersection::new(s, s2 * 2.);
         let c = Comp::new(i.clone(), r, &vec![i]);
-        let res = w.shade_hit(&c, 1);
+        let res = w.shade_hit(&c, &RenderSettings::default(), 1, 1., &mut rng());
         assert_eq!(res.rounded(5), vec![0.87676, 0.92434, 0.82917]);
     }
 
@@ -322,110 +644,530 @@ mod tests {
     fn reflected_colour_exhausted() -> () {
         let w = World::default();
         let r = Ray::new(Point::ORIGIN, Vector::new(0., 0., 1.));
-        let i = Intersection::new(Rc::new(Shape::id_sphere()), 1.);
+        let i = Intersection::new(Arc::new(Shape::id_sphere()), 1.);
         let c = Comp::new(i.clone(), r, &vec![i]);
-        let res = w.reflected_colour(&c, 0);
+        let res = w.reflected_colour(&c, &RenderSettings::default(), 0, 1., &mut rng());
         assert_eq!(res, Colour::BLACK);
     }
 
     #[test]
     fn reflected_colour_reflective_mat() -> () {
         let m = Material::default().reflective(0.5);
-        let s = Rc::new(
+        let s = Arc::new(
             Shape::new_plane(Matrix4x4::translation(0., -1., 0.))
                 .unwrap()
                 .material(m),
         );
-        let w = World::default().add_shape(Rc::clone(&s));
+        let w = World::default().add_shape(Arc::clone(&s));
         let s2 = SQRT_2 / 2.;
         let r = Ray::new(Point::new(0., 0., -3.), Vector::new(0., -s2, s2));
         let i = Intersection::new(s, s2 * 2.);
         let c = Comp::new(i.clone(), r, &vec![i]);
-        let res = w.reflected_colour(&c, 1);
+        let res = w.reflected_colour(&c, &RenderSettings::default(), 1, 1., &mut rng());
         assert_eq!(res.rounded(5), vec![0.19033, 0.23792, 0.14275]);
     }
 
+    #[test]
+    fn reflected_colour_pbr() -> () {
+        let plane = |pbr: Pbr| {
+            Arc::new(
+                Shape::new_plane(Matrix4x4::translation(0., -1., 0.))
+                    .unwrap()
+                    .material(Material::default().pbr(pbr)),
+            )
+        };
+        let s2 = SQRT_2 / 2.;
+        let r = Ray::new(Point::new(0., 0., -3.), Vector::new(0., -s2, s2));
+        let reflected = |p: Arc<Shape>, rng: &mut StdRng| {
+            let w = World::default().add_shape(Arc::clone(&p));
+            let i = Intersection::new(p, s2 * 2.);
+            let c = Comp::new(i.clone(), r, &vec![i]);
+            w.reflected_colour(&c, &RenderSettings::default(), 1, 1., rng)
+        };
+        let mut rng = rng();
+        // a smooth white metal is a mirror
+        let mirror = reflected(plane(Pbr::new(1., 0.)), &mut rng);
+        assert_eq!(mirror.rounded(1), vec![0.4, 0.5, 0.3]);
+        // dielectrics only reflect what fresnel gives them
+        let glossy = reflected(plane(Pbr::new(0., 0.)), &mut rng);
+        assert!(glossy.into_iter().all(|c| c > 0. && c < 0.03));
+        // rough surfaces see a different part of the scene every time
+        let rough = plane(Pbr::new(1., 0.5));
+        let res: Vec<_> = (0..10)
+            .map(|_| reflected(Arc::clone(&rough), &mut rng))
+            .collect();
+        assert!(res.iter().any(|c| *c != res[0]));
+    }
+
+    #[test]
+    fn reflected_colour_rough() -> () {
+        let brushed = Material::default().reflective(0.5).roughness(0.4);
+        let p = Arc::new(
+            Shape::new_plane(Matrix4x4::translation(0., -1., 0.))
+                .unwrap()
+                .material(brushed),
+        );
+        let w = World::default().add_shape(Arc::clone(&p));
+        let s2 = SQRT_2 / 2.;
+        let r = Ray::new(Point::new(0., 0., -3.), Vector::new(0., -s2, s2));
+        let i = Intersection::new(p, s2 * 2.);
+        let c = Comp::new(i.clone(), r, &vec![i]);
+        let mut rng = rng();
+        let s = RenderSettings::default().glossy_samples(1);
+        let res: Vec<_> = (0..10)
+            .map(|_| w.reflected_colour(&c, &s, 1, 1., &mut rng))
+            .collect();
+        assert!(res.iter().any(|c| *c != res[0]));
+        // the sphere's reflection is blurred, darker where the mirror sees it
+        let s = s.glossy_samples(200);
+        let blurred = w.reflected_colour(&c, &s, 1, 1., &mut rng);
+        assert!(blurred.into_iter().all(|c| c > 0.));
+        assert!(blurred.into_iter().nth(1).unwrap() < 0.23792);
+    }
+
+    // counts the numbers drawn, two per rough reflection
+    struct CountingRng(StdRng, usize);
+
+    impl RngCore for CountingRng {
+        fn next_u32(&mut self) -> u32 {
+            self.1 += 1;
+            self.0.next_u32()
+        }
+
+        fn next_u64(&mut self) -> u64 {
+            self.1 += 1;
+            self.0.next_u64()
+        }
+
+        fn fill_bytes(&mut self, dest: &mut [u8]) {
+            self.1 += 1;
+            self.0.fill_bytes(dest)
+        }
+
+        fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), rand::Error> {
+            self.1 += 1;
+            self.0.try_fill_bytes(dest)
+        }
+    }
+
+    #[test]
+    fn reflected_colour_rough_facing_mirrors() -> () {
+        // only the first bounce gets all the samples, the following ones share its weight
+        let brushed = Material::default().reflective(1.).roughness(0.5);
+        let floor = Shape::id_plane().material(brushed.clone());
+        let ceiling = Shape::new_plane(Matrix4x4::translation(0., 1., 0.))
+            .unwrap()
+            .material(brushed);
+        let w = World::new(vec![Arc::new(floor), Arc::new(ceiling)], vec![]);
+        let s = RenderSettings::default().max_depth(5).glossy_samples(8);
+        let r = Ray::new(Point::new(0., 0.5, 0.), Vector::new(0., -1., 0.1).norm());
+        let mut rng = CountingRng(rng(), 0);
+        w.sample_colour_at(&r, &s, &mut rng);
+        // 8 rays at the first bounce, then one each for the 4 others
+        assert!(rng.1 <= 2 * (8 + 8 * 4), "{}", rng.1);
+    }
+
+    #[test]
+    fn glossy_samples() -> () {
+        let s = RenderSettings::default().glossy_samples(8);
+        vec![(0., 1., 1), (0.5, 1., 8), (0.5, 0.25, 2), (0.5, 0.01, 1)]
+            .iter()
+            .for_each(|(roughness, weight, exp)| {
+                assert_eq!(World::glossy_samples(*roughness, &s, *weight), *exp);
+            });
+    }
+
+    #[test]
+    fn reflected_colour_min_contribution() -> () {
+        let m = Material::default().reflective(0.5);
+        let s = Arc::new(
+            Shape::new_plane(Matrix4x4::translation(0., -1., 0.))
+                .unwrap()
+                .material(m),
+        );
+        let w = World::default().add_shape(Arc::clone(&s));
+        let s2 = SQRT_2 / 2.;
+        let r = Ray::new(Point::new(0., 0., -3.), Vector::new(0., -s2, s2));
+        let i = Intersection::new(s, s2 * 2.);
+        let c = Comp::new(i.clone(), r, &vec![i]);
+        vec![
+            (0.4, 1., false),
+            (0.6, 1., true),
+            (0.4, 0.5, true),
+            (0.25, 0.5, false),
+        ]
+        .iter()
+        .for_each(|(min, weight, cut)| {
+            let settings = RenderSettings::default().min_contribution(*min);
+            let res = w.reflected_colour(&c, &settings, 1, *weight, &mut rng());
+            assert_eq!(res == Colour::BLACK, *cut);
+        });
+    }
+
+    #[test]
+    fn reflected_colour_background() -> () {
+        let m = Material::default().reflective(0.5);
+        let s = Arc::new(
+            Shape::new_plane(Matrix4x4::translation(0., -1., 0.))
+                .unwrap()
+                .material(m),
+        );
+        let w = World::default().shapes(vec![Arc::clone(&s)]);
+        let s2 = SQRT_2 / 2.;
+        let r = Ray::new(Point::new(0., 0., -3.), Vector::new(0., -s2, s2));
+        let i = Intersection::new(s, s2 * 2.);
+        let c = Comp::new(i.clone(), r, &vec![i]);
+        // the reflected ray goes up at 45° into the sky
+        let settings = RenderSettings::default().background(Background::Gradient {
+            bottom: Colour::BLACK,
+            top: Colour::new(0., 0., 1.),
+        });
+        let res = w.reflected_colour(&c, &settings, 1, 1., &mut rng());
+        assert_eq!(res.rounded(5), vec![0., 0., 0.42678]);
+    }
+
     #[test]
     fn reflected_colour_nonreflective_mat() -> () {
-        let s1 = Rc::new(Shape::id_sphere());
-        let s2 = Rc::new(b().material(Material::default().ambient(1.)));
-        let w = World::default().shapes(vec![Rc::clone(&s1), Rc::clone(&s2)]);
+        let s1 = Arc::new(Shape::id_sphere());
+        let s2 = Arc::new(b().material(Material::default().ambient(1.)));
+        let w = World::default().shapes(vec![Arc::clone(&s1), Arc::clone(&s2)]);
         let r = Ray::new(Point::ORIGIN, Vector::new(0., 0., 1.));
-        let i = Intersection::new(Rc::clone(&s2), 1.);
+        let i = Intersection::new(Arc::clone(&s2), 1.);
         let c = Comp::new(i.clone(), r, &vec![i]);
-        let res = w.reflected_colour(&c, 1);
+        let res = w.reflected_colour(&c, &RenderSettings::default(), 1, 1., &mut rng());
         assert_eq!(res, Colour::BLACK);
     }
 
     #[test]
     fn shade_hit_in_shadow() -> () {
-        let s1 = Rc::new(Shape::id_sphere());
-        let s2 = Rc::new(
+        let s1 = Arc::new(Shape::id_sphere());
+        let s2 = Arc::new(
             Shape::new_sphere(Matrix4x4::translation(0., 0., 10.)).unwrap_or(Shape::id_sphere()),
         );
         let w = World::default()
             .lights(vec![PointLight::new(
                 Point::new(0., 0., -10.),
                 Colour::WHITE,
-            )])
-            .shapes(vec![Rc::clone(&s1), Rc::clone(&s2)]);
+            )
+            .into()])
+            .shapes(vec![Arc::clone(&s1), Arc::clone(&s2)]);
         let r = Ray::new(Point::new(0., 0., 5.), Vector::new(0., 0., 1.));
         let i = Intersection::new(s2, 4.);
         let c = Comp::new(i.clone(), r, &vec![i]);
-        let res = w.shade_hit(&c, 1);
+        let res = w.shade_hit(&c, &RenderSettings::default(), 1, 1., &mut rng());
         assert_eq!(res, Colour::new(0.1, 0.1, 0.1));
     }
 
     #[test]
-    fn is_shadowed_shape_behind_point() -> () {
+    fn shade_hit_shadows_disabled() -> () {
+        let s1 = Arc::new(Shape::id_sphere());
+        let s2 = Arc::new(
+            Shape::new_sphere(Matrix4x4::translation(0., 0., 10.)).unwrap_or(Shape::id_sphere()),
+        );
+        let w = World::default()
+            .lights(vec![PointLight::new(
+                Point::new(0., 0., -10.),
+                Colour::WHITE,
+            )
+            .into()])
+            .shapes(vec![Arc::clone(&s1), Arc::clone(&s2)]);
+        let r = Ray::new(Point::new(0., 0., 5.), Vector::new(0., 0., 1.));
+        let i = Intersection::new(s2, 4.);
+        let c = Comp::new(i.clone(), r, &vec![i]);
+        let res = w.shade_hit(
+            &c,
+            &RenderSettings::default().shadows(false),
+            1,
+            1.,
+            &mut rng(),
+        );
+        assert_eq!(res.rounded(5), vec![1.9, 1.9, 1.9]);
+    }
+
+    #[test]
+    fn intensity_at_shape_behind_point() -> () {
         let w = World::default();
         let p = Point::new(-2., 2., 2.);
-        assert!(!w.is_shadowed(p, &w.lights[0]));
+        assert_eq!(w.intensity_at(p, &w.lights[0]), 1.);
     }
 
     #[test]
-    fn is_shadowed_behind_light() -> () {
+    fn intensity_at_behind_light() -> () {
         let w = World::default();
         let p = Point::new(-20., 20., 20.);
-        assert!(!w.is_shadowed(p, &w.lights[0]));
+        assert_eq!(w.intensity_at(p, &w.lights[0]), 1.);
     }
 
     #[test]
-    fn is_shadowed_point_behind_shape() -> () {
+    fn intensity_at_point_behind_shape() -> () {
         let w = World::default();
         let p = Point::new(10., -10., 10.);
-        assert!(w.is_shadowed(p, &w.lights[0]));
+        assert_eq!(w.intensity_at(p, &w.lights[0]), 0.);
     }
 
     #[test]
-    fn is_shadowed_point_behind_shape_no_shadows() -> () {
-        let a = Rc::new(Shape::id_sphere().material(m()).no_shadows());
-        let b = Rc::new(b().no_shadows());
-        let w = World::default().shapes(vec![Rc::clone(&a), Rc::clone(&b)]);
+    fn intensity_at_point_behind_shape_no_shadows() -> () {
+        let a = Arc::new(Shape::id_sphere().material(m()).no_shadows());
+        let b = Arc::new(b().no_shadows());
+        let w = World::default().shapes(vec![Arc::clone(&a), Arc::clone(&b)]);
         let p = Point::new(10., -10., 10.);
-        assert!(!w.is_shadowed(p, &w.lights[0]));
+        assert_eq!(w.intensity_at(p, &w.lights[0]), 1.);
     }
 
     #[test]
-    fn is_shadowed_nothing_colinear() -> () {
+    fn intensity_at_nothing_colinear() -> () {
         let w = World::default();
         let p = Point::new(0., 10., 0.);
-        assert!(!w.is_shadowed(p, &w.lights[0]));
+        assert_eq!(w.intensity_at(p, &w.lights[0]), 1.);
+    }
+
+    #[test]
+    fn intensity_at_area_light() -> () {
+        let light = AreaLight::new(
+            Point::new(-0.5, -0.5, -5.),
+            Vector::new(1., 0., 0.),
+            2,
+            Vector::new(0., 1., 0.),
+            2,
+            Colour::WHITE,
+        )
+        .unwrap();
+        let w = World::default().lights(vec![light.into()]);
+        vec![
+            (Point::new(0., 0., 2.), 0.),
+            (Point::new(1., -1., 2.), 0.25),
+            (Point::new(1.5, 0., 2.), 0.5),
+            (Point::new(1.25, 1.25, 3.), 0.75),
+            (Point::new(0., 0., -2.), 1.),
+        ]
+        .iter()
+        .for_each(|(p, exp)| assert_eq!(w.intensity_at(*p, &w.lights[0]), *exp));
+    }
+
+    #[test]
+    fn intensity_at_directional_light() -> () {
+        let sun = DirectionalLight::new(Vector::new(0., -1., 0.), Colour::WHITE).unwrap();
+        let w = World::default().lights(vec![sun.into()]);
+        vec![
+            (Point::new(0., -5., 0.), 0.),
+            (Point::new(0., -500., 0.), 0.),
+            (Point::new(5., -5., 0.), 1.),
+            (Point::new(0., 5., 0.), 1.),
+        ]
+        .iter()
+        .for_each(|(p, exp)| assert_eq!(w.intensity_at(*p, &w.lights[0]), *exp));
+    }
+
+    #[test]
+    fn shade_hit_spot_light() -> () {
+        let w = World::default();
+        let r = Ray::new(Point::new(0., 0., -5.), Vector::new(0., 0., 1.));
+        let shape = Arc::clone(&w.shapes[0]);
+        let i = Intersection::new(shape, 4.);
+        let c = Comp::new(i.clone(), r, &vec![i]);
+        let s = RenderSettings::default();
+        let spot = |d: Vector| {
+            let spot = SpotLight::new(Point::new(0., 0., -10.), d, 0.1, 0.2, Colour::WHITE);
+            World::default().lights(vec![spot.unwrap().into()])
+        };
+        // aimed at the hit point, a spot lights like a point light
+        let point = World::default().lights(vec![PointLight::new(
+            Point::new(0., 0., -10.),
+            Colour::WHITE,
+        )
+        .into()]);
+        assert_eq!(
+            spot(Vector::Z).shade_hit(&c, &s, 1, 1., &mut rng()),
+            point.shade_hit(&c, &s, 1, 1., &mut rng())
+        );
+        // aimed away only the ambient term is left
+        assert_eq!(
+            spot(Vector::X)
+                .shade_hit(&c, &s, 1, 1., &mut rng())
+                .rounded(5),
+            vec![0.08, 0.1, 0.06]
+        );
+    }
+
+    #[test]
+    fn shade_hit_emissive() -> () {
+        let glow = Colour::new(0.5, 0.25, 0.);
+        let s = Arc::new(Shape::id_sphere().material(Material::default().emissive(glow)));
+        let w = World::default().shapes(vec![Arc::clone(&s)]).lights(vec![]);
+        let r = Ray::new(Point::new(0., 0., -5.), Vector::new(0., 0., 1.));
+        let i = Intersection::new(s, 4.);
+        let c = Comp::new(i.clone(), r, &vec![i]);
+        assert_eq!(
+            w.shade_hit(&c, &RenderSettings::default(), 1, 1., &mut rng()),
+            glow
+        );
+    }
+
+    #[test]
+    fn sample_colour_at_whitted() -> () {
+        let w = World::default();
+        let r = Ray::new(Point::new(0., 0., -5.), Vector::new(0., 0., 1.));
+        let s = RenderSettings::default();
+        let mut rng = sampling::pixel_rng(0, 0, 0);
+        assert_eq!(w.sample_colour_at(&r, &s, &mut rng), w.colour_at(&r, &s));
+    }
+
+    #[test]
+    fn colour_at_deterministic() -> () {
+        let rough = Material::default().reflective(1.).roughness(0.5);
+        let w = World::default().shapes(vec![Arc::new(Shape::id_plane().material(rough))]);
+        let r = Ray::new(Point::new(0., 1., -1.), Vector::new(0., -1., 1.).norm());
+        let s = RenderSettings::default().background(Background::Solid(Colour::WHITE));
+        assert_eq!(w.colour_at(&r, &s), w.colour_at(&r, &s));
+    }
+
+    #[test]
+    fn colour_at_integrator() -> () {
+        // the floor is only lit through bounces, which whitted doesn't follow
+        let ceiling = Shape::new_plane(Matrix4x4::translation(0., 2., 0.))
+            .unwrap()
+            .material(Material::default().diffuse(0.).emissive(Colour::WHITE));
+        let w = World::new(vec![Arc::new(ceiling), Arc::new(Shape::id_plane())], vec![]);
+        let r = Ray::new(Point::new(0., 1., -1.), Vector::new(0., -1., 1.).norm());
+        let s = RenderSettings::default();
+        assert_eq!(w.colour_at(&r, &s), Colour::BLACK);
+        let s = s.integrator(Integrator::Path);
+        let mut rng = sampling::pixel_rng(s.seed, 0, 0);
+        assert_eq!(w.colour_at(&r, &s), w.trace_path(&r, &s, &mut rng));
+        assert!(w.colour_at(&r, &s) != Colour::BLACK);
+    }
+
+    #[test]
+    fn trace_path_emissive() -> () {
+        let glow = Colour::new(0.5, 0.25, 0.);
+        let m = Material::default().diffuse(0.).emissive(glow);
+        let w = World::new(vec![Arc::new(Shape::id_sphere().material(m))], vec![]);
+        let s = RenderSettings::default().integrator(Integrator::Path);
+        let mut rng = sampling::pixel_rng(0, 0, 0);
+        let hit = Ray::new(Point::new(0., 0., -5.), Vector::new(0., 0., 1.));
+        assert_eq!(w.trace_path(&hit, &s, &mut rng), glow);
+        let miss = Ray::new(Point::new(0., 0., -5.), Vector::new(0., 1., 0.));
+        let s = s.background(Background::Solid(Colour::WHITE));
+        assert_eq!(w.trace_path(&miss, &s, &mut rng), Colour::WHITE);
+    }
+
+    #[test]
+    fn trace_path_furnace() -> () {
+        // a convex white surface under a white sky reflects all of it back, in a single bounce
+        let m = |albedo: f64| {
+            Material::default()
+                .colour(Colour::new(albedo, albedo, albedo))
+                .diffuse(1.)
+                .specular(0.)
+        };
+        let s = RenderSettings::default()
+            .integrator(Integrator::Path)
+            .background(Background::Solid(Colour::WHITE));
+        let r = Ray::new(Point::new(0., 0., -5.), Vector::new(0., 0., 1.));
+        let mut rng = sampling::pixel_rng(0, 0, 0);
+        vec![1., 0.5].iter().for_each(|albedo| {
+            let w = World::new(
+                vec![Arc::new(Shape::id_sphere().material(m(*albedo)))],
+                vec![],
+            );
+            (0..10).for_each(|_| {
+                assert_eq!(w.trace_path(&r, &s, &mut rng).rounded(5), vec![*albedo; 3]);
+            });
+        });
+        // inside a glowing sphere the light bounces forever, e / (1 - albedo) on average
+        let inside = Material::default()
+            .colour(Colour::new(0.5, 0.5, 0.5))
+            .diffuse(1.)
+            .specular(0.)
+            .emissive(Colour::new(0.5, 0.5, 0.5));
+        let w = World::new(vec![Arc::new(Shape::id_sphere().material(inside))], vec![]);
+        let r = Ray::new(Point::ORIGIN, Vector::new(0., 0., 1.));
+        let n = 4000;
+        let sum = (0..n).fold(Colour::BLACK, |acc, _| acc + w.trace_path(&r, &s, &mut rng));
+        let mean = sum * (1. / n as f64);
+        assert!(mean.into_iter().all(|c| (c - 1.).abs() < 0.05), "{mean:?}");
+    }
+
+    #[test]
+    fn trace_path_pbr_furnace() -> () {
+        // microfacets can lose light between themselves but never make any
+        let s = RenderSettings::default()
+            .integrator(Integrator::Path)
+            .background(Background::Solid(Colour::WHITE));
+        let r = Ray::new(Point::new(0., 0., -5.), Vector::new(0., 0., 1.));
+        let mut rng = sampling::pixel_rng(0, 0, 0);
+        vec![Pbr::new(0., 0.5), Pbr::new(0., 1.), Pbr::new(1., 0.3)]
+            .iter()
+            .for_each(|pbr| {
+                let m = Material::default().pbr(*pbr);
+                let w = World::new(vec![Arc::new(Shape::id_sphere().material(m))], vec![]);
+                let n = 2000;
+                let sum = (0..n).fold(Colour::BLACK, |acc, _| acc + w.trace_path(&r, &s, &mut rng));
+                let mean = sum * (1. / n as f64);
+                assert!(mean.into_iter().all(|c| c > 0.8 && c < 1.02), "{mean:?}");
+            });
+    }
+
+    #[test]
+    fn trace_path_indirect() -> () {
+        // nothing but a glowing ceiling, the floor is only lit through bounces
+        let ceiling = Shape::new_plane(Matrix4x4::translation(0., 2., 0.))
+            .unwrap()
+            .material(Material::default().diffuse(0.).emissive(Colour::WHITE));
+        let floor = Shape::id_plane().material(Material::default().colour(Colour::new(1., 0., 0.)));
+        let w = World::new(vec![Arc::new(ceiling), Arc::new(floor)], vec![]);
+        let r = Ray::new(Point::new(0., 1., -1.), Vector::new(0., -1., 1.).norm());
+        let s = RenderSettings::default();
+        assert_eq!(w.colour_at(&r, &s), Colour::BLACK);
+        let mut rng = sampling::pixel_rng(0, 0, 0);
+        let path = w.trace_path(&r, &s.integrator(Integrator::Path), &mut rng);
+        assert!(path.into_iter().next().unwrap() > 0.);
+        assert_eq!(path.into_iter().skip(1).collect::<Vec<_>>(), vec![0., 0.]);
+    }
+
+    #[test]
+    fn trace_path_fresnel_strengths() -> () {
+        // half reflective and half transparent, whatever the fresnel split half the light is kept
+        let m = Material::default()
+            .diffuse(0.)
+            .reflective(0.5)
+            .transparency(0.5)
+            .refractive_index(1.5);
+        let w = World::new(vec![Arc::new(Shape::id_plane().material(m))], vec![]);
+        let s = RenderSettings::default()
+            .integrator(Integrator::Path)
+            .background(Background::Solid(Colour::WHITE));
+        let r = Ray::new(Point::new(0., 1., -1.), Vector::new(0., -1., 1.).norm());
+        let mut rng = sampling::pixel_rng(0, 0, 0);
+        (0..10).for_each(|_| {
+            assert_eq!(w.trace_path(&r, &s, &mut rng).rounded(5), vec![0.5; 3]);
+        });
+    }
+
+    #[test]
+    fn trace_path_mirror_terminates() -> () {
+        // russian roulette stops the path between the inner walls of a mirror
+        let mirror = Material::default().diffuse(0.).reflective(1.);
+        let w = World::new(vec![Arc::new(Shape::id_sphere().material(mirror))], vec![]);
+        let r = Ray::new(Point::ORIGIN, Vector::new(0., 0., 1.));
+        let s = RenderSettings::default().integrator(Integrator::Path);
+        let mut rng = sampling::pixel_rng(0, 0, 0);
+        (0..100).for_each(|_| assert_eq!(w.trace_path(&r, &s, &mut rng), Colour::BLACK));
     }
 
     #[test]
     fn colour_at_inter_behind_ray() -> () {
         let outer_m = m().ambient(1.);
-        let new_outer = Rc::new(Shape::id_sphere().material(outer_m));
+        let new_outer = Arc::new(Shape::id_sphere().material(outer_m));
 
         let inner_m = Material::default().ambient(1.);
         let inner_m_c = inner_m.colour;
-        let new_inner = Rc::new(b().material(inner_m));
+        let new_inner = Arc::new(b().material(inner_m));
 
-        let w = World::default().shapes(vec![Rc::clone(&new_outer), Rc::clone(&new_inner)]);
+        let w = World::default().shapes(vec![Arc::clone(&new_outer), Arc::clone(&new_inner)]);
 
         let ray = Ray::new(Point::new(0., 0., 0.75), Vector::new(0., 0., -1.));
-        let c = w.colour_at(&ray, 1);
+        let c = w.colour_at(&ray, &RenderSettings::default().max_depth(1));
         assert_eq!(c, inner_m_c);
     }
 
@@ -433,7 +1175,7 @@ mod tests {
     fn colour_at_hit() -> () {
         let w = World::default();
         let ray = Ray::new(Point::new(0., 0., -5.), Vector::new(0., 0., 1.));
-        let c = w.colour_at(&ray, 1);
+        let c = w.colour_at(&ray, &RenderSettings::default().max_depth(1));
         assert_eq!(c.rounded(5), vec![0.38066, 0.47583, 0.2855]);
     }
 
@@ -441,21 +1183,53 @@ mod tests {
     fn colour_at_miss() -> () {
         let w = World::default();
         let ray = Ray::new(Point::new(0., 0., -5.), Vector::new(0., 1., 0.));
-        let c = w.colour_at(&ray, 1);
+        let c = w.colour_at(&ray, &RenderSettings::default().max_depth(1));
         assert_eq!(c, Colour::BLACK);
     }
 
+    #[test]
+    fn colour_at_miss_background() -> () {
+        let w = World::default();
+        let ray = Ray::new(Point::new(0., 0., -5.), Vector::new(0., 1., 0.));
+        let bg = Colour::new(0.2, 0.3, 0.4);
+        let c = w.colour_at(
+            &ray,
+            &RenderSettings::default().background(Background::Solid(bg)),
+        );
+        assert_eq!(c, bg);
+    }
+
+    #[test]
+    fn colour_at_miss_gradient() -> () {
+        let w = World::default();
+        let s = RenderSettings::default().background(Background::Gradient {
+            bottom: Colour::BLACK,
+            top: Colour::WHITE,
+        });
+        vec![
+            (Vector::new(0., 1., 0.), 1.),
+            (Vector::new(0., -1., 0.), 0.),
+            (Vector::new(1., 0., 0.), 0.5),
+        ]
+        .iter()
+        .for_each(|(d, exp)| {
+            let c = w.colour_at(&Ray::new(Point::new(0., 0., -5.), *d), &s);
+            assert_eq!(c.rounded(5), vec![*exp; 3]);
+        });
+    }
+
     #[test]
     fn shade_inside() -> () {
         let w = World::default().lights(vec![PointLight::new(
             Point::new(0., 0.25, 0.),
             Colour::WHITE,
-        )]);
+        )
+        .into()]);
         let ray = Ray::new(Point::ORIGIN, Vector::new(0., 0., 1.));
-        let s = Rc::new(b());
+        let s = Arc::new(b());
         let i = Intersection::new(s, 0.5);
         let c = Comp::new(i.clone(), ray, &vec![i]);
-        let res = w.shade_hit(&c, 1);
+        let res = w.shade_hit(&c, &RenderSettings::default(), 1, 1., &mut rng());
         assert_eq!(res.rounded(5), vec![0.90498, 0.90498, 0.90498]);
     }
 
@@ -463,13 +1237,49 @@ mod tests {
     fn shade() -> () {
         let w = World::default();
         let ray = Ray::new(Point::new(0., 0., -5.), Vector::new(0., 0., 1.));
-        let s = Rc::new(Shape::id_sphere().material(m()));
+        let s = Arc::new(Shape::id_sphere().material(m()));
         let i = Intersection::new(s, 4.);
         let c = Comp::new(i.clone(), ray, &vec![i]);
-        let res = w.shade_hit(&c, 1);
+        let res = w.shade_hit(&c, &RenderSettings::default(), 1, 1., &mut rng());
         assert_eq!(res.rounded(5), vec![0.38066, 0.47583, 0.2855]);
     }
 
+    #[test]
+    fn intersect_group() -> () {
+        let g = Shape::new_group(
+            vec![Arc::new(Shape::id_sphere()), Arc::new(b())],
+            Matrix4x4::translation(0., 0., 1.),
+        )
+        .unwrap();
+        let w = World::default().shapes(vec![Arc::new(g)]);
+        let ray = Ray::new(Point::new(0., 0., -5.), Vector::new(0., 0., 1.));
+        let is = w.intersect(&ray);
+        let ts: Vec<f64> = is.iter().map(|i| i.t).collect();
+        assert_eq!(ts, vec![5., 5.5, 6.5, 7.]);
+        assert!(is
+            .iter()
+            .all(|i| !matches!(i.shape.underlying, ShapeKind::G(_))));
+    }
+
+    #[test]
+    fn intersect_csg_difference() -> () {
+        let c = Shape::id_csg(
+            CsgOp::Difference,
+            Arc::new(Shape::id_cube()),
+            Arc::new(Shape::new_sphere(Matrix4x4::translation(0., 0., -1.)).unwrap()),
+        )
+        .material(Material::default().transparency(1.).refractive_index(1.5));
+        let w = World::default().shapes(vec![Arc::new(c)]);
+        let ray = Ray::new(Point::new(0., 0., -5.), Vector::new(0., 0., 1.));
+        let is = w.intersect(&ray);
+        let ts: Vec<f64> = is.iter().map(|i| i.t).collect();
+        assert_eq!(ts, vec![5., 6.]);
+        let comp = Comp::new(is[0].clone(), ray, &is);
+        assert_eq!(comp.indices.ratio, 1. / 1.5);
+        let comp = Comp::new(is[1].clone(), ray, &is);
+        assert_eq!(comp.indices.ratio, 1.5);
+    }
+
     #[test]
     fn intersect() -> () {
         let w = World::default();
@@ -482,6 +1292,55 @@ mod tests {
         assert_eq!(is[3].t, 6.);
     }
 
+    #[test]
+    fn intersect_bvh() -> () {
+        let w = World::default().build_bvh();
+        let ray = Ray::new(Point::new(0., 0., -5.), Vector::new(0., 0., 1.));
+        let ts: Vec<f64> = w.intersect(&ray).iter().map(|i| i.t).collect();
+        assert_eq!(ts, vec![4., 4.5, 5.5, 6.]);
+        let ray = Ray::new(Point::new(0., 0., -5.), Vector::new(0., 1., 0.));
+        assert_eq!(w.intersect(&ray), vec![]);
+    }
+
+    #[test]
+    fn send_sync() -> () {
+        fn assert_send_sync<T: Send + Sync>() {}
+        assert_send_sync::<World>();
+    }
+
+    #[test]
+    fn bvh_stats() -> () {
+        let w = World::default();
+        assert_eq!(w.bvh_stats(), None);
+        let w = w.build_bvh();
+        assert_eq!(w.bvh_stats().map(|s| s.bounded), Some(2));
+        let w = w.add_shape(Arc::new(Shape::id_plane()));
+        assert_eq!(w.bvh_stats().map(|s| s.unbounded), Some(1));
+    }
+
+    #[test]
+    fn colour_at_bvh() -> () {
+        let shapes: Vec<Arc<Shape>> = (0..10)
+            .map(|i| {
+                Arc::new(
+                    Shape::new_sphere(Matrix4x4::translation(i as f64 - 5., 0., i as f64)).unwrap(),
+                )
+            })
+            .collect();
+        let w = World::default().shapes(shapes);
+        let bvh = World::default().shapes(w.shapes.clone()).build_bvh();
+        vec![
+            Ray::new(Point::new(0., 0., -10.), Vector::new(0., 0., 1.)),
+            Ray::new(Point::new(-8., 0.5, -10.), Vector::new(0.3, 0., 1.).norm()),
+            Ray::new(Point::new(0., 5., -10.), Vector::new(0., -0.3, 1.).norm()),
+        ]
+        .iter()
+        .for_each(|r| {
+            let s = RenderSettings::default();
+            assert_eq!(bvh.colour_at(r, &s), w.colour_at(r, &s));
+        });
+    }
+
     #[test]
     fn default() -> () {
         let light = PointLight::new(Point::new(-10., 10., -10.), Colour::WHITE);
@@ -489,13 +1348,13 @@ mod tests {
             .colour(Colour::new(0.8, 1., 0.6))
             .diffuse(0.7)
             .specular(0.2);
-        let sphere1 = Rc::new(Shape::id_sphere().material(material));
-        let sphere2 = Rc::new(
+        let sphere1 = Arc::new(Shape::id_sphere().material(material));
+        let sphere2 = Arc::new(
             Shape::new_sphere(Matrix4x4::scaling(0.5, 0.5, 0.5)).unwrap_or(Shape::id_sphere()),
         );
         let w = World::default();
         assert!(w.shapes.contains(&sphere1));
         assert!(w.shapes.contains(&sphere2));
-        assert_eq!(w.lights[0], light);
+        assert_eq!(w.lights[0], light.into());
     }
 }
@@ -0,0 +1,3 @@
+pub mod obj_file;
+pub mod scene_file;
+pub mod yaml;
@@ -0,0 +1,397 @@
+use std::{fs, io, sync::Arc};
+
+use crate::{
+    math::{point::Point, vector::Vector},
+    shapes::shape::Shape,
+};
+
+#[derive(PartialEq, Debug, Clone)]
+pub enum SkipReason {
+    // statement we don't know about or don't support (vt, o, s, usemtl, ...)
+    Unsupported,
+    Malformed(String),
+}
+
+#[derive(PartialEq, Debug, Clone)]
+pub struct SkippedLine {
+    pub line: usize,
+    pub content: String,
+    pub reason: SkipReason,
+}
+
+// https://en.wikipedia.org/wiki/Wavefront_.obj_file
+#[derive(PartialEq, Debug, Clone, Default)]
+pub struct ObjFile {
+    pub vertices: Vec<Point>,
+    pub normals: Vec<Vector>,
+    // faces declared before any g statement
+    pub default_group: Vec<Arc<Shape>>,
+    // named groups, in order of first appearance
+    pub groups: Vec<(String, Vec<Arc<Shape>>)>,
+    pub skipped: Vec<SkippedLine>,
+}
+
+// vertex index and optional normal index of a face element, both 0-based
+type FaceVertex = (usize, Option<usize>);
+
+impl ObjFile {
+    pub fn from_file(path: &str) -> io::Result<Self> {
+        let content = fs::read_to_string(path)?;
+        Ok(Self::parse(&content))
+    }
+
+    pub fn parse(input: &str) -> Self {
+        let mut obj = Self::default();
+        let mut current_group: Option<usize> = None;
+
+        for (i, raw) in input.lines().enumerate() {
+            let line = i + 1;
+            let content = raw.trim();
+            let mut tokens = content.split_whitespace();
+            let res =
+                match tokens.next() {
+                    None => Ok(()),
+                    Some(comment) if comment.starts_with('#') => Ok(()),
+                    Some("v") => Self::parse_xyz(tokens)
+                        .map(|(x, y, z)| obj.vertices.push(Point::new(x, y, z))),
+                    Some("vn") => Self::parse_xyz(tokens)
+                        .map(|(x, y, z)| obj.normals.push(Vector::new(x, y, z))),
+                    Some("f") => obj
+                        .parse_face(tokens)
+                        .map(|triangles| obj.add_triangles(current_group, triangles)),
+                    Some("g") => {
+                        let name = tokens.collect::<Vec<_>>().join(" ");
+                        current_group = Some(obj.group_idx(name));
+                        Ok(())
+                    }
+                    Some(_) => Err(SkipReason::Unsupported),
+                };
+
+            if let Err(reason) = res {
+                obj.skipped.push(SkippedLine {
+                    line,
+                    content: content.to_string(),
+                    reason,
+                });
+            }
+        }
+
+        obj
+    }
+
+    // all the triangles of the file, whatever their group
+    pub fn triangles(&self) -> Vec<Arc<Shape>> {
+        self.groups
+            .iter()
+            .fold(self.default_group.clone(), |mut acc, (_, g)| {
+                acc.extend(g.iter().cloned());
+                acc
+            })
+    }
+
+    // named groups become sub-groups of the returned one
+    pub fn to_group(&self) -> Shape {
+        let mut children = self.default_group.clone();
+        children.extend(
+            self.groups
+                .iter()
+                .map(|(_, g)| Arc::new(Shape::id_group(g.clone()))),
+        );
+        Shape::id_group(children)
+    }
+
+    pub fn group(&self, name: &str) -> Option<&Vec<Arc<Shape>>> {
+        self.groups.iter().find(|(n, _)| n == name).map(|(_, g)| g)
+    }
+
+    fn parse_xyz<'a>(
+        mut tokens: impl Iterator<Item = &'a str>,
+    ) -> Result<(f64, f64, f64), SkipReason> {
+        let mut next = || -> Result<f64, SkipReason> {
+            let token = tokens
+                .next()
+                .ok_or_else(|| SkipReason::Malformed("expected 3 coordinates".to_string()))?;
+            token
+                .parse::<f64>()
+                .map_err(|e| SkipReason::Malformed(format!("invalid coordinate {token}: {e}")))
+        };
+        Ok((next()?, next()?, next()?))
+    }
+
+    // index of the named group, created if it doesn't exist yet
+    fn group_idx(&mut self, name: String) -> usize {
+        match self.groups.iter().position(|(n, _)| *n == name) {
+            Some(idx) => idx,
+            None => {
+                self.groups.push((name, Vec::new()));
+                self.groups.len() - 1
+            }
+        }
+    }
+
+    fn add_triangles(&mut self, group: Option<usize>, triangles: Vec<Arc<Shape>>) {
+        match group {
+            None => self.default_group.extend(triangles),
+            Some(idx) => self.groups[idx].1.extend(triangles),
+        }
+    }
+
+    // obj indices start at 1, negative ones are relative to the end of what's been declared
+    fn resolve(token: &str, len: usize, what: &str) -> Result<usize, SkipReason> {
+        let idx = token
+            .parse::<i64>()
+            .map_err(|e| SkipReason::Malformed(format!("invalid {what} index {token}: {e}")))?;
+        let resolved = if idx > 0 { idx - 1 } else { len as i64 + idx };
+        if idx == 0 || resolved < 0 || resolved >= len as i64 {
+            Err(SkipReason::Malformed(format!(
+                "{what} index {idx} out of range, {len} declared"
+            )))
+        } else {
+            Ok(resolved as usize)
+        }
+    }
+
+    // v, v/vt, v//vn or v/vt/vn, texture coordinates are ignored
+    fn parse_face_vertex(&self, token: &str) -> Result<FaceVertex, SkipReason> {
+        let mut parts = token.split('/');
+        let v = Self::resolve(parts.next().unwrap_or(""), self.vertices.len(), "vertex")?;
+        let _vt = parts.next();
+        let vn = match parts.next() {
+            None | Some("") => None,
+            Some(n) => Some(Self::resolve(n, self.normals.len(), "normal")?),
+        };
+        Ok((v, vn))
+    }
+
+    // polygons are triangulated as a fan around their first vertex
+    fn parse_face<'a>(
+        &self,
+        tokens: impl Iterator<Item = &'a str>,
+    ) -> Result<Vec<Arc<Shape>>, SkipReason> {
+        let face = tokens
+            .map(|t| self.parse_face_vertex(t))
+            .collect::<Result<Vec<_>, _>>()?;
+        if face.len() < 3 {
+            return Err(SkipReason::Malformed(format!(
+                "a face needs at least 3 vertices, got {}",
+                face.len()
+            )));
+        }
+
+        let triangles = (1..face.len() - 1)
+            .map(|i| {
+                let (v1, n1) = face[0];
+                let (v2, n2) = face[i];
+                let (v3, n3) = face[i + 1];
+                let (p1, p2, p3) = (self.vertices[v1], self.vertices[v2], self.vertices[v3]);
+                let shape = match (n1, n2, n3) {
+                    (Some(n1), Some(n2), Some(n3)) => Shape::id_smooth_triangle(
+                        p1,
+                        p2,
+                        p3,
+                        self.normals[n1],
+                        self.normals[n2],
+                        self.normals[n3],
+                    ),
+                    _ => Shape::id_triangle(p1, p2, p3),
+                };
+                Arc::new(shape)
+            })
+            .collect();
+        Ok(triangles)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::shapes::{shape_kind::ShapeKind, triangle::Triangle};
+
+    use super::*;
+
+    fn triangle(s: &Arc<Shape>) -> Triangle {
+        match s.underlying {
+            ShapeKind::T(t) => t,
+            ShapeKind::ST(st) => st.flat,
+            _ => panic!("not a triangle"),
+        }
+    }
+
+    #[test]
+    fn parse_malformed() -> () {
+        let obj = ObjFile::parse(
+            "v 1 2
+v 1 a 3
+v 0 0 0
+f 1 2
+f 1 5 1
+f 0 1 1",
+        );
+        assert_eq!(obj.vertices.len(), 1);
+        assert_eq!(obj.default_group.len(), 0);
+        let lines: Vec<usize> = obj.skipped.iter().map(|s| s.line).collect();
+        assert_eq!(lines, vec![1, 2, 4, 5, 6]);
+        assert!(obj
+            .skipped
+            .iter()
+            .all(|s| matches!(s.reason, SkipReason::Malformed(_))));
+    }
+
+    #[test]
+    fn parse_faces_with_normals() -> () {
+        let obj = ObjFile::parse(
+            "v 0 1 0
+v -1 0 0
+v 1 0 0
+vn -1 0 0
+vn 1 0 0
+vn 0 1 0
+vt 0 1
+f 1//3 2//1 3//2
+f 1/1/3 2/102/1 3/14/2
+f 1 2 3",
+        );
+        assert_eq!(obj.default_group.len(), 3);
+        let smooth = |s: &Arc<Shape>| match s.underlying {
+            ShapeKind::ST(st) => Some((st.n1, st.n2, st.n3)),
+            _ => None,
+        };
+        let exp = Some((
+            Vector::new(0., 1., 0.),
+            Vector::new(-1., 0., 0.),
+            Vector::new(1., 0., 0.),
+        ));
+        assert_eq!(smooth(&obj.default_group[0]), exp);
+        assert_eq!(smooth(&obj.default_group[1]), exp);
+        assert_eq!(smooth(&obj.default_group[2]), None);
+        assert_eq!(obj.skipped.len(), 1);
+        assert_eq!(obj.skipped[0].line, 7);
+        assert_eq!(obj.skipped[0].reason, SkipReason::Unsupported);
+    }
+
+    #[test]
+    fn parse_groups() -> () {
+        let obj = ObjFile::parse(
+            "v -1 1 0
+v -1 0 0
+v 1 0 0
+v 1 1 0
+g FirstGroup
+f 1 2 3
+g SecondGroup
+f 1 3 4
+g FirstGroup
+f 1 -2 -1",
+        );
+        assert!(obj.default_group.is_empty());
+        assert_eq!(obj.groups.len(), 2);
+        let first = obj.group("FirstGroup").unwrap();
+        let second = obj.group("SecondGroup").unwrap();
+        assert_eq!(first.len(), 2);
+        assert_eq!(second.len(), 1);
+        let t1 = triangle(&first[0]);
+        assert_eq!(
+            (t1.p1, t1.p2, t1.p3),
+            (obj.vertices[0], obj.vertices[1], obj.vertices[2])
+        );
+        let t2 = triangle(&second[0]);
+        assert_eq!(
+            (t2.p1, t2.p2, t2.p3),
+            (obj.vertices[0], obj.vertices[2], obj.vertices[3])
+        );
+        let t3 = triangle(&first[1]);
+        assert_eq!(
+            (t3.p1, t3.p2, t3.p3),
+            (obj.vertices[0], obj.vertices[2], obj.vertices[3])
+        );
+        assert_eq!(obj.triangles().len(), 3);
+    }
+
+    #[test]
+    fn to_group() -> () {
+        let obj = ObjFile::parse(
+            "v -1 1 0
+v -1 0 0
+v 1 0 0
+v 1 1 0
+f 1 2 3
+g FirstGroup
+f 1 2 3
+g SecondGroup
+f 1 3 4",
+        );
+        let g = obj.to_group();
+        match &g.underlying {
+            ShapeKind::G(group) => {
+                let children = group.children();
+                assert_eq!(children.len(), 3);
+                assert_eq!(children[0], obj.default_group[0]);
+                let first = Shape::id_group(obj.group("FirstGroup").unwrap().clone());
+                assert_eq!(*children[1], first);
+                let second = Shape::id_group(obj.group("SecondGroup").unwrap().clone());
+                assert_eq!(*children[2], second);
+            }
+            _ => panic!("not a group"),
+        }
+    }
+
+    #[test]
+    fn parse_polygon() -> () {
+        let obj = ObjFile::parse(
+            "v -1 1 0
+v -1 0 0
+v 1 0 0
+v 1 1 0
+v 0 2 0
+f 1 2 3 4 5",
+        );
+        assert_eq!(obj.default_group.len(), 3);
+        let vs = &obj.vertices;
+        let exp = vec![
+            (vs[0], vs[1], vs[2]),
+            (vs[0], vs[2], vs[3]),
+            (vs[0], vs[3], vs[4]),
+        ];
+        let res: Vec<(Point, Point, Point)> = obj
+            .default_group
+            .iter()
+            .map(|s| {
+                let t = triangle(s);
+                (t.p1, t.p2, t.p3)
+            })
+            .collect();
+        assert_eq!(res, exp);
+    }
+
+    #[test]
+    fn parse_vertices() -> () {
+        let obj = ObjFile::parse(
+            "# a comment
+v -1 1 0
+v -1.0000 0.5000 0.0000
+v 1 0 0
+
+vn 1 2 3",
+        );
+        assert_eq!(
+            obj.vertices,
+            vec![
+                Point::new(-1., 1., 0.),
+                Point::new(-1., 0.5, 0.),
+                Point::new(1., 0., 0.)
+            ]
+        );
+        assert_eq!(obj.normals, vec![Vector::new(1., 2., 3.)]);
+        assert!(obj.skipped.is_empty());
+    }
+
+    #[test]
+    fn parse_gibberish() -> () {
+        let obj = ObjFile::parse(
+            "There was a young lady named Bright
+who traveled much faster than light.",
+        );
+        assert_eq!(obj.skipped.len(), 2);
+        assert_eq!(obj.skipped[1].line, 2);
+        assert_eq!(obj.skipped[1].reason, SkipReason::Unsupported);
+    }
+}
@@ -0,0 +1,1374 @@
+use std::{
+    collections::HashMap,
+    fmt::{Display, Formatter},
+    fs,
+    path::Path,
+    sync::Arc,
+};
+
+use crate::{
+    math::{
+        colour::Colour, matrix::Matrix4x4, matrix_const::MatrixConst, point::Point, vector::Vector,
+    },
+    model::{
+        area_light::AreaLight,
+        background::Background,
+        camera::Camera,
+        directional_light::DirectionalLight,
+        light::{Attenuation, Light},
+        material::Material,
+        pbr::Pbr,
+        point_light::PointLight,
+        spot_light::SpotLight,
+        world::World,
+    },
+    patterns::{
+        bump::Bump,
+        checker::Checker,
+        gradient::Gradient,
+        image::{Filter, Image},
+        nested::Nested,
+        pattern::Pattern,
+        pattern_kind::PatternKind,
+        perlin::Perlin,
+        radial_gradient::RadialGradient,
+        ring::Ring,
+        solid::Solid,
+        stripe::Stripe,
+        uv_mapping::UvMapping,
+    },
+    shapes::{csg::CsgOp, shape::Shape},
+    viewer::canvas::Canvas,
+};
+
+use super::{
+    obj_file::ObjFile,
+    yaml::{Node, Value, YamlError},
+};
+
+#[derive(PartialEq, Debug, Clone)]
+pub enum SceneError {
+    Io(String),
+    Parse { line: usize, message: String },
+    NoCamera,
+}
+
+impl Display for SceneError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SceneError::Io(e) => write!(f, "{e}"),
+            SceneError::Parse { line, message } => write!(f, "line {line}: {message}"),
+            SceneError::NoCamera => write!(f, "the scene has no camera"),
+        }
+    }
+}
+
+impl std::error::Error for SceneError {}
+
+impl From<YamlError> for SceneError {
+    fn from(e: YamlError) -> Self {
+        SceneError::Parse {
+            line: e.line,
+            message: e.message,
+        }
+    }
+}
+
+type Result<T> = std::result::Result<T, SceneError>;
+
+fn err<T>(node: &Node, message: String) -> Result<T> {
+    Err(SceneError::Parse {
+        line: node.line,
+        message,
+    })
+}
+
+// yaml description of a scene, in the spirit of the ray tracer challenge's:
+//
+// - add: camera
+//   width: 100
+//   height: 50
+//   field-of-view: 1.047
+//   from: [0, 1.5, -5]
+//   to: [0, 1, 0]
+//   up: [0, 1, 0]
+// - add: light
+//   at: [-10, 10, -10]
+//   intensity: [1, 1, 1]
+// - add: background
+//   bottom: [1, 1, 1]
+//   top: [0.5, 0.7, 1]
+// - define: red
+//   value:
+//     color: [1, 0, 0]
+// - define: shiny-red
+//   extend: red
+//   value:
+//     reflective: 0.5
+// - add: sphere
+//   material: shiny-red
+//   transform:
+//     - [scale, 0.5, 0.5, 0.5]
+//     - [translate, 0, 1, 0]
+//
+// transforms are applied in the order they're listed
+pub struct SceneFile {
+    pub world: World,
+    pub camera: Camera,
+    // to be set on the render settings, black when the scene doesn't add one
+    pub background: Background,
+}
+
+impl SceneFile {
+    // obj files are looked up relative to the scene file
+    pub fn from_file(path: &str) -> Result<Self> {
+        let content = fs::read_to_string(path).map_err(|e| SceneError::Io(e.to_string()))?;
+        let base = Path::new(path).parent().unwrap_or(Path::new(""));
+        Self::parse_in(&content, base)
+    }
+
+    pub fn parse(input: &str) -> Result<Self> {
+        Self::parse_in(input, Path::new(""))
+    }
+
+    fn parse_in(input: &str, base: &Path) -> Result<Self> {
+        let root = Node::parse(input)?;
+        let items = match root.as_seq() {
+            Some(items) => items,
+            None => return err(&root, "expected a list of add and define".to_string()),
+        };
+        let mut loader = Loader {
+            base,
+            defines: HashMap::new(),
+        };
+        let mut camera = None;
+        let mut background = Background::default();
+        let mut shapes = Vec::new();
+        let mut lights = Vec::new();
+        for item in items {
+            if let Some(name) = item.get("define") {
+                loader.define(item, name)?;
+                continue;
+            }
+            let item = loader.expand(item)?;
+            match loader.str(&item, "add")? {
+                "camera" => camera = Some(loader.camera(&item)?),
+                "light" => lights.push(loader.light(&item)?),
+                "background" => background = loader.background(&item)?,
+                _ => shapes.push(Arc::new(loader.shape(&item)?)),
+            }
+        }
+        let camera = camera.ok_or(SceneError::NoCamera)?;
+        Ok(Self {
+            world: World::new(shapes, lights).build_bvh(),
+            camera,
+            background,
+        })
+    }
+}
+
+struct Loader<'a> {
+    base: &'a Path,
+    defines: HashMap<String, Node>,
+}
+
+impl<'a> Loader<'a> {
+    fn define(&mut self, item: &Node, name: &Node) -> Result<()> {
+        Self::check_keys(item, &["define", "extend", "value"])?;
+        let name = Self::scalar(name)?;
+        let value = self.extend(item)?;
+        self.defines.insert(name.to_string(), value);
+        Ok(())
+    }
+
+    // the value of a definition, merged into the one it extends if any
+    fn extend(&self, item: &Node) -> Result<Node> {
+        let value = match item.get("value") {
+            Some(v) => v.clone(),
+            None => return err(item, "missing value".to_string()),
+        };
+        match item.get("extend") {
+            None => Ok(value),
+            Some(parent) => {
+                let parent_entries = self.lookup(parent)?.as_map().map(|e| e.to_vec());
+                match (parent_entries, value.value) {
+                    (Some(mut entries), Value::Map(mut own)) => {
+                        entries.append(&mut own);
+                        Ok(Node {
+                            line: value.line,
+                            value: Value::Map(entries),
+                        })
+                    }
+                    _ => err(parent, "only mappings can be extended".to_string()),
+                }
+            }
+        }
+    }
+
+    fn lookup(&self, name: &Node) -> Result<&Node> {
+        let s = Self::scalar(name)?;
+        match self.defines.get(s) {
+            Some(n) => Ok(n),
+            None => err(name, format!("unknown definition: {s}")),
+        }
+    }
+
+    // an add referring to a defined shape takes the definition's keys, overridden by its own
+    fn expand(&self, item: &Node) -> Result<Node> {
+        let add = match item.get("add") {
+            Some(add) => add,
+            None => return err(item, "expected add or define".to_string()),
+        };
+        let name = Self::scalar(add)?;
+        match self.defines.get(name).and_then(|d| d.as_map()) {
+            Some(entries) if entries.iter().any(|(k, _)| k == "add") => {
+                let mut merged = entries.to_vec();
+                let own = item.as_map().unwrap_or(&[]);
+                merged.extend(own.iter().filter(|(k, _)| k != "add").cloned());
+                Ok(Node {
+                    line: item.line,
+                    value: Value::Map(merged),
+                })
+            }
+            _ => Ok(item.clone()),
+        }
+    }
+
+    fn check_keys(node: &Node, allowed: &[&str]) -> Result<()> {
+        let entries = match node.as_map() {
+            Some(entries) => entries,
+            None => return err(node, "expected a mapping".to_string()),
+        };
+        match entries.iter().find(|(k, _)| !allowed.contains(&k.as_str())) {
+            Some((k, v)) => err(v, format!("unknown key: {k}")),
+            None => Ok(()),
+        }
+    }
+
+    fn field<'n>(node: &'n Node, key: &str) -> Result<&'n Node> {
+        match node.get(key) {
+            Some(v) => Ok(v),
+            None => err(node, format!("missing {key}")),
+        }
+    }
+
+    fn scalar(node: &Node) -> Result<&str> {
+        match node.as_str() {
+            Some(s) => Ok(s),
+            None => err(node, "expected a single value".to_string()),
+        }
+    }
+
+    fn str<'n>(&self, node: &'n Node, key: &str) -> Result<&'n str> {
+        Self::scalar(Self::field(node, key)?)
+    }
+
+    fn number(node: &Node) -> Result<f64> {
+        let s = Self::scalar(node)?;
+        match s.parse() {
+            Ok(f) => Ok(f),
+            Err(_) => err(node, format!("not a number: {s}")),
+        }
+    }
+
+    fn size(node: &Node) -> Result<usize> {
+        let s = Self::scalar(node)?;
+        match s.parse() {
+            Ok(u) => Ok(u),
+            Err(_) => err(node, format!("not a positive integer: {s}")),
+        }
+    }
+
+    fn bool(node: &Node) -> Result<bool> {
+        match Self::scalar(node)? {
+            "true" => Ok(true),
+            "false" => Ok(false),
+            s => err(node, format!("not a boolean: {s}")),
+        }
+    }
+
+    fn numbers(node: &Node, n: usize) -> Result<Vec<f64>> {
+        match node.as_seq() {
+            Some(items) if items.len() == n => items.iter().map(Self::number).collect(),
+            _ => err(node, format!("expected {n} numbers")),
+        }
+    }
+
+    fn triple(node: &Node) -> Result<(f64, f64, f64)> {
+        let v = Self::numbers(node, 3)?;
+        Ok((v[0], v[1], v[2]))
+    }
+
+    fn point(node: &Node) -> Result<Point> {
+        Self::triple(node).map(|(x, y, z)| Point::new(x, y, z))
+    }
+
+    fn vector(node: &Node) -> Result<Vector> {
+        Self::triple(node).map(|(x, y, z)| Vector::new(x, y, z))
+    }
+
+    fn colour(node: &Node) -> Result<Colour> {
+        Self::triple(node).map(|(r, g, b)| Colour::new(r, g, b))
+    }
+
+    fn camera(&self, node: &Node) -> Result<Camera> {
+        Self::check_keys(
+            node,
+            &[
+                "add",
+                "width",
+                "height",
+                "field-of-view",
+                "from",
+                "to",
+                "up",
+                "aperture",
+                "focal-distance",
+            ],
+        )?;
+        let camera = Camera::new(
+            Self::size(Self::field(node, "width")?)?,
+            Self::size(Self::field(node, "height")?)?,
+            Self::number(Self::field(node, "field-of-view")?)?,
+        );
+        let view = Matrix4x4::view_transform(
+            Self::point(Self::field(node, "from")?)?,
+            Self::point(Self::field(node, "to")?)?,
+            Self::vector(Self::field(node, "up")?)?,
+        );
+        let aperture = node.get("aperture").map_or(Ok(0.), Self::number)?;
+        let camera = match node.get("focal-distance") {
+            None => camera,
+            Some(d) => match camera.focal_distance(Self::number(d)?) {
+                Some(c) => c,
+                None => return err(d, "focal-distance should be positive".to_string()),
+            },
+        };
+        let camera = camera.aperture(aperture);
+        match camera.transform(view) {
+            Some(c) => Ok(c),
+            None => err(node, "degenerate camera orientation".to_string()),
+        }
+    }
+
+    // the kind of light follows from its keys: a corner makes an area light, a direction
+    // alone a directional light, at and direction a spot light and at alone a point light
+    fn light(&self, node: &Node) -> Result<Light> {
+        let intensity = Self::colour(Self::field(node, "intensity")?)?;
+        let attenuation = |node: &Node| -> Result<Option<Attenuation>> {
+            node.get("attenuation")
+                .map(|a| Self::triple(a).map(|(c, l, q)| Attenuation::new(c, l, q)))
+                .transpose()
+        };
+        if node.get("corner").is_some() {
+            Self::check_keys(
+                node,
+                &[
+                    "add",
+                    "corner",
+                    "uvec",
+                    "usteps",
+                    "vvec",
+                    "vsteps",
+                    "jitter",
+                    "intensity",
+                ],
+            )?;
+            let light = AreaLight::new(
+                Self::point(Self::field(node, "corner")?)?,
+                Self::vector(Self::field(node, "uvec")?)?,
+                Self::size(Self::field(node, "usteps")?)?,
+                Self::vector(Self::field(node, "vvec")?)?,
+                Self::size(Self::field(node, "vsteps")?)?,
+                intensity,
+            );
+            let jitter = node.get("jitter").map_or(Ok(false), Self::bool)?;
+            return match light {
+                Some(l) => Ok(l.jitter(jitter).into()),
+                None => err(node, "usteps and vsteps should be positive".to_string()),
+            };
+        }
+        match (node.get("at"), node.get("direction")) {
+            (Some(at), None) => {
+                Self::check_keys(node, &["add", "at", "intensity", "attenuation"])?;
+                let light = PointLight::new(Self::point(at)?, intensity);
+                Ok(match attenuation(node)? {
+                    Some(a) => light.attenuation(a),
+                    None => light,
+                }
+                .into())
+            }
+            (None, Some(direction)) => {
+                Self::check_keys(node, &["add", "direction", "intensity"])?;
+                match DirectionalLight::new(Self::vector(direction)?, intensity) {
+                    Some(l) => Ok(l.into()),
+                    None => err(direction, "direction can't be null".to_string()),
+                }
+            }
+            (Some(at), Some(direction)) => {
+                Self::check_keys(
+                    node,
+                    &[
+                        "add",
+                        "at",
+                        "direction",
+                        "inner-angle",
+                        "outer-angle",
+                        "intensity",
+                        "attenuation",
+                    ],
+                )?;
+                let outer = Self::number(Self::field(node, "outer-angle")?)?;
+                let inner = node.get("inner-angle").map_or(Ok(outer), Self::number)?;
+                let light = SpotLight::new(
+                    Self::point(at)?,
+                    Self::vector(direction)?,
+                    inner,
+                    outer,
+                    intensity,
+                );
+                match (light, attenuation(node)?) {
+                    (Some(l), Some(a)) => Ok(l.attenuation(a).into()),
+                    (Some(l), None) => Ok(l.into()),
+                    (None, _) => err(
+                        node,
+                        "expected 0 <= inner-angle <= outer-angle <= pi and a direction"
+                            .to_string(),
+                    ),
+                }
+            }
+            (None, None) => err(node, "missing at, direction or corner".to_string()),
+        }
+    }
+
+    // a list of operations or names of defined lists, applied first to last
+    fn transform(&self, node: &Node) -> Result<Matrix4x4> {
+        self.expand_transform(node, &[])
+    }
+
+    // expanding holds the names of the lists being expanded, finding one again would never end
+    fn expand_transform(&self, node: &Node, expanding: &[&str]) -> Result<Matrix4x4> {
+        let items = match node.as_seq() {
+            Some(items) => items,
+            None => return err(node, "expected a list of transforms".to_string()),
+        };
+        items.iter().try_fold(Matrix4x4::ID, |acc, item| {
+            if let Some(name) = item.as_str() {
+                if expanding.contains(&name) {
+                    return err(item, format!("recursive definition: {name}"));
+                }
+                let expanding = [expanding, &[name]].concat();
+                return Ok(self.expand_transform(self.lookup(item)?, &expanding)? * acc);
+            }
+            let op = match item.as_seq() {
+                Some([op, ..]) => Self::scalar(op)?,
+                _ => return err(item, "expected [operation, values...]".to_string()),
+            };
+            let args = |n: usize| -> Result<Vec<f64>> {
+                let values = &item.as_seq().unwrap_or(&[])[1..];
+                if values.len() != n {
+                    return err(item, format!("{op} expects {n} values"));
+                }
+                values.iter().map(Self::number).collect()
+            };
+            Ok(match op {
+                "translate" => args(3).map(|v| acc.translate(v[0], v[1], v[2]))?,
+                "scale" => args(3).map(|v| acc.scale(v[0], v[1], v[2]))?,
+                "rotate-x" => args(1).map(|v| acc.rotate_x(v[0]))?,
+                "rotate-y" => args(1).map(|v| acc.rotate_y(v[0]))?,
+                "rotate-z" => args(1).map(|v| acc.rotate_z(v[0]))?,
+                "shear" => args(6).map(|v| acc.shear(v[0], v[1], v[2], v[3], v[4], v[5]))?,
+                _ => return err(item, format!("unknown transform: {op}")),
+            })
+        })
+    }
+
+    fn optional_transform(&self, node: &Node) -> Result<Matrix4x4> {
+        node.get("transform")
+            .map_or(Ok(Matrix4x4::ID), |t| self.transform(t))
+    }
+
+    // either the name of a definition or the material itself
+    fn material(&self, node: &Node) -> Result<Material> {
+        let node = match node.as_str() {
+            Some(_) => self.lookup(node)?,
+            None => node,
+        };
+        Self::check_keys(
+            node,
+            &[
+                "color",
+                "colour",
+                "ambient",
+                "diffuse",
+                "specular",
+                "shininess",
+                "reflective",
+                "transparency",
+                "refractive-index",
+                "pattern",
+                "bump",
+                "emissive",
+                "metallic",
+                "roughness",
+                "absorption",
+            ],
+        )?;
+        node.as_map()
+            .unwrap_or(&[])
+            .iter()
+            .try_fold(Material::default(), |m, (k, v)| {
+                Ok(match k.as_str() {
+                    "color" | "colour" => m.colour(Self::colour(v)?),
+                    "ambient" => m.ambient(Self::number(v)?),
+                    "diffuse" => m.diffuse(Self::number(v)?),
+                    "specular" => m.specular(Self::number(v)?),
+                    "shininess" => m.shininess(Self::number(v)?),
+                    "reflective" => m.reflective(Self::number(v)?),
+                    "transparency" => m.transparency(Self::number(v)?),
+                    "refractive-index" => m.refractive_index(Self::number(v)?),
+                    "bump" => m.bump(self.bump(v)?),
+                    "emissive" => m.emissive(Self::colour(v)?),
+                    // switches the material to pbr, keeping its roughness whatever the order
+                    "metallic" => {
+                        let roughness = match node.get("roughness") {
+                            Some(_) => m.roughness,
+                            None => Pbr::default().roughness,
+                        };
+                        m.pbr(Pbr::new(Self::number(v)?, roughness))
+                    }
+                    "roughness" => m.roughness(Self::number(v)?),
+                    "absorption" => m.absorption(Self::colour(v)?),
+                    _ => m.pattern(self.pattern(v)?),
+                })
+            })
+    }
+
+    fn pattern(&self, node: &Node) -> Result<Pattern> {
+        Self::check_keys(
+            node,
+            &[
+                "type",
+                "colors",
+                "colours",
+                "transform",
+                "scale",
+                "pattern",
+                "patterns",
+                "file",
+                "mapping",
+                "filter",
+            ],
+        )?;
+        let kind = self.pattern_kind(node)?;
+        match Pattern::new(kind, self.optional_transform(node)?) {
+            Some(p) => Ok(p),
+            None => err(node, "transform can't be inverted".to_string()),
+        }
+    }
+
+    // noise takes a size, height the pattern whose brightness gives the relief
+    fn bump(&self, node: &Node) -> Result<Bump> {
+        Self::check_keys(node, &["type", "size", "depth", "pattern"])?;
+        let depth = Self::number(Self::field(node, "depth")?)?;
+        match self.str(node, "type")? {
+            "noise" => match Bump::new_noise(Self::number(Self::field(node, "size")?)?, depth) {
+                Some(b) => Ok(b),
+                None => err(node, "size should be positive".to_string()),
+            },
+            "height" => Ok(Bump::height(
+                self.pattern(Self::field(node, "pattern")?)?,
+                depth,
+            )),
+            t => err(node, format!("unknown bump: {t}")),
+        }
+    }
+
+    // inner patterns of perlin and nested ones share the outer transform
+    fn pattern_kind(&self, node: &Node) -> Result<PatternKind> {
+        let two_colours = || -> Result<(Colour, Colour)> {
+            let colours = node.get("colors").or(node.get("colours"));
+            match colours.and_then(|c| c.as_seq()) {
+                Some([a, b]) => Ok((Self::colour(a)?, Self::colour(b)?)),
+                _ => err(node, "expected colors: [[r, g, b], [r, g, b]]".to_string()),
+            }
+        };
+        Ok(match self.str(node, "type")? {
+            "stripes" | "stripe" => {
+                two_colours().map(|(a, b)| PatternKind::Stripe(Stripe::new(a, b)))?
+            }
+            "gradient" => two_colours().map(|(a, b)| PatternKind::Gradient(Gradient::new(a, b)))?,
+            "rings" | "ring" => two_colours().map(|(a, b)| PatternKind::Ring(Ring::new(a, b)))?,
+            "checkers" | "checker" => {
+                two_colours().map(|(a, b)| PatternKind::Checker(Checker::new(a, b)))?
+            }
+            "radial-gradient" => two_colours()
+                .map(|(a, b)| PatternKind::RadialGradient(RadialGradient::new(a, b)))?,
+            "solid" => {
+                let colours = node.get("colors").or(node.get("colours"));
+                match colours.and_then(|c| c.as_seq()) {
+                    Some([c]) => PatternKind::Solid(Solid::new(Self::colour(c)?)),
+                    _ => return err(node, "expected colors: [[r, g, b]]".to_string()),
+                }
+            }
+            "perlin" => {
+                let inner = self.pattern_kind(Self::field(node, "pattern")?)?;
+                let scale = Self::number(Self::field(node, "scale")?)?;
+                PatternKind::Perlin(Box::new(Perlin::new(inner, scale)))
+            }
+            "nested" => {
+                let patterns = match Self::field(node, "patterns")?.as_seq() {
+                    Some(ps) if !ps.is_empty() => ps,
+                    _ => return err(node, "expected a list of patterns".to_string()),
+                };
+                let kinds = patterns
+                    .iter()
+                    .map(|p| self.pattern_kind(p))
+                    .collect::<Result<Vec<_>>>()?;
+                let nested = kinds
+                    .into_iter()
+                    .rev()
+                    .fold(None, |child: Option<Nested>, kind| {
+                        Some(match child {
+                            None => Nested::new(kind),
+                            Some(c) => Nested::with_child(kind, c),
+                        })
+                    });
+                PatternKind::Nested(Box::new(nested.unwrap_or_else(|| unreachable!())))
+            }
+            "image" => {
+                let canvas = self.canvas(Self::field(node, "file")?)?;
+                let mapping = match self.str(node, "mapping")? {
+                    "spherical" => UvMapping::Spherical,
+                    "planar" => UvMapping::Planar,
+                    "cylindrical" => UvMapping::Cylindrical,
+                    "cube" => UvMapping::Cube,
+                    m => return err(node, format!("unknown mapping: {m}")),
+                };
+                PatternKind::Image(Image::new(canvas, mapping, Self::filter(node)?))
+            }
+            t => return err(node, format!("unknown pattern: {t}")),
+        })
+    }
+
+    // ppm images are looked up relative to the scene file
+    fn canvas(&self, file: &Node) -> Result<Arc<Canvas>> {
+        let path = self.base.join(Self::scalar(file)?);
+        match Canvas::from_ppm_file(&path.to_string_lossy()) {
+            Ok(c) => Ok(Arc::new(c)),
+            Err(e) => err(file, format!("{}: {e}", path.display())),
+        }
+    }
+
+    fn filter(node: &Node) -> Result<Filter> {
+        match node.get("filter").map(Self::scalar).transpose()? {
+            None | Some("bilinear") => Ok(Filter::Bilinear),
+            Some("nearest") => Ok(Filter::Nearest),
+            Some(f) => err(node, format!("unknown filter: {f}")),
+        }
+    }
+
+    // the kind of background follows from its keys like lights: a colour, a bottom and top
+    // gradient, a single equirectangular file or six cube map faces
+    fn background(&self, node: &Node) -> Result<Background> {
+        Self::check_keys(
+            node,
+            &[
+                "add", "color", "colour", "bottom", "top", "file", "faces", "filter",
+            ],
+        )?;
+        let colour = node.get("color").or(node.get("colour"));
+        match (
+            colour,
+            node.get("bottom"),
+            node.get("file"),
+            node.get("faces"),
+        ) {
+            (Some(c), None, None, None) => Ok(Background::Solid(Self::colour(c)?)),
+            (None, Some(bottom), None, None) => Ok(Background::Gradient {
+                bottom: Self::colour(bottom)?,
+                top: Self::colour(Self::field(node, "top")?)?,
+            }),
+            (None, None, Some(file), None) => Ok(Background::equirectangular(
+                self.canvas(file)?,
+                Self::filter(node)?,
+            )),
+            (None, None, None, Some(faces)) => {
+                let faces = match faces.as_seq() {
+                    Some(fs) if fs.len() == 6 => fs
+                        .iter()
+                        .map(|f| self.canvas(f))
+                        .collect::<Result<Vec<_>>>()?,
+                    _ => {
+                        return err(
+                            faces,
+                            "expected six faces: +x, -x, +y, -y, +z, -z".to_string(),
+                        )
+                    }
+                };
+                let faces = <[Arc<Canvas>; 6]>::try_from(faces).unwrap_or_else(|_| unreachable!());
+                Ok(Background::cube_map(faces, Self::filter(node)?))
+            }
+            _ => err(
+                node,
+                "expected one of color, bottom and top, file or faces".to_string(),
+            ),
+        }
+    }
+
+    // node has already been through expand, children are expanded before recursing
+    fn shape(&self, node: &Node) -> Result<Shape> {
+        let kind = self.str(node, "add")?;
+        let common = ["add", "transform", "material", "shadow"];
+        let extra: &[&str] = match kind {
+            "cylinder" | "cone" => &["min", "max", "closed"],
+            "triangle" => &["p1", "p2", "p3"],
+            "group" => &["children"],
+            "csg" => &["operation", "left", "right"],
+            "obj" => &["file"],
+            _ => &[],
+        };
+        Self::check_keys(node, &[&common[..], extra].concat())?;
+
+        let limit = |key: &str, default: f64| node.get(key).map_or(Ok(default), Self::number);
+        let shape = match kind {
+            "sphere" => Shape::id_sphere(),
+            "plane" => Shape::id_plane(),
+            "cube" => Shape::id_cube(),
+            "cylinder" | "cone" => {
+                let min = limit("min", f64::NEG_INFINITY)?;
+                let max = limit("max", f64::INFINITY)?;
+                let closed = node.get("closed").map_or(Ok(false), Self::bool)?;
+                if kind == "cylinder" {
+                    Shape::new_cylinder(min, max, closed, Matrix4x4::ID)
+                } else {
+                    Shape::new_cone(min, max, closed, Matrix4x4::ID)
+                }
+                .unwrap_or_else(|| unreachable!("the identity can be inverted"))
+            }
+            "triangle" => Shape::id_triangle(
+                Self::point(Self::field(node, "p1")?)?,
+                Self::point(Self::field(node, "p2")?)?,
+                Self::point(Self::field(node, "p3")?)?,
+            ),
+            "group" => {
+                let children = match Self::field(node, "children")?.as_seq() {
+                    Some(children) => children
+                        .iter()
+                        .map(|c| self.shape(&self.expand(c)?).map(Arc::new))
+                        .collect::<Result<Vec<_>>>()?,
+                    None => return err(node, "expected a list of children".to_string()),
+                };
+                Shape::id_group(children)
+            }
+            "csg" => {
+                let op = match self.str(node, "operation")? {
+                    "union" => CsgOp::Union,
+                    "intersection" => CsgOp::Intersection,
+                    "difference" => CsgOp::Difference,
+                    op => return err(node, format!("unknown csg operation: {op}")),
+                };
+                let left = self.shape(&self.expand(Self::field(node, "left")?)?)?;
+                let right = self.shape(&self.expand(Self::field(node, "right")?)?)?;
+                Shape::id_csg(op, Arc::new(left), Arc::new(right))
+            }
+            "obj" => {
+                let file = self.str(node, "file")?;
+                let path = self.base.join(file);
+                match ObjFile::from_file(&path.to_string_lossy()) {
+                    Ok(obj) => obj.to_group(),
+                    Err(e) => return err(node, format!("{}: {e}", path.display())),
+                }
+            }
+            k => return err(node, format!("unknown shape: {k}")),
+        };
+
+        // groups only hand their material and shadows down when given one
+        let shape = match node.get("material") {
+            Some(m) => shape.material(self.material(m)?),
+            None => shape,
+        };
+        let shape = match node.get("shadow").map(Self::bool).transpose()? {
+            Some(true) => shape.shadows(),
+            Some(false) => shape.no_shadows(),
+            None => shape,
+        };
+        match shape.t(self.optional_transform(node)?) {
+            Some(s) => Ok(s),
+            None => err(node, "transform can't be inverted".to_string()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{env, f64::consts::FRAC_PI_2};
+
+    use crate::{
+        math::round::Round,
+        model::{ray::Ray, render_settings::RenderSettings},
+        shapes::shape_kind::ShapeKind,
+    };
+
+    use super::*;
+
+    const SCENE: &str = "
+- add: camera
+  width: 11
+  height: 11
+  field-of-view: 1.5707963267948966
+  from: [0, 0, -5]
+  to: [0, 0, 0]
+  up: [0, 1, 0]
+
+- add: light
+  at: [-10, 10, -10]
+  intensity: [1, 1, 1]
+
+- define: base
+  value:
+    color: [0.8, 1.0, 0.6]
+    diffuse: 0.7
+
+- define: outer
+  extend: base
+  value:
+    specular: 0.2
+
+- define: half
+  value:
+    - [scale, 0.5, 0.5, 0.5]
+
+- add: sphere
+  material: outer
+
+- add: sphere
+  transform:
+    - half
+";
+
+    #[test]
+    fn parse_default_world() -> () {
+        let scene = SceneFile::parse(SCENE).unwrap();
+        assert_eq!(scene.camera.hsize, 11);
+        let exp = Camera::new(11, 11, FRAC_PI_2)
+            .transform(Matrix4x4::view_transform(
+                Point::new(0., 0., -5.),
+                Point::ORIGIN,
+                Vector::new(0., 1., 0.),
+            ))
+            .unwrap();
+        assert_eq!(scene.camera, exp);
+        // same as World::default so the colour is the one from the book
+        let r = Ray::for_pixel(&scene.camera, 5, 5);
+        assert_eq!(
+            scene
+                .world
+                .colour_at(&r, &RenderSettings::default())
+                .rounded(5),
+            vec![0.38066, 0.47583, 0.2855]
+        );
+    }
+
+    #[test]
+    fn camera_lens() -> () {
+        let loader = Loader {
+            base: Path::new(""),
+            defines: HashMap::new(),
+        };
+        let node = Node::parse(
+            "add: camera
+width: 10
+height: 10
+field-of-view: 1
+from: [0, 0, -5]
+to: [0, 0, 0]
+up: [0, 1, 0]
+aperture: 0.25
+focal-distance: 5",
+        )
+        .unwrap();
+        let c = loader.camera(&node).unwrap();
+        assert_eq!((c.aperture, c.focal_distance), (0.25, 5.));
+    }
+
+    #[test]
+    fn from_file() -> () {
+        let scene = SceneFile::from_file("scenes/room.yml").unwrap();
+        assert_eq!((scene.camera.hsize, scene.camera.vsize), (500, 250));
+        assert_eq!(scene.world.bvh_stats().map(|s| s.bounded), Some(2));
+    }
+
+    #[test]
+    fn material_inline() -> () {
+        let loader = Loader {
+            base: Path::new(""),
+            defines: HashMap::new(),
+        };
+        let node =
+            Node::parse("color: [1, 0, 0]\nambient: 0.5\nreflective: 0.3\nemissive: [2, 2, 2]")
+                .unwrap();
+        let m = loader.material(&node).unwrap();
+        assert_eq!(
+            m,
+            Material::default()
+                .colour(Colour::new(1., 0., 0.))
+                .ambient(0.5)
+                .reflective(0.3)
+                .emissive(Colour::new(2., 2., 2.))
+        );
+    }
+
+    #[test]
+    fn material_absorption() -> () {
+        let loader = Loader {
+            base: Path::new(""),
+            defines: HashMap::new(),
+        };
+        let node = Node::parse("transparency: 1\nabsorption: [0, 0.5, 1]").unwrap();
+        assert_eq!(
+            loader.material(&node).unwrap(),
+            Material::default()
+                .transparency(1.)
+                .absorption(Colour::new(0., 0.5, 1.))
+        );
+    }
+
+    #[test]
+    fn material_pbr() -> () {
+        let loader = Loader {
+            base: Path::new(""),
+            defines: HashMap::new(),
+        };
+        vec![
+            (
+                "metallic: 1\nroughness: 0.2",
+                Material::default().pbr(Pbr::new(1., 0.2)),
+            ),
+            (
+                "roughness: 0.2\nmetallic: 1",
+                Material::default().pbr(Pbr::new(1., 0.2)),
+            ),
+            ("metallic: 1", Material::default().pbr(Pbr::new(1., 0.5))),
+            (
+                "metallic: 1\nroughness: 0",
+                Material::default().pbr(Pbr::new(1., 0.)),
+            ),
+            // phong materials get blurry reflections and refractions
+            ("roughness: 2", Material::default().roughness(1.)),
+        ]
+        .iter()
+        .for_each(|(src, exp)| {
+            let m = loader.material(&Node::parse(src).unwrap()).unwrap();
+            assert_eq!(m, *exp);
+        });
+    }
+
+    #[test]
+    fn material_bump() -> () {
+        let loader = Loader {
+            base: Path::new(""),
+            defines: HashMap::new(),
+        };
+        let noise = Node::parse("bump:\n  type: noise\n  size: 0.5\n  depth: 0.2").unwrap();
+        assert_eq!(
+            loader.material(&noise).unwrap(),
+            Material::default().bump(Bump::new_noise(0.5, 0.2).unwrap())
+        );
+        let height = Node::parse(
+            "bump:\n  type: height\n  depth: 0.1\n  pattern:\n    type: gradient\n    colors: [[0, 0, 0], [1, 1, 1]]",
+        )
+        .unwrap();
+        let exp = Bump::height(Pattern::id_gradient(Colour::BLACK, Colour::WHITE), 0.1);
+        assert_eq!(
+            loader.material(&height).unwrap(),
+            Material::default().bump(exp)
+        );
+    }
+
+    #[test]
+    fn area_light() -> () {
+        let loader = Loader {
+            base: Path::new(""),
+            defines: HashMap::new(),
+        };
+        let node = Node::parse(
+            "add: light
+corner: [-1, 2, 4]
+uvec: [2, 0, 0]
+vvec: [0, 2, 0]
+usteps: 4
+vsteps: 2
+jitter: true
+intensity: [1.5, 1.5, 1.5]",
+        )
+        .unwrap();
+        let exp = AreaLight::new(
+            Point::new(-1., 2., 4.),
+            Vector::new(2., 0., 0.),
+            4,
+            Vector::new(0., 2., 0.),
+            2,
+            Colour::new(1.5, 1.5, 1.5),
+        )
+        .unwrap()
+        .jitter(true);
+        assert_eq!(loader.light(&node).unwrap(), exp.into());
+    }
+
+    #[test]
+    fn lights() -> () {
+        let loader = Loader {
+            base: Path::new(""),
+            defines: HashMap::new(),
+        };
+        let at = Point::new(0., 10., 0.);
+        let down = Vector::new(0., -1., 0.);
+        let attenuation = Attenuation::new(1., 0.5, 0.25);
+        vec![
+            (
+                "{ add: light, at: [0, 10, 0], intensity: [1, 1, 1] }",
+                PointLight::new(at, Colour::WHITE).into(),
+            ),
+            (
+                "{ add: light, at: [0, 10, 0], intensity: [1, 1, 1], attenuation: [1, 0.5, 0.25] }",
+                PointLight::new(at, Colour::WHITE)
+                    .attenuation(attenuation)
+                    .into(),
+            ),
+            (
+                "{ add: light, direction: [0, -1, 0], intensity: [1, 1, 1] }",
+                DirectionalLight::new(down, Colour::WHITE).unwrap().into(),
+            ),
+            (
+                "{ add: light, at: [0, 10, 0], direction: [0, -1, 0], outer-angle: 0.5, intensity: [1, 1, 1] }",
+                SpotLight::new(at, down, 0.5, 0.5, Colour::WHITE)
+                    .unwrap()
+                    .into(),
+            ),
+            (
+                "{ add: light, at: [0, 10, 0], direction: [0, -1, 0], inner-angle: 0.25, outer-angle: 0.5, intensity: [1, 1, 1], attenuation: [1, 0.5, 0.25] }",
+                SpotLight::new(at, down, 0.25, 0.5, Colour::WHITE)
+                    .unwrap()
+                    .attenuation(attenuation)
+                    .into(),
+            ),
+        ]
+        .iter()
+        .for_each(|(input, exp): &(&str, Light)| {
+            let node = Node::parse(input).unwrap();
+            assert_eq!(loader.light(&node).unwrap(), *exp, "{input}");
+        });
+    }
+
+    #[test]
+    fn image_pattern() -> () {
+        let dir = env::temp_dir().join(format!("ray-scene-image-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("texture.ppm"), "P3 2 1 255\n255 0 0  0 0 255\n").unwrap();
+        let loader = Loader {
+            base: &dir,
+            defines: HashMap::new(),
+        };
+        let node = Node::parse("type: image\nfile: texture.ppm\nmapping: planar\nfilter: nearest")
+            .unwrap();
+        let p = loader.pattern(&node);
+        let missing = Node::parse("type: image\nfile: nope.ppm\nmapping: planar").unwrap();
+        let unknown = Node::parse("type: image\nfile: texture.ppm\nmapping: torus").unwrap();
+        let (missing, unknown) = (loader.pattern(&missing), loader.pattern(&unknown));
+        fs::remove_dir_all(&dir).unwrap();
+
+        let mut canvas = Canvas::black(2, 1);
+        canvas.update(0, 0, Colour::new(1., 0., 0.));
+        canvas.update(1, 0, Colour::new(0., 0., 1.));
+        let exp = Pattern::id_image(Arc::new(canvas), UvMapping::Planar, Filter::Nearest);
+        assert_eq!(p.unwrap(), exp);
+        assert!(
+            matches!(missing, Err(SceneError::Parse { message, .. }) if message.contains("nope.ppm"))
+        );
+        assert!(
+            matches!(unknown, Err(SceneError::Parse { message, .. }) if message == "unknown mapping: torus")
+        );
+    }
+
+    #[test]
+    fn background() -> () {
+        let dir = env::temp_dir().join(format!("ray-scene-background-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("sky.ppm"), "P3 1 1 255\n0 0 255\n").unwrap();
+        let loader = Loader {
+            base: &dir,
+            defines: HashMap::new(),
+        };
+        let parse = |input: &str| loader.background(&Node::parse(input).unwrap());
+        let solid = parse("{ add: background, color: [0.1, 0.2, 0.3] }");
+        let gradient = parse("{ add: background, bottom: [1, 1, 1], top: [0, 0, 1] }");
+        let panorama = parse("{ add: background, file: sky.ppm, filter: nearest }");
+        let cube = parse(
+            "{ add: background, faces: [sky.ppm, sky.ppm, sky.ppm, sky.ppm, sky.ppm, sky.ppm] }",
+        );
+        let few = parse("{ add: background, faces: [sky.ppm] }");
+        let both = parse("{ add: background, color: [1, 1, 1], file: sky.ppm }");
+        fs::remove_dir_all(&dir).unwrap();
+
+        let blue = || {
+            let mut c = Canvas::black(1, 1);
+            c.update(0, 0, Colour::new(0., 0., 1.));
+            Arc::new(c)
+        };
+        assert_eq!(
+            solid.unwrap(),
+            Background::Solid(Colour::new(0.1, 0.2, 0.3))
+        );
+        assert_eq!(
+            gradient.unwrap(),
+            Background::Gradient {
+                bottom: Colour::WHITE,
+                top: Colour::new(0., 0., 1.)
+            }
+        );
+        assert_eq!(
+            panorama.unwrap(),
+            Background::equirectangular(blue(), Filter::Nearest)
+        );
+        assert_eq!(
+            cube.unwrap(),
+            Background::cube_map([(); 6].map(|_| blue()), Filter::Bilinear)
+        );
+        assert!(
+            matches!(few, Err(SceneError::Parse { message, .. }) if message.starts_with("expected six faces"))
+        );
+        assert!(both.is_err());
+        // scenes without one keep the default
+        let scene = SceneFile::parse(
+            "- add: camera\n  width: 1\n  height: 1\n  field-of-view: 1\n  from: [0, 0, -5]\n  to: [0, 0, 0]\n  up: [0, 1, 0]",
+        )
+        .unwrap();
+        assert_eq!(scene.background, Background::default());
+    }
+
+    #[test]
+    fn transform_order() -> () {
+        let loader = Loader {
+            base: Path::new(""),
+            defines: HashMap::new(),
+        };
+        let node = Node::parse(
+            "- [rotate-x, 1.5707963267948966]\n- [scale, 5, 5, 5]\n- [translate, 10, 5, 7]",
+        )
+        .unwrap();
+        let t = loader.transform(&node).unwrap();
+        let exp = Matrix4x4::rotation_x(FRAC_PI_2)
+            .scale(5., 5., 5.)
+            .translate(10., 5., 7.);
+        assert_eq!(t, exp);
+    }
+
+    #[test]
+    fn parse_shapes() -> () {
+        let scene = "
+- add: camera
+  width: 10
+  height: 10
+  field-of-view: 1
+  from: [0, 0, -5]
+  to: [0, 0, 0]
+  up: [0, 1, 0]
+- add: light
+  at: [-10, 10, -10]
+  intensity: [1, 1, 1]
+- define: ball
+  value:
+    add: sphere
+    material:
+      pattern:
+        type: checkers
+        colors: [[1, 1, 1], [0, 0, 0]]
+        transform:
+          - [scale, 0.25, 0.25, 0.25]
+- add: ball
+  transform:
+    - [translate, 0, 2, 0]
+- add: group
+  shadow: false
+  children:
+    - add: cylinder
+      min: 0
+      max: 1
+      closed: true
+    - add: csg
+      operation: difference
+      left: { add: cube }
+      right: { add: ball }
+- add: plane
+";
+        let scene = SceneFile::parse(scene).unwrap();
+        let stats = scene.world.bvh_stats().unwrap();
+        assert_eq!(stats.bounded, 3);
+        assert_eq!(stats.unbounded, 1);
+        let r = Ray::new(Point::new(0., 2., -5.), Vector::new(0., 0., 1.));
+        assert_ne!(
+            scene
+                .world
+                .colour_at(&r, &RenderSettings::default().max_depth(1)),
+            Colour::BLACK
+        );
+    }
+
+    #[test]
+    fn parse_group_children() -> () {
+        let loader = Loader {
+            base: Path::new(""),
+            defines: HashMap::new(),
+        };
+        let node = Node::parse(
+            "add: group
+transform:
+  - [translate, 1, 0, 0]
+material: { ambient: 1 }
+children:
+  - add: triangle
+    p1: [0, 1, 0]
+    p2: [-1, 0, 0]
+    p3: [1, 0, 0]",
+        )
+        .unwrap();
+        let g = loader.shape(&node).unwrap();
+        match &g.underlying {
+            ShapeKind::G(group) => {
+                let child = &group.children()[0];
+                assert_eq!(child.material, Material::default().ambient(1.));
+                assert_eq!(child.inv_t, Matrix4x4::translation(-1., 0., 0.));
+            }
+            _ => panic!("not a group"),
+        }
+    }
+
+    #[test]
+    fn parse_errors() -> () {
+        let camera = "- add: camera
+  width: 10
+  height: 10
+  field-of-view: 1
+  from: [0, 0, -5]
+  to: [0, 0, 0]
+  up: [0, 1, 0]
+";
+        vec![
+            (
+                "- add: sphere\n  colour: [1, 0, 0]",
+                2,
+                "unknown key: colour",
+            ),
+            (
+                "- add: sphere\n  material: missing",
+                2,
+                "unknown definition: missing",
+            ),
+            ("- add: teapot", 1, "unknown shape: teapot"),
+            (
+                "- add: sphere\n  transform:\n    - [scale, 1, 1]",
+                3,
+                "scale expects 3 values",
+            ),
+            (
+                "- add: sphere\n  transform:\n    - [scale, 0, 0, 0]",
+                1,
+                "transform can't be inverted",
+            ),
+            (
+                "- add: light\n  at: [0, x, 0]\n  intensity: [1, 1, 1]",
+                2,
+                "not a number: x",
+            ),
+            ("- add: cone\n  closed: yes", 2, "not a boolean: yes"),
+            (
+                "- add: light\n  intensity: [1, 1, 1]",
+                1,
+                "missing at, direction or corner",
+            ),
+            (
+                "- add: light\n  direction: [0, 0, 0]\n  intensity: [1, 1, 1]",
+                2,
+                "direction can't be null",
+            ),
+            (
+                "- add: light\n  at: [0, 0, 0]\n  direction: [0, 1, 0]\n  inner-angle: 1\n  outer-angle: 0.5\n  intensity: [1, 1, 1]",
+                1,
+                "expected 0 <= inner-angle <= outer-angle <= pi and a direction",
+            ),
+            (
+                "- add: light\n  corner: [0, 0, 0]\n  uvec: [1, 0, 0]\n  usteps: 0\n  vvec: [0, 1, 0]\n  vsteps: 1\n  intensity: [1, 1, 1]",
+                1,
+                "usteps and vsteps should be positive",
+            ),
+            (
+                "- define: m\n  extend: nope\n  value:\n    ambient: 1",
+                2,
+                "unknown definition: nope",
+            ),
+            (
+                "- add: sphere\n  material:\n    pattern:\n      type: waves",
+                4,
+                "unknown pattern: waves",
+            ),
+            (
+                "- add: obj\n  file: does_not_exist.obj",
+                1,
+                "does_not_exist.obj",
+            ),
+            (
+                "- add: background\n  top: [1, 1, 1]",
+                1,
+                "expected one of color, bottom and top, file or faces",
+            ),
+            (
+                "- add: plane\n  material:\n    bump:\n      type: noise\n      size: 0\n      depth: 1",
+                4,
+                "size should be positive",
+            ),
+            (
+                "- define: a\n  value:\n    - a\n- add: sphere\n  transform:\n    - a",
+                3,
+                "recursive definition: a",
+            ),
+            (
+                "- define: a\n  value:\n    - b\n- define: b\n  value:\n    - [scale, 2, 2, 2]\n    - a\n- add: sphere\n  transform:\n    - a",
+                7,
+                "recursive definition: a",
+            ),
+            ("- [1, 2]", 1, "expected add or define"),
+        ]
+        .iter()
+        .for_each(|(input, line, message)| {
+            let input = format!("{camera}{input}");
+            match SceneFile::parse(&input) {
+                Err(SceneError::Parse {
+                    line: l,
+                    message: m,
+                }) => {
+                    assert_eq!(l, line + 7, "{input}");
+                    assert!(m.contains(message), "{m} vs {message}");
+                }
+                Err(e) => panic!("unexpected error {e}"),
+                Ok(_) => panic!("should have failed: {input}"),
+            }
+        });
+        assert_eq!(SceneFile::parse("").err(), Some(SceneError::NoCamera));
+        vec!["0", "-1"].iter().for_each(|d| {
+            let input = format!("{camera}  focal-distance: {d}");
+            match SceneFile::parse(&input) {
+                Err(SceneError::Parse { line, message }) => {
+                    assert_eq!(line, 8);
+                    assert_eq!(message, "focal-distance should be positive");
+                }
+                Err(e) => panic!("unexpected error {e}"),
+                Ok(_) => panic!("should have failed: {input}"),
+            }
+        });
+        assert!(matches!(
+            SceneFile::from_file("does_not_exist.yml"),
+            Err(SceneError::Io(_))
+        ));
+    }
+}
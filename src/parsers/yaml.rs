@@ -0,0 +1,421 @@
+// the subset of yaml used by scene files: block sequences and mappings, flow sequences
+// and mappings on a single line, plain or double quoted scalars and # comments
+
+#[derive(PartialEq, Debug, Clone)]
+pub enum Value {
+    Scalar(String),
+    Seq(Vec<Node>),
+    Map(Vec<(String, Node)>),
+}
+
+// every node remembers the line it started on to report errors
+#[derive(PartialEq, Debug, Clone)]
+pub struct Node {
+    pub line: usize,
+    pub value: Value,
+}
+
+#[derive(PartialEq, Debug, Clone)]
+pub struct YamlError {
+    pub line: usize,
+    pub message: String,
+}
+
+#[derive(PartialEq, Debug, Clone)]
+struct Line {
+    no: usize,
+    indent: usize,
+    text: String,
+}
+
+impl Node {
+    pub fn scalar(line: usize, s: &str) -> Self {
+        Self {
+            line,
+            value: Value::Scalar(s.to_string()),
+        }
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        match &self.value {
+            Value::Scalar(s) => Some(s),
+            _ => None,
+        }
+    }
+
+    pub fn as_seq(&self) -> Option<&[Node]> {
+        match &self.value {
+            Value::Seq(items) => Some(items),
+            _ => None,
+        }
+    }
+
+    pub fn as_map(&self) -> Option<&[(String, Node)]> {
+        match &self.value {
+            Value::Map(entries) => Some(entries),
+            _ => None,
+        }
+    }
+
+    // last one wins for duplicated keys, like in extended definitions
+    pub fn get(&self, key: &str) -> Option<&Node> {
+        self.as_map()
+            .and_then(|entries| entries.iter().rev().find(|(k, _)| k == key))
+            .map(|(_, v)| v)
+    }
+
+    pub fn parse(input: &str) -> Result<Node, YamlError> {
+        let mut lines: Vec<Line> = input
+            .lines()
+            .enumerate()
+            .filter_map(|(i, raw)| {
+                let text = strip_comment(raw).trim_end();
+                let trimmed = text.trim_start();
+                (!trimmed.is_empty()).then(|| Line {
+                    no: i + 1,
+                    indent: text.len() - trimmed.len(),
+                    text: trimmed.to_string(),
+                })
+            })
+            .collect();
+        if lines.is_empty() {
+            return Ok(Node {
+                line: 1,
+                value: Value::Seq(vec![]),
+            });
+        }
+        let mut idx = 0;
+        let indent = lines[0].indent;
+        let node = parse_block(&mut lines, &mut idx, indent)?;
+        match lines.get(idx) {
+            Some(l) => Err(YamlError {
+                line: l.no,
+                message: "unexpected indentation".to_string(),
+            }),
+            None => Ok(node),
+        }
+    }
+}
+
+fn strip_comment(raw: &str) -> &str {
+    let mut quoted = false;
+    for (i, c) in raw.char_indices() {
+        match c {
+            '"' => quoted = !quoted,
+            '#' if !quoted && (i == 0 || raw[..i].ends_with(char::is_whitespace)) => {
+                return &raw[..i];
+            }
+            _ => (),
+        }
+    }
+    raw
+}
+
+// position of the colon separating a key from its value, outside of any brackets or quotes
+fn key_colon(text: &str) -> Option<usize> {
+    let mut depth = 0;
+    let mut quoted = false;
+    for (i, c) in text.char_indices() {
+        match c {
+            '"' => quoted = !quoted,
+            '[' | '{' if !quoted => depth += 1,
+            ']' | '}' if !quoted => depth -= 1,
+            ':' if !quoted && depth == 0 => {
+                let rest = &text[i + 1..];
+                if rest.is_empty() || rest.starts_with(' ') {
+                    return Some(i);
+                }
+            }
+            _ => (),
+        }
+    }
+    None
+}
+
+fn is_item(text: &str) -> bool {
+    text == "-" || text.starts_with("- ")
+}
+
+fn parse_block(lines: &mut [Line], idx: &mut usize, indent: usize) -> Result<Node, YamlError> {
+    let first = lines[*idx].clone();
+    if is_item(&first.text) {
+        let mut items = Vec::new();
+        while let Some(l) = lines.get(*idx).cloned() {
+            if l.indent != indent || !is_item(&l.text) {
+                break;
+            }
+            let rest = l.text[1..].trim_start();
+            if rest.is_empty() {
+                *idx += 1;
+                items.push(parse_nested(lines, idx, indent, l.no)?);
+            } else {
+                // the item content becomes a line of its own, further indented
+                let item_indent = indent + l.text.len() - rest.len();
+                lines[*idx] = Line {
+                    no: l.no,
+                    indent: item_indent,
+                    text: rest.to_string(),
+                };
+                items.push(parse_block(lines, idx, item_indent)?);
+            }
+        }
+        Ok(Node {
+            line: first.no,
+            value: Value::Seq(items),
+        })
+    } else if key_colon(&first.text).is_some() {
+        let mut entries = Vec::new();
+        while let Some(l) = lines.get(*idx).cloned() {
+            if l.indent != indent || is_item(&l.text) {
+                break;
+            }
+            let colon = key_colon(&l.text).ok_or_else(|| YamlError {
+                line: l.no,
+                message: format!("expected a key: {}", l.text),
+            })?;
+            let key = unquote(l.text[..colon].trim());
+            let rest = l.text[colon + 1..].trim();
+            *idx += 1;
+            let value = if rest.is_empty() {
+                parse_nested(lines, idx, indent, l.no)?
+            } else {
+                parse_flow(rest, l.no)?
+            };
+            entries.push((key, value));
+        }
+        Ok(Node {
+            line: first.no,
+            value: Value::Map(entries),
+        })
+    } else {
+        *idx += 1;
+        parse_flow(&first.text, first.no)
+    }
+}
+
+// value of a key or item left empty on its own line, sequences can stay at the key's indentation
+fn parse_nested(
+    lines: &mut [Line],
+    idx: &mut usize,
+    indent: usize,
+    no: usize,
+) -> Result<Node, YamlError> {
+    match lines.get(*idx).cloned() {
+        Some(l) if l.indent > indent || (l.indent == indent && is_item(&l.text)) => {
+            parse_block(lines, idx, l.indent)
+        }
+        _ => Ok(Node::scalar(no, "")),
+    }
+}
+
+fn unquote(s: &str) -> String {
+    s.strip_prefix('"')
+        .and_then(|s| s.strip_suffix('"'))
+        .unwrap_or(s)
+        .to_string()
+}
+
+fn parse_flow(text: &str, no: usize) -> Result<Node, YamlError> {
+    let chars: Vec<char> = text.chars().collect();
+    let mut pos = 0;
+    let node = flow_value(&chars, &mut pos, no, true)?;
+    skip_spaces(&chars, &mut pos);
+    if pos < chars.len() {
+        return Err(YamlError {
+            line: no,
+            message: format!("unexpected trailing characters: {}", text),
+        });
+    }
+    Ok(node)
+}
+
+fn skip_spaces(chars: &[char], pos: &mut usize) {
+    while chars.get(*pos).is_some_and(|c| c.is_whitespace()) {
+        *pos += 1;
+    }
+}
+
+fn flow_value(chars: &[char], pos: &mut usize, no: usize, top: bool) -> Result<Node, YamlError> {
+    let err = |message: &str| YamlError {
+        line: no,
+        message: message.to_string(),
+    };
+    skip_spaces(chars, pos);
+    match chars.get(*pos) {
+        Some('[') => {
+            *pos += 1;
+            let mut items = Vec::new();
+            loop {
+                skip_spaces(chars, pos);
+                if chars.get(*pos) == Some(&']') {
+                    *pos += 1;
+                    break;
+                }
+                items.push(flow_value(chars, pos, no, false)?);
+                skip_spaces(chars, pos);
+                match chars.get(*pos) {
+                    Some(',') => *pos += 1,
+                    Some(']') => (),
+                    _ => return Err(err("expected , or ] in sequence")),
+                }
+            }
+            Ok(Node {
+                line: no,
+                value: Value::Seq(items),
+            })
+        }
+        Some('{') => {
+            *pos += 1;
+            let mut entries = Vec::new();
+            loop {
+                skip_spaces(chars, pos);
+                if chars.get(*pos) == Some(&'}') {
+                    *pos += 1;
+                    break;
+                }
+                let start = *pos;
+                while chars.get(*pos).is_some_and(|c| *c != ':') {
+                    *pos += 1;
+                }
+                if *pos >= chars.len() {
+                    return Err(err("expected : in mapping"));
+                }
+                let key: String = chars[start..*pos].iter().collect();
+                *pos += 1;
+                entries.push((unquote(key.trim()), flow_value(chars, pos, no, false)?));
+                skip_spaces(chars, pos);
+                match chars.get(*pos) {
+                    Some(',') => *pos += 1,
+                    Some('}') => (),
+                    _ => return Err(err("expected , or } in mapping")),
+                }
+            }
+            Ok(Node {
+                line: no,
+                value: Value::Map(entries),
+            })
+        }
+        Some('"') => {
+            *pos += 1;
+            let start = *pos;
+            while chars.get(*pos).is_some_and(|c| *c != '"') {
+                *pos += 1;
+            }
+            if *pos >= chars.len() {
+                return Err(err("unterminated string"));
+            }
+            let s: String = chars[start..*pos].iter().collect();
+            *pos += 1;
+            Ok(Node::scalar(no, &s))
+        }
+        _ => {
+            // outside of brackets a plain scalar runs until the end of the line
+            let start = *pos;
+            while chars
+                .get(*pos)
+                .is_some_and(|c| top || !matches!(c, ',' | ']' | '}'))
+            {
+                *pos += 1;
+            }
+            let s: String = chars[start..*pos].iter().collect();
+            Ok(Node::scalar(no, s.trim()))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn s(line: usize, v: &str) -> Node {
+        Node::scalar(line, v)
+    }
+
+    #[test]
+    fn parse_sequence_of_mappings() -> () {
+        let input = "
+# comment
+- add: camera
+  width: 100 # trailing comment
+  from: [1, 2.5, -3]
+
+- add: light
+  intensity: \"white # not a comment\"
+";
+        let node = Node::parse(input).unwrap();
+        let items = node.as_seq().unwrap();
+        assert_eq!(items.len(), 2);
+        assert_eq!(items[0].line, 3);
+        assert_eq!(items[0].get("add"), Some(&s(3, "camera")));
+        assert_eq!(items[0].get("width"), Some(&s(4, "100")));
+        assert_eq!(
+            items[0].get("from").unwrap().value,
+            Value::Seq(vec![s(5, "1"), s(5, "2.5"), s(5, "-3")])
+        );
+        assert_eq!(
+            items[1].get("intensity"),
+            Some(&s(8, "white # not a comment"))
+        );
+    }
+
+    #[test]
+    fn parse_nested_blocks() -> () {
+        let input = "- define: m
+  value:
+    color: [1, 0, 0]
+    pattern:
+      type: stripes
+  transform:
+  - [scale, 2, 2, 2]
+  - other
+  children:
+    - add: sphere
+      material: { diffuse: 0.5, color: [1, 1, 1] }
+";
+        let node = Node::parse(input).unwrap();
+        let item = &node.as_seq().unwrap()[0];
+        let value = item.get("value").unwrap();
+        assert_eq!(value.line, 3);
+        assert_eq!(
+            value.get("pattern").unwrap().get("type"),
+            Some(&s(5, "stripes"))
+        );
+        let transform = item.get("transform").unwrap().as_seq().unwrap();
+        assert_eq!(transform.len(), 2);
+        assert_eq!(transform[1], s(8, "other"));
+        let children = item.get("children").unwrap().as_seq().unwrap();
+        let material = children[0].get("material").unwrap();
+        assert_eq!(material.get("diffuse"), Some(&s(11, "0.5")));
+        assert_eq!(material.get("color").unwrap().as_seq().unwrap().len(), 3);
+    }
+
+    #[test]
+    fn get_last_key_wins() -> () {
+        let node = Node::parse("a: 1\nb: 2\na: 3").unwrap();
+        assert_eq!(node.get("a"), Some(&s(3, "3")));
+        assert_eq!(node.get("c"), None);
+    }
+
+    #[test]
+    fn parse_empty() -> () {
+        assert_eq!(
+            Node::parse("# nothing\n\n").unwrap().as_seq(),
+            Some(&[][..])
+        );
+    }
+
+    #[test]
+    fn parse_errors() -> () {
+        vec![
+            ("- a: [1, 2", 1),
+            ("- a: 1\n  b: {c 2}", 2),
+            ("- a: 1\n    b: 2", 2),
+            ("- a: \"open", 1),
+            ("a: 1\nb", 2),
+        ]
+        .iter()
+        .for_each(|(input, line)| {
+            assert_eq!(Node::parse(input).map_err(|e| e.line), Err(*line));
+        });
+    }
+}
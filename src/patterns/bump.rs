@@ -0,0 +1,142 @@
+use std::sync::Arc;
+
+use crate::{
+    math::{matrix_transpose::MatrixTranspose, point::Point, vector::Vector},
+    shapes::shape::Shape,
+};
+
+use super::{pattern::Pattern, perlin::Perlin};
+
+// tilts the shading normal to fake relief, the geometry is left untouched
+#[derive(PartialEq, Debug, Clone)]
+pub enum Bump {
+    // perlin noise in object space, size is roughly the width of a ripple
+    Noise { size: f64, depth: f64 },
+    // the brightness of the pattern is a height map, images included
+    Height { pattern: Box<Pattern>, depth: f64 },
+}
+
+impl Bump {
+    const EPS: f64 = 0.0001;
+
+    pub fn new_noise(size: f64, depth: f64) -> Option<Self> {
+        (size > 0.).then_some(Bump::Noise { size, depth })
+    }
+
+    pub fn height(pattern: Pattern, depth: f64) -> Self {
+        Bump::Height {
+            pattern: Box::new(pattern),
+            depth,
+        }
+    }
+
+    // normal is the geometric one at p, both in world space
+    // the slope of either kind is taken in object space, then carried to world space
+    // like a normal so that the relief follows the shape's rotation and scaling
+    pub fn normal(&self, shape: &Arc<Shape>, p: Point, normal: Vector) -> Vector {
+        let object_p = shape.inv_t * p;
+        let (slope, depth) = match self {
+            Bump::Noise { size, depth } => {
+                let q = object_p * (1. / size);
+                // three decorrelated noises, one per axis
+                let slope = Vector::new(
+                    Perlin::noise_w(q),
+                    Perlin::noise_w(Point::new(q.z, q.x, q.y)),
+                    Perlin::noise_w(Point::new(q.y, q.z, q.x)),
+                );
+                (slope, *depth)
+            }
+            Bump::Height { pattern, depth } => {
+                let height = |d: Vector| {
+                    let above = pattern.at_object(object_p + d).luminance();
+                    let below = pattern.at_object(object_p - d).luminance();
+                    (above - below) / (2. * Self::EPS)
+                };
+                let slope = Vector::new(
+                    height(Vector::X * Self::EPS),
+                    height(Vector::Y * Self::EPS),
+                    height(Vector::Z * Self::EPS),
+                );
+                (slope, *depth)
+            }
+        };
+        let slope = (shape.inv_t.transpose() * slope).w(0.);
+        // only the part of the slope along the surface tilts the normal
+        let along = slope - normal * slope.dot(normal);
+        (normal - along * depth).norm()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::f64::consts::PI;
+
+    use crate::{
+        math::{colour::Colour, matrix::Matrix4x4, round::Round},
+        patterns::pattern::Pattern,
+    };
+
+    use super::*;
+
+    #[test]
+    fn new_noise() -> () {
+        assert!(Bump::new_noise(0.5, 0.2).is_some());
+        assert!(Bump::new_noise(0., 0.2).is_none());
+        assert!(Bump::new_noise(-1., 0.2).is_none());
+    }
+
+    #[test]
+    fn normal_noise() -> () {
+        let s = Arc::new(Shape::new_plane(Matrix4x4::scaling(2., 2., 2.)).unwrap());
+        let b = Bump::new_noise(0.5, 0.3).unwrap();
+        // the noise is null on its lattice, here every unit in object space
+        let on_lattice = b.normal(&s, Point::new(2., 0., 4.), Vector::Y);
+        assert_eq!(on_lattice.rounded(5), Vector::Y.rounded(5));
+        let between = b.normal(&s, Point::new(0.3, 0., 0.7), Vector::Y);
+        assert!(between != Vector::Y && between.y > 0.);
+        assert_eq!(vec![between.len()].rounded(5), vec![1.]);
+        // no depth no ripples
+        let flat = Bump::new_noise(0.5, 0.).unwrap();
+        assert_eq!(
+            flat.normal(&s, Point::new(0.3, 0., 0.7), Vector::Y),
+            Vector::Y
+        );
+    }
+
+    #[test]
+    fn normal_rotated_shape() -> () {
+        // the relief turns with the shape
+        let r = Matrix4x4::rotation_y(PI / 3.);
+        let id = Arc::new(Shape::id_plane());
+        let rotated = Arc::new(Shape::new_plane(r).unwrap());
+        let p = Point::new(0.3, 0., 0.7);
+        vec![
+            Bump::new_noise(0.5, 0.3).unwrap(),
+            Bump::height(Pattern::id_gradient(Colour::BLACK, Colour::WHITE), 0.5),
+        ]
+        .iter()
+        .for_each(|b| {
+            let exp = r * b.normal(&id, p, Vector::Y);
+            let res = b.normal(&rotated, r * p, Vector::Y);
+            assert_eq!(res.rounded(5), exp.rounded(5));
+        });
+    }
+
+    #[test]
+    fn normal_height() -> () {
+        let s = Arc::new(Shape::id_plane());
+        // rising by one every unit along x
+        let b = Bump::height(Pattern::id_gradient(Colour::BLACK, Colour::WHITE), 0.5);
+        let res = b.normal(&s, Point::new(0.5, 0., 0.3), Vector::Y);
+        assert_eq!(
+            res.rounded(5),
+            Vector::new(-0.44721, 0.89443, 0.).rounded(5)
+        );
+        // a solid pattern is flat
+        let flat = Bump::height(Pattern::id_gradient(Colour::WHITE, Colour::WHITE), 0.5);
+        assert_eq!(
+            flat.normal(&s, Point::new(0.5, 0., 0.3), Vector::Y),
+            Vector::Y
+        );
+    }
+}
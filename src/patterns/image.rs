@@ -0,0 +1,123 @@
+use std::sync::Arc;
+
+use crate::{
+    math::{colour::Colour, point::Point},
+    viewer::canvas::Canvas,
+};
+
+use super::{pattern_at::PatternAt, uv_mapping::UvMapping};
+
+#[derive(PartialEq, Debug, Copy, Clone)]
+pub enum Filter {
+    // the closest pixel, blocky when magnified
+    Nearest,
+    // weighted average of the four surrounding pixels
+    Bilinear,
+}
+
+// a texture wrapped onto the shape, the canvas is shared between every pattern using it
+#[derive(PartialEq, Debug, Clone)]
+pub struct Image {
+    canvas: Arc<Canvas>,
+    mapping: UvMapping,
+    filter: Filter,
+}
+
+impl Image {
+    pub fn new(canvas: Arc<Canvas>, mapping: UvMapping, filter: Filter) -> Self {
+        Self {
+            canvas,
+            mapping,
+            filter,
+        }
+    }
+
+    // v goes up while canvas rows go down
+    pub fn uv_at(&self, u: f64, v: f64) -> Colour {
+        let x = u.clamp(0., 1.) * (self.canvas.width.max(1) - 1) as f64;
+        let y = (1. - v.clamp(0., 1.)) * (self.canvas.height.max(1) - 1) as f64;
+        let at = |x: f64, y: f64| {
+            self.canvas
+                .at(x as usize, y as usize)
+                .unwrap_or(Colour::BLACK)
+        };
+        match self.filter {
+            Filter::Nearest => at(x.round(), y.round()),
+            Filter::Bilinear => {
+                let (x0, y0) = (x.floor(), y.floor());
+                let (x1, y1) = (x.ceil(), y.ceil());
+                let (tx, ty) = (x - x0, y - y0);
+                let top = at(x0, y0) * (1. - tx) + at(x1, y0) * tx;
+                let bottom = at(x0, y1) * (1. - tx) + at(x1, y1) * tx;
+                top * (1. - ty) + bottom * ty
+            }
+        }
+    }
+}
+
+impl PatternAt for Image {
+    fn pattern_at(&self, p: Point) -> Colour {
+        let (u, v) = self.mapping.uv(p);
+        self.uv_at(u, v)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::math::round::Round;
+
+    use super::*;
+
+    // a gradient going right and a gradient going down
+    fn canvas() -> Arc<Canvas> {
+        let mut c = Canvas::black(10, 10);
+        for y in 0..10 {
+            for x in 0..10 {
+                c.update(x, y, Colour::new(x as f64 / 9., y as f64 / 9., 0.));
+            }
+        }
+        Arc::new(c)
+    }
+
+    #[test]
+    fn uv_at_nearest() -> () {
+        let image = Image::new(canvas(), UvMapping::Planar, Filter::Nearest);
+        vec![
+            (0., 0., Colour::new(0., 1., 0.)),
+            (0.3, 0., Colour::new(3. / 9., 1., 0.)),
+            (0.6, 0.3, Colour::new(5. / 9., 6. / 9., 0.)),
+            (1., 1., Colour::new(1., 0., 0.)),
+            // the edges are repeated outside of the texture
+            (1.5, -0.5, Colour::new(1., 1., 0.)),
+        ]
+        .iter()
+        .for_each(|(u, v, exp)| {
+            assert_eq!(image.uv_at(*u, *v).rounded(5), exp.rounded(5));
+        });
+    }
+
+    #[test]
+    fn uv_at_bilinear() -> () {
+        let image = Image::new(canvas(), UvMapping::Planar, Filter::Bilinear);
+        // the canvas is linear so interpolating is exact
+        vec![(0., 0.), (0.3, 0.), (0.6, 0.3), (0.123, 0.789), (1., 1.)]
+            .iter()
+            .for_each(|(u, v)| {
+                let exp = Colour::new(*u, 1. - *v, 0.);
+                assert_eq!(image.uv_at(*u, *v).rounded(5), exp.rounded(5));
+            });
+        let nearest = Image::new(canvas(), UvMapping::Planar, Filter::Nearest);
+        assert_ne!(image.uv_at(0.123, 0.789), nearest.uv_at(0.123, 0.789));
+    }
+
+    #[test]
+    fn pattern_at() -> () {
+        let image = Image::new(canvas(), UvMapping::Spherical, Filter::Nearest);
+        // the back of the sphere is on the left edge of the texture, its equator half way
+        let res = image.pattern_at(Point::new(0., 0., -1.));
+        assert_eq!(res.rounded(5), Colour::new(0., 5. / 9., 0.).rounded(5));
+        // the north pole is on the top row
+        let res = image.pattern_at(Point::new(0., 1., 0.));
+        assert_eq!(res.rounded(5), Colour::new(5. / 9., 0., 0.).rounded(5));
+    }
+}
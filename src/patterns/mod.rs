@@ -1,5 +1,7 @@
+pub mod bump;
 pub mod checker;
 pub mod gradient;
+pub mod image;
 pub mod nested;
 pub mod pattern;
 pub mod pattern_at;
@@ -10,3 +12,4 @@ pub mod ring;
 pub mod solid;
 pub mod stripe;
 pub mod test_pattern;
+pub mod uv_mapping;
@@ -1,4 +1,4 @@
-use std::rc::Rc;
+use std::sync::Arc;
 
 use crate::{
     math::{
@@ -6,11 +6,19 @@ use crate::{
         point::Point,
     },
     shapes::shape::Shape,
+    viewer::canvas::Canvas,
 };
 
 use super::{
-    checker::Checker, gradient::Gradient, pattern_at::PatternAt, pattern_kind::PatternKind,
-    radial_gradient::RadialGradient, ring::Ring, stripe::Stripe,
+    checker::Checker,
+    gradient::Gradient,
+    image::{Filter, Image},
+    pattern_at::PatternAt,
+    pattern_kind::PatternKind,
+    radial_gradient::RadialGradient,
+    ring::Ring,
+    stripe::Stripe,
+    uv_mapping::UvMapping,
 };
 
 #[derive(PartialEq, Debug, Clone)]
@@ -50,6 +58,15 @@ impl Pattern {
         Self::new(PatternKind::Checker(Checker::new(a, b)), t)
     }
 
+    pub fn new_image(
+        canvas: Arc<Canvas>,
+        mapping: UvMapping,
+        filter: Filter,
+        t: Matrix4x4,
+    ) -> Option<Self> {
+        Self::new(PatternKind::Image(Image::new(canvas, mapping, filter)), t)
+    }
+
     pub fn id(p: PatternKind) -> Self {
         Self {
             t: Matrix4x4::ID,
@@ -78,8 +95,15 @@ impl Pattern {
         Self::id(PatternKind::Checker(Checker::new(a, b)))
     }
 
-    pub fn at_shape(&self, s: Rc<Shape>, world_p: Point) -> Colour {
-        let object_p = s.inv_t * world_p;
+    pub fn id_image(canvas: Arc<Canvas>, mapping: UvMapping, filter: Filter) -> Self {
+        Self::id(PatternKind::Image(Image::new(canvas, mapping, filter)))
+    }
+
+    pub fn at_shape(&self, s: Arc<Shape>, world_p: Point) -> Colour {
+        self.at_object(s.inv_t * world_p)
+    }
+
+    pub fn at_object(&self, object_p: Point) -> Colour {
         let pattern_p = self.inv_t * object_p;
         self.underlying.pattern_at(pattern_p)
     }
@@ -93,7 +117,7 @@ mod tests {
 
     #[test]
     fn at_shape_pattern_shape_ts() -> () {
-        let s = Rc::new(Shape::new_sphere(Matrix4x4::scaling(2., 2., 2.)).unwrap());
+        let s = Arc::new(Shape::new_sphere(Matrix4x4::scaling(2., 2., 2.)).unwrap());
         let p = Pattern::new_stripe(
             Colour::WHITE,
             Colour::BLACK,
@@ -106,7 +130,7 @@ mod tests {
 
     #[test]
     fn at_shape_pattern_t() -> () {
-        let s = Rc::new(Shape::id_sphere());
+        let s = Arc::new(Shape::id_sphere());
         let p = Pattern::new_stripe(Colour::WHITE, Colour::BLACK, Matrix4x4::scaling(2., 2., 2.))
             .unwrap();
         let res = p.at_shape(s, Point::new(1.5, 0., 0.));
@@ -115,12 +139,31 @@ mod tests {
 
     #[test]
     fn at_shape_shape_t() -> () {
-        let s = Rc::new(Shape::new_sphere(Matrix4x4::scaling(2., 2., 2.)).unwrap());
+        let s = Arc::new(Shape::new_sphere(Matrix4x4::scaling(2., 2., 2.)).unwrap());
         let p = Pattern::id_stripe(Colour::WHITE, Colour::BLACK);
         let res = p.at_shape(s, Point::new(1.5, 0., 0.));
         assert_eq!(res, Colour::WHITE);
     }
 
+    #[test]
+    fn at_shape_image() -> () {
+        let mut canvas = Canvas::black(2, 2);
+        canvas.update(1, 0, Colour::WHITE);
+        let s = Arc::new(Shape::new_plane(Matrix4x4::translation(0., 1., 0.)).unwrap());
+        let p = Pattern::new_image(
+            Arc::new(canvas),
+            UvMapping::Planar,
+            Filter::Nearest,
+            Matrix4x4::scaling(2., 2., 2.),
+        )
+        .unwrap();
+        // u = 0.75, v = 0.75 once back in pattern space, the top right pixel
+        let res = p.at_shape(Arc::clone(&s), Point::new(1.5, 1., 1.5));
+        assert_eq!(res, Colour::WHITE);
+        let res = p.at_shape(s, Point::new(0.5, 1., 1.5));
+        assert_eq!(res, Colour::BLACK);
+    }
+
     #[test]
     fn id() -> () {
         let pk = PatternKind::Stripe(Stripe::new(Colour::WHITE, Colour::BLACK));
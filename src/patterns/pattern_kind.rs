@@ -1,8 +1,8 @@
 use crate::math::{colour::Colour, point::Point};
 
 use super::{
-    checker::Checker, gradient::Gradient, nested::Nested, pattern_at::PatternAt, perlin::Perlin,
-    radial_gradient::RadialGradient, ring::Ring, solid::Solid, stripe::Stripe,
+    checker::Checker, gradient::Gradient, image::Image, nested::Nested, pattern_at::PatternAt,
+    perlin::Perlin, radial_gradient::RadialGradient, ring::Ring, solid::Solid, stripe::Stripe,
     test_pattern::TestPattern,
 };
 
@@ -16,6 +16,7 @@ pub enum PatternKind {
     Solid(Solid),
     Nested(Box<Nested>),
     Perlin(Box<Perlin>),
+    Image(Image),
     Test,
 }
 
@@ -30,6 +31,7 @@ impl PatternAt for PatternKind {
             PatternKind::Solid(solid) => solid.pattern_at(p),
             PatternKind::Nested(nested) => nested.pattern_at(p),
             PatternKind::Perlin(perlin) => perlin.pattern_at(p),
+            PatternKind::Image(image) => image.pattern_at(p),
             PatternKind::Test => TestPattern {}.pattern_at(p),
         }
     }
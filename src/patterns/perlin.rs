@@ -16,7 +16,7 @@ impl Perlin {
     }
 
     // https://en.wikipedia.org/wiki/Perlin_noise
-    fn noise_w(p: Point) -> f64 {
+    pub fn noise_w(p: Point) -> f64 {
         fn smootherstep(a0: f64, a1: f64, w: f64) -> f64 {
             if w < 0. {
                 a0
@@ -0,0 +1,191 @@
+use std::f64::consts::PI;
+
+use crate::math::point::Point;
+
+// from a point in pattern space to (u, v) texture coordinates, both from 0 to 1
+#[derive(PartialEq, Debug, Copy, Clone)]
+pub enum UvMapping {
+    // around the unit sphere, u follows the longitude and v the latitude
+    Spherical,
+    // the xz plane, repeated every unit
+    Planar,
+    // around the y axis, v repeated every unit of height
+    Cylindrical,
+    // each face of the unit cube gets the whole texture
+    Cube,
+}
+
+// faces of the unit cube, in the +x, -x, +y, -y, +z, -z order of cube maps
+#[derive(PartialEq, Debug, Copy, Clone)]
+pub enum Face {
+    Right,
+    Left,
+    Up,
+    Down,
+    Front,
+    Back,
+}
+
+impl UvMapping {
+    pub fn uv(&self, p: Point) -> (f64, f64) {
+        match self {
+            UvMapping::Spherical => {
+                let radius = (p.x * p.x + p.y * p.y + p.z * p.z).sqrt();
+                let phi = (p.y / radius).acos();
+                (Self::longitude(p), 1. - phi / PI)
+            }
+            UvMapping::Planar => (p.x.rem_euclid(1.), p.z.rem_euclid(1.)),
+            UvMapping::Cylindrical => (Self::longitude(p), p.y.rem_euclid(1.)),
+            UvMapping::Cube => Face::of(p).uv(p),
+        }
+    }
+
+    // counterclockwise around y when looking from above, starting behind the origin
+    fn longitude(p: Point) -> f64 {
+        let theta = p.x.atan2(p.z);
+        1. - (theta / (2. * PI) + 0.5)
+    }
+}
+
+impl Face {
+    // the face whose axis has the largest coordinate
+    pub fn of(p: Point) -> Self {
+        let coord = p.x.abs().max(p.y.abs()).max(p.z.abs());
+        if coord == p.x {
+            Face::Right
+        } else if coord == -p.x {
+            Face::Left
+        } else if coord == p.y {
+            Face::Up
+        } else if coord == -p.y {
+            Face::Down
+        } else if coord == p.z {
+            Face::Front
+        } else {
+            Face::Back
+        }
+    }
+
+    // as seen from outside the cube, p is on the face
+    pub fn uv(&self, p: Point) -> (f64, f64) {
+        let half = |a: f64| (a + 1.).rem_euclid(2.) / 2.;
+        match self {
+            Face::Front => (half(p.x), half(p.y)),
+            Face::Back => (half(-p.x), half(p.y)),
+            Face::Left => (half(p.z), half(p.y)),
+            Face::Right => (half(-p.z), half(p.y)),
+            Face::Up => (half(p.x), half(-p.z)),
+            Face::Down => (half(p.x), half(p.z)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::f64::consts::SQRT_2;
+
+    use crate::math::round::Round;
+
+    use super::*;
+
+    fn assert_uv(m: UvMapping, cases: Vec<(Point, (f64, f64))>) {
+        cases.iter().for_each(|(p, (u, v))| {
+            let (ru, rv) = m.uv(*p);
+            assert_eq!(vec![ru, rv].rounded(5), vec![*u, *v].rounded(5), "{p}");
+        });
+    }
+
+    #[test]
+    fn uv_spherical() -> () {
+        let s2 = SQRT_2 / 2.;
+        assert_uv(
+            UvMapping::Spherical,
+            vec![
+                (Point::new(0., 0., -1.), (0., 0.5)),
+                (Point::new(1., 0., 0.), (0.25, 0.5)),
+                (Point::new(0., 0., 1.), (0.5, 0.5)),
+                (Point::new(-1., 0., 0.), (0.75, 0.5)),
+                (Point::new(0., 1., 0.), (0.5, 1.)),
+                (Point::new(0., -1., 0.), (0.5, 0.)),
+                (Point::new(s2, s2, 0.), (0.25, 0.75)),
+            ],
+        );
+    }
+
+    #[test]
+    fn uv_planar() -> () {
+        assert_uv(
+            UvMapping::Planar,
+            vec![
+                (Point::new(0.25, 0., 0.5), (0.25, 0.5)),
+                (Point::new(0.25, 0., -0.25), (0.25, 0.75)),
+                (Point::new(0.25, 0.5, -0.25), (0.25, 0.75)),
+                (Point::new(1.25, 0., 0.5), (0.25, 0.5)),
+                (Point::new(0.25, 0., -1.75), (0.25, 0.25)),
+                (Point::new(1., 0., -1.), (0., 0.)),
+                (Point::new(0., 0., 0.), (0., 0.)),
+            ],
+        );
+    }
+
+    #[test]
+    fn uv_cylindrical() -> () {
+        let s2 = SQRT_2 / 2.;
+        assert_uv(
+            UvMapping::Cylindrical,
+            vec![
+                (Point::new(0., 0., -1.), (0., 0.)),
+                (Point::new(0., 0.5, -1.), (0., 0.5)),
+                (Point::new(0., 1., -1.), (0., 0.)),
+                (Point::new(s2, 0.5, -s2), (0.125, 0.5)),
+                (Point::new(1., 0.5, 0.), (0.25, 0.5)),
+                (Point::new(s2, 0.5, s2), (0.375, 0.5)),
+                (Point::new(0., -0.25, 1.), (0.5, 0.75)),
+                (Point::new(-s2, 0.5, s2), (0.625, 0.5)),
+                (Point::new(-1., 1.25, 0.), (0.75, 0.25)),
+                (Point::new(-s2, 0.5, -s2), (0.875, 0.5)),
+            ],
+        );
+    }
+
+    #[test]
+    fn face_of() -> () {
+        vec![
+            (Point::new(-1., 0.5, -0.25), Face::Left),
+            (Point::new(1.1, -0.75, 0.8), Face::Right),
+            (Point::new(0.1, 0.6, 0.9), Face::Front),
+            (Point::new(-0.7, 0., -2.), Face::Back),
+            (Point::new(0.5, 1., 0.9), Face::Up),
+            (Point::new(-0.2, -1.3, 1.1), Face::Down),
+        ]
+        .iter()
+        .for_each(|(p, exp)| assert_eq!(Face::of(*p), *exp));
+    }
+
+    #[test]
+    fn uv_cube() -> () {
+        assert_uv(
+            UvMapping::Cube,
+            vec![
+                // front
+                (Point::new(-0.5, 0.5, 1.), (0.25, 0.75)),
+                (Point::new(0.5, -0.5, 1.), (0.75, 0.25)),
+                // back
+                (Point::new(0.5, 0.5, -1.), (0.25, 0.75)),
+                (Point::new(-0.5, -0.5, -1.), (0.75, 0.25)),
+                // left
+                (Point::new(-1., 0.5, -0.5), (0.25, 0.75)),
+                (Point::new(-1., -0.5, 0.5), (0.75, 0.25)),
+                // right
+                (Point::new(1., 0.5, 0.5), (0.25, 0.75)),
+                (Point::new(1., -0.5, -0.5), (0.75, 0.25)),
+                // up
+                (Point::new(-0.5, 1., -0.5), (0.25, 0.75)),
+                (Point::new(0.5, 1., 0.5), (0.75, 0.25)),
+                // down
+                (Point::new(-0.5, -1., 0.5), (0.25, 0.75)),
+                (Point::new(0.5, -1., -0.5), (0.75, 0.25)),
+            ],
+        );
+    }
+}
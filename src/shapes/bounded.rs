@@ -0,0 +1,6 @@
+use super::bounds::Bounds;
+
+pub trait Bounded {
+    // in object space, except for groups and csgs whose children are already in world space
+    fn bounds(&self) -> Bounds;
+}
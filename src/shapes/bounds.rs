@@ -0,0 +1,213 @@
+use crate::{
+    math::{matrix::Matrix4x4, point::Point},
+    model::ray::Ray,
+};
+
+// axis-aligned bounding box
+#[derive(PartialEq, Debug, Copy, Clone)]
+pub struct Bounds {
+    pub min: Point,
+    pub max: Point,
+}
+
+impl Bounds {
+    pub const EMPTY: Bounds = Bounds {
+        min: Point::new(f64::INFINITY, f64::INFINITY, f64::INFINITY),
+        max: Point::new(f64::NEG_INFINITY, f64::NEG_INFINITY, f64::NEG_INFINITY),
+    };
+
+    pub fn new(min: Point, max: Point) -> Self {
+        Self { min, max }
+    }
+
+    pub fn from_points(points: &[Point]) -> Self {
+        points.iter().fold(Self::EMPTY, |acc, p| acc.include(*p))
+    }
+
+    pub fn include(self, p: Point) -> Self {
+        Self::new(
+            Point::new(
+                self.min.x.min(p.x),
+                self.min.y.min(p.y),
+                self.min.z.min(p.z),
+            ),
+            Point::new(
+                self.max.x.max(p.x),
+                self.max.y.max(p.y),
+                self.max.z.max(p.z),
+            ),
+        )
+    }
+
+    pub fn merge(self, o: Bounds) -> Self {
+        self.include(o.min).include(o.max)
+    }
+
+    pub fn is_finite(&self) -> bool {
+        [self.min, self.max]
+            .iter()
+            .all(|p| p.x.is_finite() && p.y.is_finite() && p.z.is_finite())
+    }
+
+    pub fn centre(&self) -> Point {
+        Point::new(
+            (self.min.x + self.max.x) / 2.,
+            (self.min.y + self.max.y) / 2.,
+            (self.min.z + self.max.z) / 2.,
+        )
+    }
+
+    // 0 for x, 1 for y, 2 for z
+    pub fn largest_axis(&self) -> usize {
+        let (dx, dy, dz) = (
+            self.max.x - self.min.x,
+            self.max.y - self.min.y,
+            self.max.z - self.min.z,
+        );
+        if dx >= dy && dx >= dz {
+            0
+        } else if dy >= dz {
+            1
+        } else {
+            2
+        }
+    }
+
+    // https://www.realtimerendering.com/resources/GraphicsGems/gems/TransBox.c
+    // done per matrix element rather than per corner so that infinite bounds don't turn into NaN
+    pub fn transform(&self, t: Matrix4x4) -> Self {
+        if *self == Self::EMPTY {
+            return *self;
+        }
+        let min = [self.min.x, self.min.y, self.min.z];
+        let max = [self.max.x, self.max.y, self.max.z];
+        let mut new_min = [t[(0, 3)], t[(1, 3)], t[(2, 3)]];
+        let mut new_max = new_min;
+        for i in 0..3 {
+            for j in 0..3 {
+                if t[(i, j)] == 0. {
+                    continue;
+                }
+                let a = t[(i, j)] * min[j];
+                let b = t[(i, j)] * max[j];
+                new_min[i] += a.min(b);
+                new_max[i] += a.max(b);
+            }
+        }
+        Self::new(
+            Point::new(new_min[0], new_min[1], new_min[2]),
+            Point::new(new_max[0], new_max[1], new_max[2]),
+        )
+    }
+
+    // https://en.wikipedia.org/wiki/Slab_method
+    pub fn intersects(&self, r: &Ray) -> bool {
+        fn check_axis(min: f64, max: f64, origin: f64, direction: f64) -> (f64, f64) {
+            let inv = 1. / direction;
+            let t1 = (min - origin) * inv;
+            let t2 = (max - origin) * inv;
+            // NaN when the ray lies on a slab boundary, min/max then pick the other value
+            (t1.min(t2), t1.max(t2))
+        }
+
+        let (xtmin, xtmax) = check_axis(self.min.x, self.max.x, r.origin.x, r.direction.x);
+        let (ytmin, ytmax) = check_axis(self.min.y, self.max.y, r.origin.y, r.direction.y);
+        let (ztmin, ztmax) = check_axis(self.min.z, self.max.z, r.origin.z, r.direction.z);
+        let tmin = xtmin.max(ytmin).max(ztmin);
+        let tmax = xtmax.min(ytmax).min(ztmax);
+        tmin <= tmax && tmax >= 0.
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::f64::consts::FRAC_PI_4;
+
+    use crate::math::{round::Round, vector::Vector};
+
+    use super::*;
+
+    #[test]
+    fn intersects() -> () {
+        vec![
+            (Point::new(5., 0.5, 0.), Vector::new(-1., 0., 0.), true),
+            (Point::new(-5., 0.5, 0.), Vector::new(1., 0., 0.), true),
+            (Point::new(0.5, 5., 0.), Vector::new(0., -1., 0.), true),
+            (Point::new(0.5, 0., 5.), Vector::new(0., 0., -1.), true),
+            (Point::new(0., 0.5, 0.), Vector::new(0., 0., 1.), true),
+            (Point::new(-2., 0., 0.), Vector::new(2., 4., 6.), false),
+            (Point::new(0., -2., 0.), Vector::new(6., 2., 4.), false),
+            (Point::new(2., 0., 2.), Vector::new(0., 0., -1.), false),
+            (Point::new(2., 2., 0.), Vector::new(-1., 0., 0.), false),
+            (Point::new(0., 0., 5.), Vector::new(0., 0., 1.), false),
+        ]
+        .iter()
+        .for_each(|(origin, direction, exp)| {
+            let b = Bounds::new(Point::new(-1., -1., -1.), Point::new(1., 1., 1.));
+            let r = Ray::new(*origin, direction.norm());
+            assert_eq!(b.intersects(&r), *exp);
+        });
+    }
+
+    #[test]
+    fn intersects_infinite() -> () {
+        let b = Bounds::new(
+            Point::new(f64::NEG_INFINITY, 0., f64::NEG_INFINITY),
+            Point::new(f64::INFINITY, 0., f64::INFINITY),
+        );
+        let hit = Ray::new(Point::new(0., 1., 0.), Vector::new(0., -1., 0.));
+        assert!(b.intersects(&hit));
+        let miss = Ray::new(Point::new(0., 1., 0.), Vector::new(0., 1., 0.));
+        assert!(!b.intersects(&miss));
+    }
+
+    #[test]
+    fn transform() -> () {
+        let b = Bounds::new(Point::new(-1., -1., -1.), Point::new(1., 1., 1.));
+        let t = Matrix4x4::rotation_y(FRAC_PI_4).rotate_x(FRAC_PI_4);
+        let res = b.transform(t);
+        assert_eq!(res.min.rounded(4), vec![-1.4142, -1.7071, -1.7071, 1.]);
+        assert_eq!(res.max.rounded(4), vec![1.4142, 1.7071, 1.7071, 1.]);
+        let translated = b.transform(Matrix4x4::translation(1., 2., 3.));
+        assert_eq!(translated.min, Point::new(0., 1., 2.));
+        assert_eq!(translated.max, Point::new(2., 3., 4.));
+    }
+
+    #[test]
+    fn transform_infinite() -> () {
+        let b = Bounds::new(
+            Point::new(f64::NEG_INFINITY, 0., f64::NEG_INFINITY),
+            Point::new(f64::INFINITY, 0., f64::INFINITY),
+        );
+        let res = b.transform(Matrix4x4::translation(0., 1., 0.));
+        assert_eq!(res.min.y, 1.);
+        assert_eq!(res.max.y, 1.);
+        assert!(!res.is_finite());
+        let rotated = b.transform(Matrix4x4::rotation_x(FRAC_PI_4));
+        assert!(rotated.min.into_iter().all(|c| !c.is_nan()));
+    }
+
+    #[test]
+    fn merge() -> () {
+        let b1 = Bounds::new(Point::new(-5., -2., 0.), Point::new(7., 4., 4.));
+        let b2 = Bounds::new(Point::new(8., -7., -2.), Point::new(14., 2., 8.));
+        let res = b1.merge(b2);
+        assert_eq!(res.min, Point::new(-5., -7., -2.));
+        assert_eq!(res.max, Point::new(14., 4., 8.));
+        assert_eq!(res.largest_axis(), 0);
+        assert_eq!(Bounds::EMPTY.merge(b1), b1);
+    }
+
+    #[test]
+    fn from_points() -> () {
+        let b = Bounds::from_points(&[
+            Point::new(-5., 2., 0.),
+            Point::new(7., 0., -3.),
+            Point::new(0., 0., 0.),
+        ]);
+        assert_eq!(b.min, Point::new(-5., 0., -3.));
+        assert_eq!(b.max, Point::new(7., 2., 0.));
+        assert_eq!(b.centre(), Point::new(1., 1., -1.5));
+        assert!(b.is_finite());
+    }
+}
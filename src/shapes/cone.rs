@@ -0,0 +1,196 @@
+use crate::{
+    math::{point::Point, vector::Vector},
+    model::ray::Ray,
+};
+
+use super::{bounded::Bounded, bounds::Bounds, intersect::Intersect, normal::Normal};
+
+// double-napped cone, the radius at any y is |y|
+#[derive(PartialEq, Debug, Copy, Clone)]
+pub struct Cone {
+    min: f64,
+    max: f64,
+    closed: bool,
+}
+
+impl Cone {
+    const EPS: f64 = 0.00001;
+
+    pub fn new(min: f64, max: f64, closed: bool) -> Self {
+        Self { min, max, closed }
+    }
+
+    fn check_cap(r: &Ray, t: f64, radius: f64) -> bool {
+        let x = r.origin.x + t * r.direction.x;
+        let z = r.origin.z + t * r.direction.z;
+        x.powf(2.) + z.powf(2.) <= radius.powf(2.)
+    }
+
+    fn intersect_caps(&self, r: &Ray, xs: &mut Vec<f64>) {
+        if !self.closed || r.direction.y.abs() < Self::EPS {
+            return;
+        }
+
+        let t_min = (self.min - r.origin.y) / r.direction.y;
+        if Self::check_cap(r, t_min, self.min) {
+            xs.push(t_min);
+        }
+        let t_max = (self.max - r.origin.y) / r.direction.y;
+        if Self::check_cap(r, t_max, self.max) {
+            xs.push(t_max);
+        }
+    }
+
+    fn push_if_within(&self, r: &Ray, t: f64, xs: &mut Vec<f64>) {
+        let y = r.origin.y + t * r.direction.y;
+        if self.min < y && y < self.max {
+            xs.push(t);
+        }
+    }
+}
+
+impl Default for Cone {
+    fn default() -> Self {
+        Self::new(f64::NEG_INFINITY, f64::INFINITY, false)
+    }
+}
+
+impl Normal for Cone {
+    fn normal_at(&self, object_point: Point) -> Vector {
+        let dist = object_point.x.powf(2.) + object_point.z.powf(2.);
+        if dist < self.max.powf(2.) && object_point.y >= self.max - Self::EPS {
+            Vector::new(0., 1., 0.)
+        } else if dist < self.min.powf(2.) && object_point.y <= self.min + Self::EPS {
+            Vector::new(0., -1., 0.)
+        } else {
+            let y = if object_point.y > 0. {
+                -dist.sqrt()
+            } else {
+                dist.sqrt()
+            };
+            Vector::new(object_point.x, y, object_point.z)
+        }
+    }
+}
+
+impl Intersect for Cone {
+    fn intersect(&self, r: &Ray) -> Vec<f64> {
+        let mut xs = Vec::new();
+        let a = r.direction.x.powf(2.) - r.direction.y.powf(2.) + r.direction.z.powf(2.);
+        let b = 2. * r.origin.x * r.direction.x - 2. * r.origin.y * r.direction.y
+            + 2. * r.origin.z * r.direction.z;
+        let c = r.origin.x.powf(2.) - r.origin.y.powf(2.) + r.origin.z.powf(2.);
+
+        if a.abs() < Self::EPS {
+            // ray parallel to one of the halves, at most one wall intersection
+            if b.abs() >= Self::EPS {
+                self.push_if_within(r, -c / (2. * b), &mut xs);
+            }
+        } else {
+            let discriminant = b.powf(2.) - 4. * a * c;
+            if discriminant < 0. {
+                return xs;
+            }
+
+            let t0 = (-b - discriminant.sqrt()) / (2. * a);
+            let t1 = (-b + discriminant.sqrt()) / (2. * a);
+            let (t0, t1) = if t0 > t1 { (t1, t0) } else { (t0, t1) };
+            self.push_if_within(r, t0, &mut xs);
+            self.push_if_within(r, t1, &mut xs);
+        }
+
+        self.intersect_caps(r, &mut xs);
+        xs
+    }
+}
+
+impl Bounded for Cone {
+    fn bounds(&self) -> Bounds {
+        let radius = self.min.abs().max(self.max.abs());
+        Bounds::new(
+            Point::new(-radius, self.min, -radius),
+            Point::new(radius, self.max, radius),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::f64::consts::SQRT_2;
+
+    use crate::math::round::Round;
+
+    use super::*;
+
+    #[test]
+    fn normal_at() -> () {
+        vec![
+            (Point::ORIGIN, Vector::new(0., 0., 0.)),
+            (Point::new(1., 1., 1.), Vector::new(1., -SQRT_2, 1.)),
+            (Point::new(-1., -1., 0.), Vector::new(-1., 1., 0.)),
+        ]
+        .iter()
+        .for_each(|(point, normal)| {
+            let c = Cone::default();
+            assert_eq!(c.normal_at(*point), *normal);
+        });
+    }
+
+    #[test]
+    fn intersect_caps() -> () {
+        vec![
+            (Point::new(0., 0., -5.), Vector::new(0., 1., 0.), 0),
+            (Point::new(0., 0., -0.25), Vector::new(0., 1., 1.), 2),
+            (Point::new(0., 0., -0.25), Vector::new(0., 1., 0.), 4),
+        ]
+        .iter()
+        .for_each(|(origin, direction, count)| {
+            let c = Cone::new(-0.5, 0.5, true);
+            let r = Ray::new(*origin, direction.norm());
+            assert_eq!(c.intersect(&r).len(), *count);
+        });
+    }
+
+    #[test]
+    fn intersect_parallel_to_half() -> () {
+        let c = Cone::default();
+        let r = Ray::new(Point::new(0., 0., -1.), Vector::new(0., 1., 1.).norm());
+        let res = c.intersect(&r);
+        assert_eq!(res.rounded(5), vec![0.35355]);
+    }
+
+    #[test]
+    fn intersect() -> () {
+        vec![
+            (Point::new(0., 0., -5.), Vector::new(0., 0., 1.), 5., 5.),
+            (
+                Point::new(0., 0., -5.),
+                Vector::new(1., 1., 1.),
+                8.66025,
+                8.66025,
+            ),
+            (
+                Point::new(1., 1., -5.),
+                Vector::new(-0.5, -1., 1.),
+                4.55006,
+                49.44994,
+            ),
+        ]
+        .iter()
+        .for_each(|(origin, direction, t1, t2)| {
+            let c = Cone::default();
+            let r = Ray::new(*origin, direction.norm());
+            let res = c.intersect(&r);
+            assert_eq!(res.len(), 2);
+            assert_eq!(res.rounded(5), vec![*t1, *t2]);
+        });
+    }
+
+    #[test]
+    fn new() -> () {
+        let c = Cone::new(-1., 2., true);
+        assert_eq!(c.min, -1.);
+        assert_eq!(c.max, 2.);
+        assert!(c.closed);
+    }
+}
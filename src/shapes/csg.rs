@@ -0,0 +1,223 @@
+use std::sync::Arc;
+
+use crate::model::{intersection::Intersection, ray::Ray};
+
+use super::{bounded::Bounded, bounds::Bounds, shape::Shape, shape_kind::ShapeKind};
+
+#[derive(PartialEq, Debug, Copy, Clone)]
+pub enum CsgOp {
+    Union,
+    Intersection,
+    Difference,
+}
+
+impl CsgOp {
+    // https://en.wikipedia.org/wiki/Constructive_solid_geometry
+    // lhit: the hit is on the left shape, inl / inr: the ray is inside the left / right shape
+    pub fn intersection_allowed(&self, lhit: bool, inl: bool, inr: bool) -> bool {
+        match self {
+            CsgOp::Union => (lhit && !inr) || (!lhit && !inl),
+            CsgOp::Intersection => (lhit && inr) || (!lhit && inl),
+            CsgOp::Difference => (lhit && !inr) || (!lhit && inl),
+        }
+    }
+}
+
+// like groups, both operands are stored with the csg transform already applied
+#[derive(PartialEq, Debug, Clone)]
+pub struct Csg {
+    pub op: CsgOp,
+    pub(super) left: Arc<Shape>,
+    pub(super) right: Arc<Shape>,
+    bounds: Bounds,
+}
+
+impl Csg {
+    pub fn new(op: CsgOp, left: Arc<Shape>, right: Arc<Shape>) -> Self {
+        let bounds = left.bounds().merge(right.bounds());
+        Self {
+            op,
+            left,
+            right,
+            bounds,
+        }
+    }
+
+    pub fn left(&self) -> &Arc<Shape> {
+        &self.left
+    }
+
+    pub fn right(&self) -> &Arc<Shape> {
+        &self.right
+    }
+
+    // r is in world space, intersections are reported against the leaf shapes
+    pub fn intersections(&self, r: &Ray) -> Vec<Intersection> {
+        if !self.bounds.intersects(r) {
+            return vec![];
+        }
+        let mut is = Intersection::intersections(Arc::clone(&self.left), r);
+        is.append(&mut Intersection::intersections(Arc::clone(&self.right), r));
+        // safe if no NaN
+        is.sort_by(|a, b| a.t.partial_cmp(&b.t).unwrap());
+        self.filter(is)
+    }
+
+    pub fn filter(&self, is: Vec<Intersection>) -> Vec<Intersection> {
+        let mut inl = false;
+        let mut inr = false;
+        let mut res = Vec::new();
+        for i in is {
+            let lhit = Self::includes(&self.left, &i.shape);
+            if self.op.intersection_allowed(lhit, inl, inr) {
+                res.push(i);
+            }
+            if lhit {
+                inl = !inl;
+            } else {
+                inr = !inr;
+            }
+        }
+        res
+    }
+
+    // leaves are compared by identity so that two equal shapes on either side stay apart
+    fn includes(s: &Arc<Shape>, leaf: &Arc<Shape>) -> bool {
+        match &s.underlying {
+            ShapeKind::G(group) => group.children().iter().any(|c| Self::includes(c, leaf)),
+            ShapeKind::Csg(csg) => {
+                Self::includes(&csg.left, leaf) || Self::includes(&csg.right, leaf)
+            }
+            _ => Arc::ptr_eq(s, leaf),
+        }
+    }
+}
+
+impl Bounded for Csg {
+    fn bounds(&self) -> Bounds {
+        self.bounds
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::math::{matrix::Matrix4x4, point::Point, vector::Vector};
+
+    use super::*;
+
+    #[test]
+    fn intersections_transformed_csg() -> () {
+        let c = Shape::new_csg(
+            CsgOp::Union,
+            Arc::new(Shape::id_sphere()),
+            Arc::new(Shape::id_cube()),
+            Matrix4x4::translation(0., 0., 5.),
+        )
+        .unwrap();
+        let r = Ray::new(Point::ORIGIN, Vector::new(0., 0., 1.));
+        let ts: Vec<f64> = Intersection::intersections(Arc::new(c), &r)
+            .iter()
+            .map(|i| i.t)
+            .collect();
+        assert_eq!(ts, vec![4., 6.]);
+    }
+
+    #[test]
+    fn intersections() -> () {
+        let s1 = Arc::new(Shape::id_sphere());
+        let s2 = Arc::new(Shape::new_sphere(Matrix4x4::translation(0., 0., 0.5)).unwrap());
+        let c = Csg::new(CsgOp::Union, Arc::clone(&s1), Arc::clone(&s2));
+        let r = Ray::new(Point::new(0., 0., -5.), Vector::new(0., 0., 1.));
+        let res = c.intersections(&r);
+        assert_eq!(res.len(), 2);
+        assert_eq!(res[0], Intersection::new(s1, 4.));
+        assert_eq!(res[1], Intersection::new(s2, 6.5));
+    }
+
+    #[test]
+    fn intersections_miss() -> () {
+        let c = Csg::new(
+            CsgOp::Union,
+            Arc::new(Shape::id_sphere()),
+            Arc::new(Shape::id_cube()),
+        );
+        let r = Ray::new(Point::new(0., 2., -5.), Vector::new(0., 0., 1.));
+        assert_eq!(c.intersections(&r), vec![]);
+    }
+
+    #[test]
+    fn filter() -> () {
+        vec![
+            (CsgOp::Union, 0, 3),
+            (CsgOp::Intersection, 1, 2),
+            (CsgOp::Difference, 0, 1),
+        ]
+        .iter()
+        .for_each(|(op, x0, x1)| {
+            let s1 = Arc::new(Shape::id_sphere());
+            let s2 = Arc::new(Shape::id_cube());
+            let c = Csg::new(*op, Arc::clone(&s1), Arc::clone(&s2));
+            let is = vec![
+                Intersection::new(Arc::clone(&s1), 1.),
+                Intersection::new(Arc::clone(&s2), 2.),
+                Intersection::new(Arc::clone(&s1), 3.),
+                Intersection::new(Arc::clone(&s2), 4.),
+            ];
+            let res = c.filter(is.clone());
+            assert_eq!(res, vec![is[*x0].clone(), is[*x1].clone()]);
+        });
+    }
+
+    #[test]
+    fn filter_nested() -> () {
+        let s1 = Arc::new(Shape::id_sphere());
+        let s2 = Arc::new(Shape::id_sphere());
+        let g = Arc::new(Shape::id_group(vec![s1]));
+        let c = Csg::new(CsgOp::Difference, Arc::clone(&g), Arc::clone(&s2));
+        let leaf = match &c.left().underlying {
+            ShapeKind::G(group) => Arc::clone(&group.children()[0]),
+            _ => panic!("not a group"),
+        };
+        let is = vec![
+            Intersection::new(Arc::clone(&leaf), 1.),
+            Intersection::new(Arc::clone(&s2), 2.),
+            Intersection::new(Arc::clone(&leaf), 3.),
+            Intersection::new(Arc::clone(&s2), 4.),
+        ];
+        assert_eq!(c.filter(is.clone()), vec![is[0].clone(), is[1].clone()]);
+    }
+
+    #[test]
+    fn intersection_allowed() -> () {
+        vec![
+            (CsgOp::Union, true, true, true, false),
+            (CsgOp::Union, true, true, false, true),
+            (CsgOp::Union, true, false, true, false),
+            (CsgOp::Union, true, false, false, true),
+            (CsgOp::Union, false, true, true, false),
+            (CsgOp::Union, false, true, false, false),
+            (CsgOp::Union, false, false, true, true),
+            (CsgOp::Union, false, false, false, true),
+            (CsgOp::Intersection, true, true, true, true),
+            (CsgOp::Intersection, true, true, false, false),
+            (CsgOp::Intersection, true, false, true, true),
+            (CsgOp::Intersection, true, false, false, false),
+            (CsgOp::Intersection, false, true, true, true),
+            (CsgOp::Intersection, false, true, false, true),
+            (CsgOp::Intersection, false, false, true, false),
+            (CsgOp::Intersection, false, false, false, false),
+            (CsgOp::Difference, true, true, true, false),
+            (CsgOp::Difference, true, true, false, true),
+            (CsgOp::Difference, true, false, true, false),
+            (CsgOp::Difference, true, false, false, true),
+            (CsgOp::Difference, false, true, true, true),
+            (CsgOp::Difference, false, true, false, true),
+            (CsgOp::Difference, false, false, true, false),
+            (CsgOp::Difference, false, false, false, false),
+        ]
+        .iter()
+        .for_each(|(op, lhit, inl, inr, exp)| {
+            assert_eq!(op.intersection_allowed(*lhit, *inl, *inr), *exp);
+        });
+    }
+}
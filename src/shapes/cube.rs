@@ -1,11 +1,9 @@
-use std::f64::INFINITY;
-
 use crate::{
     math::{point::Point, vector::Vector},
     model::ray::Ray,
 };
 
-use super::{intersect::Intersect, normal::Normal};
+use super::{bounded::Bounded, bounds::Bounds, intersect::Intersect, normal::Normal};
 
 #[derive(PartialEq, Debug, Copy, Clone)]
 pub struct Cube {}
@@ -42,8 +40,8 @@ impl Intersect for Cube {
                 tmin /= direction;
                 tmax /= direction;
             } else {
-                tmin *= INFINITY;
-                tmax *= INFINITY;
+                tmin *= f64::INFINITY;
+                tmax *= f64::INFINITY;
             }
 
             if tmin > tmax {
@@ -85,6 +83,12 @@ impl Intersect for Cube {
     }
 }
 
+impl Bounded for Cube {
+    fn bounds(&self) -> Bounds {
+        Bounds::new(Point::new(-1., -1., -1.), Point::new(1., 1., 1.))
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -0,0 +1,220 @@
+use crate::{
+    math::{point::Point, vector::Vector},
+    model::ray::Ray,
+};
+
+use super::{bounded::Bounded, bounds::Bounds, intersect::Intersect, normal::Normal};
+
+#[derive(PartialEq, Debug, Copy, Clone)]
+pub struct Cylinder {
+    min: f64,
+    max: f64,
+    closed: bool,
+}
+
+impl Cylinder {
+    const EPS: f64 = 0.00001;
+
+    pub fn new(min: f64, max: f64, closed: bool) -> Self {
+        Self { min, max, closed }
+    }
+
+    // a cap is hit if the intersection is within the radius (1) of the y axis
+    fn check_cap(r: &Ray, t: f64) -> bool {
+        let x = r.origin.x + t * r.direction.x;
+        let z = r.origin.z + t * r.direction.z;
+        x.powf(2.) + z.powf(2.) <= 1.
+    }
+
+    fn intersect_caps(&self, r: &Ray, xs: &mut Vec<f64>) {
+        if !self.closed || r.direction.y.abs() < Self::EPS {
+            return;
+        }
+
+        let t_min = (self.min - r.origin.y) / r.direction.y;
+        if Self::check_cap(r, t_min) {
+            xs.push(t_min);
+        }
+        let t_max = (self.max - r.origin.y) / r.direction.y;
+        if Self::check_cap(r, t_max) {
+            xs.push(t_max);
+        }
+    }
+}
+
+impl Default for Cylinder {
+    fn default() -> Self {
+        Self::new(f64::NEG_INFINITY, f64::INFINITY, false)
+    }
+}
+
+impl Normal for Cylinder {
+    fn normal_at(&self, object_point: Point) -> Vector {
+        let dist = object_point.x.powf(2.) + object_point.z.powf(2.);
+        if dist < 1. && object_point.y >= self.max - Self::EPS {
+            Vector::new(0., 1., 0.)
+        } else if dist < 1. && object_point.y <= self.min + Self::EPS {
+            Vector::new(0., -1., 0.)
+        } else {
+            Vector::new(object_point.x, 0., object_point.z)
+        }
+    }
+}
+
+impl Intersect for Cylinder {
+    fn intersect(&self, r: &Ray) -> Vec<f64> {
+        let mut xs = Vec::new();
+        let a = r.direction.x.powf(2.) + r.direction.z.powf(2.);
+
+        // ray parallel to the y axis can only hit the caps
+        if a.abs() >= Self::EPS {
+            let b = 2. * r.origin.x * r.direction.x + 2. * r.origin.z * r.direction.z;
+            let c = r.origin.x.powf(2.) + r.origin.z.powf(2.) - 1.;
+            let discriminant = b.powf(2.) - 4. * a * c;
+            if discriminant < 0. {
+                return xs;
+            }
+
+            let t0 = (-b - discriminant.sqrt()) / (2. * a);
+            let t1 = (-b + discriminant.sqrt()) / (2. * a);
+            let (t0, t1) = if t0 > t1 { (t1, t0) } else { (t0, t1) };
+
+            let y0 = r.origin.y + t0 * r.direction.y;
+            if self.min < y0 && y0 < self.max {
+                xs.push(t0);
+            }
+            let y1 = r.origin.y + t1 * r.direction.y;
+            if self.min < y1 && y1 < self.max {
+                xs.push(t1);
+            }
+        }
+
+        self.intersect_caps(r, &mut xs);
+        xs
+    }
+}
+
+impl Bounded for Cylinder {
+    fn bounds(&self) -> Bounds {
+        Bounds::new(Point::new(-1., self.min, -1.), Point::new(1., self.max, 1.))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::math::round::Round;
+
+    use super::*;
+
+    #[test]
+    fn normal_at_caps() -> () {
+        vec![
+            (Point::new(0., 1., 0.), Vector::new(0., -1., 0.)),
+            (Point::new(0.5, 1., 0.), Vector::new(0., -1., 0.)),
+            (Point::new(0., 1., 0.5), Vector::new(0., -1., 0.)),
+            (Point::new(0., 2., 0.), Vector::new(0., 1., 0.)),
+            (Point::new(0.5, 2., 0.), Vector::new(0., 1., 0.)),
+            (Point::new(0., 2., 0.5), Vector::new(0., 1., 0.)),
+        ]
+        .iter()
+        .for_each(|(point, normal)| {
+            let c = Cylinder::new(1., 2., true);
+            assert_eq!(c.normal_at(*point), *normal);
+        });
+    }
+
+    #[test]
+    fn normal_at() -> () {
+        vec![
+            (Point::new(1., 0., 0.), Vector::new(1., 0., 0.)),
+            (Point::new(0., 5., -1.), Vector::new(0., 0., -1.)),
+            (Point::new(0., -2., 1.), Vector::new(0., 0., 1.)),
+            (Point::new(-1., 1., 0.), Vector::new(-1., 0., 0.)),
+        ]
+        .iter()
+        .for_each(|(point, normal)| {
+            let c = Cylinder::default();
+            assert_eq!(c.normal_at(*point), *normal);
+        });
+    }
+
+    #[test]
+    fn intersect_caps() -> () {
+        vec![
+            (Point::new(0., 3., 0.), Vector::new(0., -1., 0.), 2),
+            (Point::new(0., 3., -2.), Vector::new(0., -1., 2.), 2),
+            (Point::new(0., 4., -2.), Vector::new(0., -1., 1.), 2),
+            (Point::new(0., 0., -2.), Vector::new(0., 1., 2.), 2),
+            (Point::new(0., -1., -2.), Vector::new(0., 1., 1.), 2),
+        ]
+        .iter()
+        .for_each(|(origin, direction, count)| {
+            let c = Cylinder::new(1., 2., true);
+            let r = Ray::new(*origin, direction.norm());
+            assert_eq!(c.intersect(&r).len(), *count);
+        });
+    }
+
+    #[test]
+    fn intersect_truncated() -> () {
+        vec![
+            (Point::new(0., 1.5, 0.), Vector::new(0.1, 1., 0.), 0),
+            (Point::new(0., 3., -5.), Vector::new(0., 0., 1.), 0),
+            (Point::new(0., 0., -5.), Vector::new(0., 0., 1.), 0),
+            (Point::new(0., 2., -5.), Vector::new(0., 0., 1.), 0),
+            (Point::new(0., 1., -5.), Vector::new(0., 0., 1.), 0),
+            (Point::new(0., 1.5, -2.), Vector::new(0., 0., 1.), 2),
+        ]
+        .iter()
+        .for_each(|(origin, direction, count)| {
+            let c = Cylinder::new(1., 2., false);
+            let r = Ray::new(*origin, direction.norm());
+            assert_eq!(c.intersect(&r).len(), *count);
+        });
+    }
+
+    #[test]
+    fn intersect() -> () {
+        vec![
+            (Point::new(1., 0., -5.), Vector::new(0., 0., 1.), 5., 5.),
+            (Point::new(0., 0., -5.), Vector::new(0., 0., 1.), 4., 6.),
+            (
+                Point::new(0.5, 0., -5.),
+                Vector::new(0.1, 1., 1.),
+                6.80798,
+                7.08872,
+            ),
+        ]
+        .iter()
+        .for_each(|(origin, direction, t1, t2)| {
+            let c = Cylinder::default();
+            let r = Ray::new(*origin, direction.norm());
+            let res = c.intersect(&r);
+            assert_eq!(res.len(), 2);
+            assert_eq!(res.rounded(5), vec![*t1, *t2]);
+        });
+    }
+
+    #[test]
+    fn no_intersect() -> () {
+        vec![
+            (Point::new(1., 0., 0.), Vector::new(0., 1., 0.)),
+            (Point::new(0., 0., 0.), Vector::new(0., 1., 0.)),
+            (Point::new(0., 0., -5.), Vector::new(1., 1., 1.)),
+        ]
+        .iter()
+        .for_each(|(origin, direction)| {
+            let c = Cylinder::default();
+            let r = Ray::new(*origin, direction.norm());
+            assert_eq!(c.intersect(&r), vec![]);
+        });
+    }
+
+    #[test]
+    fn default() -> () {
+        let c = Cylinder::default();
+        assert_eq!(c.min, f64::NEG_INFINITY);
+        assert_eq!(c.max, f64::INFINITY);
+        assert!(!c.closed);
+    }
+}
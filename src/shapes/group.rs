@@ -0,0 +1,93 @@
+use std::sync::Arc;
+
+use crate::model::{intersection::Intersection, ray::Ray};
+
+use super::{bounded::Bounded, bounds::Bounds, shape::Shape};
+
+// children are stored with their transform already composed with the group's (and
+// every ancestor's), their inv_t takes world points straight to their object space
+#[derive(PartialEq, Debug, Clone)]
+pub struct Group {
+    pub(super) children: Vec<Arc<Shape>>,
+    bounds: Bounds,
+}
+
+impl Group {
+    pub fn new(children: Vec<Arc<Shape>>) -> Self {
+        let bounds = children
+            .iter()
+            .fold(Bounds::EMPTY, |acc, c| acc.merge(c.bounds()));
+        Self { children, bounds }
+    }
+
+    pub fn children(&self) -> &[Arc<Shape>] {
+        &self.children
+    }
+
+    // r is in world space, intersections are reported against the leaf shapes
+    pub fn intersections(&self, r: &Ray) -> Vec<Intersection> {
+        if !self.bounds.intersects(r) {
+            return vec![];
+        }
+        let mut is: Vec<Intersection> = self
+            .children
+            .iter()
+            .flat_map(|c| Intersection::intersections(Arc::clone(c), r))
+            .collect();
+        // safe if no NaN
+        is.sort_by(|a, b| a.t.partial_cmp(&b.t).unwrap());
+        is
+    }
+}
+
+impl Bounded for Group {
+    fn bounds(&self) -> Bounds {
+        self.bounds
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::math::{matrix::Matrix4x4, point::Point, vector::Vector};
+
+    use super::*;
+
+    #[test]
+    fn intersections_transformed_group() -> () {
+        let s = Shape::new_sphere(Matrix4x4::translation(5., 0., 0.)).unwrap();
+        let g = Shape::new_group(vec![Arc::new(s)], Matrix4x4::scaling(2., 2., 2.)).unwrap();
+        let r = Ray::new(Point::new(10., 0., -10.), Vector::new(0., 0., 1.));
+        let res = Intersection::intersections(Arc::new(g), &r);
+        assert_eq!(res.len(), 2);
+    }
+
+    #[test]
+    fn intersections() -> () {
+        let s1 = Arc::new(Shape::id_sphere());
+        let s2 = Arc::new(Shape::new_sphere(Matrix4x4::translation(0., 0., -3.)).unwrap());
+        let s3 = Arc::new(Shape::new_sphere(Matrix4x4::translation(5., 0., 0.)).unwrap());
+        let g = Group::new(vec![Arc::clone(&s1), Arc::clone(&s2), s3]);
+        let r = Ray::new(Point::new(0., 0., -5.), Vector::new(0., 0., 1.));
+        let res = g.intersections(&r);
+        let shapes: Vec<Arc<Shape>> = res.iter().map(|i| Arc::clone(&i.shape)).collect();
+        assert_eq!(shapes, vec![Arc::clone(&s2), s2, Arc::clone(&s1), s1]);
+    }
+
+    #[test]
+    fn bounds() -> () {
+        let s = Arc::new(Shape::new_sphere(Matrix4x4::translation(2., 5., -3.)).unwrap());
+        let c = Arc::new(
+            Shape::new_cylinder(-2., 2., true, Matrix4x4::translation(-4., -1., 4.)).unwrap(),
+        );
+        let g = Group::new(vec![s, c]);
+        assert_eq!(g.bounds().min, Point::new(-5., -3., -4.));
+        assert_eq!(g.bounds().max, Point::new(3., 6., 5.));
+    }
+
+    #[test]
+    fn intersections_empty() -> () {
+        let g = Group::new(vec![]);
+        let r = Ray::new(Point::ORIGIN, Vector::new(0., 0., 1.));
+        assert_eq!(g.intersections(&r), vec![]);
+    }
+}
@@ -2,4 +2,10 @@ use crate::model::ray::Ray;
 
 pub trait Intersect {
     fn intersect(&self, r: &Ray) -> Vec<f64>;
+
+    // shapes parameterised over their surface also report where they were hit,
+    // the others only know about t
+    fn intersect_uv(&self, r: &Ray) -> Vec<(f64, Option<(f64, f64)>)> {
+        self.intersect(r).into_iter().map(|t| (t, None)).collect()
+    }
 }
@@ -1,7 +1,15 @@
+pub mod bounded;
+pub mod bounds;
+pub mod cone;
+pub mod csg;
 pub mod cube;
+pub mod cylinder;
+pub mod group;
 pub mod intersect;
 pub mod normal;
 pub mod plane;
 pub mod shape;
 pub mod shape_kind;
+pub mod smooth_triangle;
 pub mod sphere;
+pub mod triangle;
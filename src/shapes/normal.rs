@@ -2,4 +2,9 @@ use crate::math::{point::Point, vector::Vector};
 
 pub trait Normal {
     fn normal_at(&self, object_point: Point) -> Vector;
+
+    // uv is what intersect_uv reported for the hit, only smooth triangles make use of it
+    fn normal_at_uv(&self, object_point: Point, _uv: Option<(f64, f64)>) -> Vector {
+        self.normal_at(object_point)
+    }
 }
@@ -3,7 +3,7 @@ use crate::{
     model::ray::Ray,
 };
 
-use super::{intersect::Intersect, normal::Normal};
+use super::{bounded::Bounded, bounds::Bounds, intersect::Intersect, normal::Normal};
 
 #[derive(PartialEq, Debug, Copy, Clone)]
 pub struct Plane {}
@@ -29,6 +29,15 @@ impl Intersect for Plane {
     }
 }
 
+impl Bounded for Plane {
+    fn bounds(&self) -> Bounds {
+        Bounds::new(
+            Point::new(f64::NEG_INFINITY, 0., f64::NEG_INFINITY),
+            Point::new(f64::INFINITY, 0., f64::INFINITY),
+        )
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -1,3 +1,5 @@
+use std::sync::Arc;
+
 use crate::{
     math::{
         matrix::Matrix4x4, matrix_const::MatrixConst, matrix_invert::MatrixInvert,
@@ -6,7 +8,20 @@ use crate::{
     model::material::Material,
 };
 
-use super::{cube::Cube, normal::Normal, plane::Plane, shape_kind::ShapeKind, sphere::Sphere};
+use super::{
+    bounded::Bounded,
+    bounds::Bounds,
+    cone::Cone,
+    csg::{Csg, CsgOp},
+    cube::Cube,
+    cylinder::Cylinder,
+    group::Group,
+    plane::Plane,
+    shape_kind::ShapeKind,
+    smooth_triangle::SmoothTriangle,
+    sphere::Sphere,
+    triangle::Triangle,
+};
 
 #[derive(PartialEq, Debug, Clone)]
 pub struct Shape {
@@ -43,6 +58,26 @@ impl Shape {
         Self::new(ShapeKind::C(Cube {}), t)
     }
 
+    pub fn new_cylinder(min: f64, max: f64, closed: bool, t: Matrix4x4) -> Option<Self> {
+        Self::new(ShapeKind::Cyl(Cylinder::new(min, max, closed)), t)
+    }
+
+    pub fn new_cone(min: f64, max: f64, closed: bool, t: Matrix4x4) -> Option<Self> {
+        Self::new(ShapeKind::Cone(Cone::new(min, max, closed)), t)
+    }
+
+    pub fn new_triangle(p1: Point, p2: Point, p3: Point, t: Matrix4x4) -> Option<Self> {
+        Self::new(ShapeKind::T(Triangle::new(p1, p2, p3)), t)
+    }
+
+    pub fn new_group(children: Vec<Arc<Shape>>, t: Matrix4x4) -> Option<Self> {
+        Self::id_group(children).t(t)
+    }
+
+    pub fn new_csg(op: CsgOp, left: Arc<Shape>, right: Arc<Shape>, t: Matrix4x4) -> Option<Self> {
+        Self::id_csg(op, left, right).t(t)
+    }
+
     pub fn id(s: ShapeKind) -> Self {
         Self {
             t: Matrix4x4::ID,
@@ -66,33 +101,116 @@ impl Shape {
         Self::id(ShapeKind::C(Cube {}))
     }
 
+    pub fn id_cylinder() -> Self {
+        Self::id(ShapeKind::Cyl(Cylinder::default()))
+    }
+
+    pub fn id_cone() -> Self {
+        Self::id(ShapeKind::Cone(Cone::default()))
+    }
+
+    pub fn id_triangle(p1: Point, p2: Point, p3: Point) -> Self {
+        Self::id(ShapeKind::T(Triangle::new(p1, p2, p3)))
+    }
+
+    pub fn id_smooth_triangle(
+        p1: Point,
+        p2: Point,
+        p3: Point,
+        n1: Vector,
+        n2: Vector,
+        n3: Vector,
+    ) -> Self {
+        Self::id(ShapeKind::ST(SmoothTriangle::new(p1, p2, p3, n1, n2, n3)))
+    }
+
+    pub fn id_group(children: Vec<Arc<Shape>>) -> Self {
+        Self::id(ShapeKind::G(Group::new(children)))
+    }
+
+    pub fn id_csg(op: CsgOp, left: Arc<Shape>, right: Arc<Shape>) -> Self {
+        Self::id(ShapeKind::Csg(Csg::new(op, left, right)))
+    }
+
     pub fn t(mut self, t: Matrix4x4) -> Option<Self> {
-        self.t = t;
         t.invert().map(|inv_t| {
+            // children of a group are in world space, they move along with it
+            let (delta, inv_delta) = (t * self.inv_t, self.t * inv_t);
+            self = self.parent_t_children(delta, inv_delta);
+            self.t = t;
             self.inv_t = inv_t;
             self.t_inv_t = inv_t.transpose();
             self
         })
     }
 
+    // prepends a parent transform, given alongside its inverse
+    fn parent_t(mut self, t: Matrix4x4, inv_t: Matrix4x4) -> Self {
+        self = self.parent_t_children(t, inv_t);
+        self.t = t * self.t;
+        self.inv_t = self.inv_t * inv_t;
+        self.t_inv_t = self.inv_t.transpose();
+        self
+    }
+
+    fn parent_t_children(self, t: Matrix4x4, inv_t: Matrix4x4) -> Self {
+        // nothing moves, the children can stay shared
+        if t == Matrix4x4::ID {
+            return self;
+        }
+        self.map_children(|c| c.parent_t(t, inv_t))
+    }
+
+    // children are only cloned when they are shared with another shape
+    fn map_children<F: Fn(Shape) -> Shape>(mut self, f: F) -> Self {
+        let map = |c: Arc<Shape>| Arc::new(f(Arc::unwrap_or_clone(c)));
+        // rebuilt so that the cached bounds follow the children
+        self.underlying = match self.underlying {
+            ShapeKind::G(group) => {
+                ShapeKind::G(Group::new(group.children.into_iter().map(map).collect()))
+            }
+            ShapeKind::Csg(csg) => ShapeKind::Csg(Csg::new(csg.op, map(csg.left), map(csg.right))),
+            kind => kind,
+        };
+        self
+    }
+
+    // a group's or csg's material applies to all of its children
     pub fn material(mut self, m: Material) -> Self {
+        self = self.map_children(|c| c.material(m.clone()));
         self.material = m;
         self
     }
 
-    pub fn normal_at(&self, world_point: Point) -> Vector {
+    // none for groups and csgs, only their leaves have a surface
+    pub fn normal_at(&self, world_point: Point) -> Option<Vector> {
+        self.normal_at_uv(world_point, None)
+    }
+
+    // uv as reported on the intersection, used to interpolate smooth triangle normals
+    pub fn normal_at_uv(&self, world_point: Point, uv: Option<(f64, f64)>) -> Option<Vector> {
         let object_point = self.inv_t * world_point;
-        let object_normal = self.underlying.normal_at(object_point);
+        let object_normal = self.underlying.normal_at_uv(object_point, uv)?;
         let world_normal = self.t_inv_t * object_normal;
-        world_normal.w(0.0).norm()
+        Some(world_normal.w(0.0).norm())
+    }
+
+    // world space bounding box
+    pub fn bounds(&self) -> Bounds {
+        match &self.underlying {
+            ShapeKind::G(_) | ShapeKind::Csg(_) => self.underlying.bounds(),
+            s => s.bounds().transform(self.t),
+        }
     }
 
     pub fn shadows(mut self) -> Self {
+        self = self.map_children(|c| c.shadows());
         self.cast_shadows = true;
         self
     }
 
     pub fn no_shadows(mut self) -> Self {
+        self = self.map_children(|c| c.no_shadows());
         self.cast_shadows = false;
         self
     }
@@ -102,7 +220,7 @@ impl Shape {
 mod tests {
     use std::f64::consts::{PI, SQRT_2};
 
-    use crate::math::round::Round;
+    use crate::math::{matrix::Matrix, round::Round};
 
     use super::*;
 
@@ -121,14 +239,14 @@ mod tests {
         let t = Matrix4x4::rotation_z(PI / 5.).scale(1., 0.5, 1.);
         let s = Shape::new_sphere(t).unwrap_or(Shape::id_sphere());
         let s2 = SQRT_2 / 2.;
-        let res = s.normal_at(Point::new(0., s2, -s2));
+        let res = s.normal_at(Point::new(0., s2, -s2)).unwrap();
         assert_eq!(res.rounded(5), vec![0., 0.97014, -0.24254, 0.]);
     }
 
     #[test]
     fn normal_at_translated_shape() -> () {
         let s = Shape::new_sphere(Matrix4x4::translation(0., 1., 0.)).unwrap_or(Shape::id_sphere());
-        let res = s.normal_at(Point::new(0., 1.70711, -0.70711));
+        let res = s.normal_at(Point::new(0., 1.70711, -0.70711)).unwrap();
         assert_eq!(res.rounded(5), vec![0., 0.70711, -0.70711, 0.]);
     }
 
@@ -148,7 +266,7 @@ mod tests {
         assert!(sphere.is_some());
         let s = sphere.unwrap();
         let s2 = SQRT_2 / 2.;
-        let res = s.normal_at(Point::new(0., s2, -s2));
+        let res = s.normal_at(Point::new(0., s2, -s2)).unwrap();
         assert_eq!(res.rounded(5), vec![0., 0.97014, -0.24254, 0.]);
     }
 
@@ -158,22 +276,162 @@ mod tests {
         assert!(sphere.is_some());
         let s = sphere.unwrap();
         let s2 = SQRT_2 / 2.;
-        let res = s.normal_at(Point::new(0., 1. + s2, -s2));
+        let res = s.normal_at(Point::new(0., 1. + s2, -s2)).unwrap();
         assert_eq!(res.rounded(5), Vector::new(0., s2, -s2).rounded(5));
     }
 
     #[test]
     fn normal_at() -> () {
         let s = Shape::id_sphere();
-        assert_eq!(s.normal_at(Point::new(1., 0., 0.)), Vector::new(1., 0., 0.));
-        assert_eq!(s.normal_at(Point::new(0., 1., 0.)), Vector::new(0., 1., 0.));
-        assert_eq!(s.normal_at(Point::new(0., 0., 1.)), Vector::new(0., 0., 1.));
+        assert_eq!(
+            s.normal_at(Point::new(1., 0., 0.)).unwrap(),
+            Vector::new(1., 0., 0.)
+        );
+        assert_eq!(
+            s.normal_at(Point::new(0., 1., 0.)).unwrap(),
+            Vector::new(0., 1., 0.)
+        );
+        assert_eq!(
+            s.normal_at(Point::new(0., 0., 1.)).unwrap(),
+            Vector::new(0., 0., 1.)
+        );
         let s3 = 3f64.sqrt() / 3.;
-        let res = s.normal_at(Point::new(s3, s3, s3));
+        let res = s.normal_at(Point::new(s3, s3, s3)).unwrap();
         assert_eq!(res, Vector::new(s3, s3, s3));
         assert_eq!(res.norm(), res);
     }
 
+    #[test]
+    fn normal_at_translated_cylinder() -> () {
+        let s = Shape::new_cylinder(0., 1., true, Matrix4x4::translation(0., 1., 0.)).unwrap();
+        assert_eq!(
+            s.normal_at(Point::new(0.5, 2., 0.)).unwrap(),
+            Vector::new(0., 1., 0.)
+        );
+        assert_eq!(
+            s.normal_at(Point::new(1., 1.5, 0.)).unwrap(),
+            Vector::new(1., 0., 0.)
+        );
+    }
+
+    #[test]
+    fn normal_at_uv_smooth_triangle() -> () {
+        let s = Shape::id_smooth_triangle(
+            Point::new(0., 1., 0.),
+            Point::new(-1., 0., 0.),
+            Point::new(1., 0., 0.),
+            Vector::new(0., 1., 0.),
+            Vector::new(-1., 0., 0.),
+            Vector::new(1., 0., 0.),
+        );
+        let res = s.normal_at_uv(Point::ORIGIN, Some((0.45, 0.25))).unwrap();
+        assert_eq!(res.rounded(5), vec![-0.5547, 0.83205, 0., 0.]);
+    }
+
+    #[test]
+    fn normal_at_nested_groups() -> () {
+        let s = Shape::new_sphere(Matrix4x4::translation(5., 0., 0.)).unwrap();
+        let g2 = Shape::new_group(vec![Arc::new(s)], Matrix4x4::scaling(1., 2., 3.)).unwrap();
+        let g1 = Shape::new_group(vec![Arc::new(g2)], Matrix4x4::rotation_y(PI / 2.)).unwrap();
+        let leaf = match &g1.underlying {
+            ShapeKind::G(g1) => match &g1.children()[0].underlying {
+                ShapeKind::G(g2) => Arc::clone(&g2.children()[0]),
+                _ => panic!("not a group"),
+            },
+            _ => panic!("not a group"),
+        };
+        let res = leaf.normal_at(Point::new(1.7321, 1.1547, -5.5774)).unwrap();
+        assert_eq!(res.rounded(3), vec![0.286, 0.429, -0.857, 0.]);
+        let world_t = Matrix4x4::rotation_y(PI / 2.)
+            * Matrix4x4::scaling(1., 2., 3.)
+            * Matrix4x4::translation(5., 0., 0.);
+        assert_eq!(
+            Matrix { m: leaf.inv_t }.rounded(5),
+            Matrix {
+                m: world_t.invert().unwrap()
+            }
+            .rounded(5)
+        );
+    }
+
+    #[test]
+    fn bounds() -> () {
+        let s = Shape::new_sphere(Matrix4x4::scaling(0.5, 2., 4.).translate(1., -3., 5.)).unwrap();
+        assert_eq!(s.bounds().min, Point::new(0.5, -5., 1.));
+        assert_eq!(s.bounds().max, Point::new(1.5, -1., 9.));
+        let g = Shape::new_group(vec![Arc::new(s)], Matrix4x4::translation(1., 0., 0.)).unwrap();
+        assert_eq!(g.bounds().min, Point::new(1.5, -5., 1.));
+        assert_eq!(g.bounds().max, Point::new(2.5, -1., 9.));
+    }
+
+    #[test]
+    fn t_group_moves_children() -> () {
+        let s = Arc::new(Shape::new_sphere(Matrix4x4::translation(5., 0., 0.)).unwrap());
+        let g = Shape::id_group(vec![s])
+            .t(Matrix4x4::scaling(2., 2., 2.))
+            .unwrap()
+            .t(Matrix4x4::translation(0., 1., 0.))
+            .unwrap();
+        match &g.underlying {
+            ShapeKind::G(group) => {
+                let exp = Matrix4x4::translation(0., 1., 0.) * Matrix4x4::translation(5., 0., 0.);
+                assert_eq!(group.children()[0].t, exp);
+            }
+            _ => panic!("not a group"),
+        }
+    }
+
+    #[test]
+    fn material_group() -> () {
+        let m = Material::default().ambient(1.);
+        let g = Shape::id_group(vec![Arc::new(Shape::id_sphere())])
+            .material(m.clone())
+            .no_shadows();
+        match &g.underlying {
+            ShapeKind::G(group) => {
+                assert_eq!(group.children()[0].material, m);
+                assert!(!group.children()[0].cast_shadows);
+            }
+            _ => panic!("not a group"),
+        }
+    }
+
+    #[test]
+    fn map_children_sharing() -> () {
+        let s = Arc::new(Shape::id_sphere());
+        // an identity transform leaves the children as they are
+        let g = Shape::new_group(vec![Arc::clone(&s)], Matrix4x4::ID).unwrap();
+        match &g.underlying {
+            ShapeKind::G(group) => assert!(Arc::ptr_eq(&group.children()[0], &s)),
+            _ => panic!("not a group"),
+        }
+        // shared children are copied before being changed
+        let g = g
+            .t(Matrix4x4::translation(1., 0., 0.))
+            .unwrap()
+            .no_shadows();
+        assert_eq!(*s, Shape::id_sphere());
+        match &g.underlying {
+            ShapeKind::G(group) => {
+                assert_eq!(group.children()[0].t, Matrix4x4::translation(1., 0., 0.));
+                assert!(!group.children()[0].cast_shadows);
+            }
+            _ => panic!("not a group"),
+        }
+    }
+
+    #[test]
+    fn normal_at_group_csg() -> () {
+        let g = Shape::id_group(vec![Arc::new(Shape::id_sphere())]);
+        assert_eq!(g.normal_at(Point::new(1., 0., 0.)), None);
+        let c = Shape::id_csg(
+            CsgOp::Union,
+            Arc::new(Shape::id_sphere()),
+            Arc::new(Shape::id_cube()),
+        );
+        assert_eq!(c.normal_at(Point::new(1., 0., 0.)), None);
+    }
+
     #[test]
     fn t() -> () {
         let s = Shape::id_sphere();
@@ -3,13 +3,23 @@ use crate::{
     model::ray::Ray,
 };
 
-use super::{cube::Cube, intersect::Intersect, normal::Normal, plane::Plane, sphere::Sphere};
+use super::{
+    bounded::Bounded, bounds::Bounds, cone::Cone, csg::Csg, cube::Cube, cylinder::Cylinder,
+    group::Group, intersect::Intersect, normal::Normal, plane::Plane,
+    smooth_triangle::SmoothTriangle, sphere::Sphere, triangle::Triangle,
+};
 
-#[derive(PartialEq, Debug, Copy, Clone)]
+#[derive(PartialEq, Debug, Clone)]
 pub enum ShapeKind {
     P(Plane),
     S(Sphere),
     C(Cube),
+    Cyl(Cylinder),
+    Cone(Cone),
+    T(Triangle),
+    ST(SmoothTriangle),
+    G(Group),
+    Csg(Csg),
 }
 
 impl Intersect for ShapeKind {
@@ -18,16 +28,51 @@ impl Intersect for ShapeKind {
             ShapeKind::P(plane) => plane.intersect(r),
             ShapeKind::S(sphere) => sphere.intersect(r),
             ShapeKind::C(cube) => cube.intersect(r),
+            ShapeKind::Cyl(cylinder) => cylinder.intersect(r),
+            ShapeKind::Cone(cone) => cone.intersect(r),
+            ShapeKind::T(triangle) => triangle.intersect(r),
+            ShapeKind::ST(smooth_triangle) => smooth_triangle.intersect(r),
+            // intersected through their children, see Intersection::intersections
+            ShapeKind::G(_) | ShapeKind::Csg(_) => vec![],
+        }
+    }
+
+    fn intersect_uv(&self, r: &Ray) -> Vec<(f64, Option<(f64, f64)>)> {
+        match self {
+            ShapeKind::ST(smooth_triangle) => smooth_triangle.intersect_uv(r),
+            _ => self.intersect(r).into_iter().map(|t| (t, None)).collect(),
+        }
+    }
+}
+
+impl ShapeKind {
+    // groups and csgs have no surface of their own, intersections refer to their leaves
+    pub fn normal_at_uv(&self, object_point: Point, uv: Option<(f64, f64)>) -> Option<Vector> {
+        match self {
+            ShapeKind::P(plane) => Some(plane.normal_at(object_point)),
+            ShapeKind::S(sphere) => Some(sphere.normal_at(object_point)),
+            ShapeKind::C(cube) => Some(cube.normal_at(object_point)),
+            ShapeKind::Cyl(cylinder) => Some(cylinder.normal_at(object_point)),
+            ShapeKind::Cone(cone) => Some(cone.normal_at(object_point)),
+            ShapeKind::T(triangle) => Some(triangle.normal_at(object_point)),
+            ShapeKind::ST(smooth_triangle) => Some(smooth_triangle.normal_at_uv(object_point, uv)),
+            ShapeKind::G(_) | ShapeKind::Csg(_) => None,
         }
     }
 }
 
-impl Normal for ShapeKind {
-    fn normal_at(&self, object_point: Point) -> Vector {
+impl Bounded for ShapeKind {
+    fn bounds(&self) -> Bounds {
         match self {
-            ShapeKind::P(plane) => plane.normal_at(object_point),
-            ShapeKind::S(sphere) => sphere.normal_at(object_point),
-            ShapeKind::C(cube) => cube.normal_at(object_point),
+            ShapeKind::P(plane) => plane.bounds(),
+            ShapeKind::S(sphere) => sphere.bounds(),
+            ShapeKind::C(cube) => cube.bounds(),
+            ShapeKind::Cyl(cylinder) => cylinder.bounds(),
+            ShapeKind::Cone(cone) => cone.bounds(),
+            ShapeKind::T(triangle) => triangle.bounds(),
+            ShapeKind::ST(smooth_triangle) => smooth_triangle.bounds(),
+            ShapeKind::G(group) => group.bounds(),
+            ShapeKind::Csg(csg) => csg.bounds(),
         }
     }
 }
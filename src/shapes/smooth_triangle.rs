@@ -0,0 +1,114 @@
+use crate::{
+    math::{point::Point, vector::Vector},
+    model::ray::Ray,
+};
+
+use super::{
+    bounded::Bounded, bounds::Bounds, intersect::Intersect, normal::Normal, triangle::Triangle,
+};
+
+// triangle whose normal is interpolated from its vertex normals
+#[derive(PartialEq, Debug, Copy, Clone)]
+pub struct SmoothTriangle {
+    pub flat: Triangle,
+    pub n1: Vector,
+    pub n2: Vector,
+    pub n3: Vector,
+}
+
+impl SmoothTriangle {
+    pub fn new(p1: Point, p2: Point, p3: Point, n1: Vector, n2: Vector, n3: Vector) -> Self {
+        Self {
+            flat: Triangle::new(p1, p2, p3),
+            n1,
+            n2,
+            n3,
+        }
+    }
+}
+
+impl Normal for SmoothTriangle {
+    fn normal_at(&self, object_point: Point) -> Vector {
+        self.flat.normal_at(object_point)
+    }
+
+    fn normal_at_uv(&self, object_point: Point, uv: Option<(f64, f64)>) -> Vector {
+        match uv {
+            Some((u, v)) => self.n2 * u + self.n3 * v + self.n1 * (1. - u - v),
+            None => self.normal_at(object_point),
+        }
+    }
+}
+
+impl Intersect for SmoothTriangle {
+    fn intersect(&self, r: &Ray) -> Vec<f64> {
+        self.flat.intersect(r)
+    }
+
+    fn intersect_uv(&self, r: &Ray) -> Vec<(f64, Option<(f64, f64)>)> {
+        self.flat
+            .moller_trumbore(r)
+            .map(|(t, u, v)| (t, Some((u, v))))
+            .into_iter()
+            .collect()
+    }
+}
+
+impl Bounded for SmoothTriangle {
+    fn bounds(&self) -> Bounds {
+        self.flat.bounds()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::math::round::Round;
+
+    use super::*;
+
+    fn st() -> SmoothTriangle {
+        SmoothTriangle::new(
+            Point::new(0., 1., 0.),
+            Point::new(-1., 0., 0.),
+            Point::new(1., 0., 0.),
+            Vector::new(0., 1., 0.),
+            Vector::new(-1., 0., 0.),
+            Vector::new(1., 0., 0.),
+        )
+    }
+
+    #[test]
+    fn normal_at_uv() -> () {
+        let res = st().normal_at_uv(Point::ORIGIN, Some((0.45, 0.25)));
+        assert_eq!(res.rounded(5), vec![-0.2, 0.3, 0., 0.]);
+    }
+
+    #[test]
+    fn normal_at_without_uv() -> () {
+        let s = st();
+        assert_eq!(
+            s.normal_at_uv(Point::ORIGIN, None),
+            s.flat.normal_at(Point::ORIGIN)
+        );
+    }
+
+    #[test]
+    fn intersect_uv() -> () {
+        let r = Ray::new(Point::new(-0.2, 0.3, -2.), Vector::new(0., 0., 1.));
+        let res = st().intersect_uv(&r);
+        assert_eq!(res.len(), 1);
+        let (t, uv) = res[0];
+        assert_eq!(t, 2.);
+        let (u, v) = uv.unwrap();
+        assert_eq!(vec![u, v].rounded(5), vec![0.45, 0.25]);
+    }
+
+    #[test]
+    fn new() -> () {
+        let s = st();
+        assert_eq!(s.flat.p1, Point::new(0., 1., 0.));
+        assert_eq!(s.n1, Vector::new(0., 1., 0.));
+        assert_eq!(s.n2, Vector::new(-1., 0., 0.));
+        assert_eq!(s.n3, Vector::new(1., 0., 0.));
+    }
+}
@@ -3,7 +3,7 @@ use crate::{
     model::ray::Ray,
 };
 
-use super::{intersect::Intersect, normal::Normal};
+use super::{bounded::Bounded, bounds::Bounds, intersect::Intersect, normal::Normal};
 
 #[derive(PartialEq, Debug, Copy, Clone)]
 pub struct Sphere {}
@@ -39,9 +39,18 @@ impl Intersect for Sphere {
     }
 }
 
+impl Bounded for Sphere {
+    fn bounds(&self) -> Bounds {
+        Bounds::new(
+            Point::new(-Sphere::RADIUS, -Sphere::RADIUS, -Sphere::RADIUS),
+            Point::new(Sphere::RADIUS, Sphere::RADIUS, Sphere::RADIUS),
+        )
+    }
+}
+
 #[cfg(test)]
 mod tests {
-    use std::rc::Rc;
+    use std::sync::Arc;
 
     use crate::{math::matrix::Matrix4x4, model::intersection::Intersection, shapes::shape::Shape};
 
@@ -62,27 +71,27 @@ mod tests {
     #[test]
     fn intersect_after_sphere() -> () {
         let r = Ray::new(Point::new(0., 0., 5.), Vector::new(0., 0., 1.));
-        let s = Rc::new(Shape::id_sphere());
-        let res = Intersection::intersections(Rc::clone(&s), &r);
+        let s = Arc::new(Shape::id_sphere());
+        let res = Intersection::intersections(Arc::clone(&s), &r);
         assert_eq!(res.len(), 2);
-        assert_eq!(res[0], Intersection::new(Rc::clone(&s), -6.));
-        assert_eq!(res[1], Intersection::new(Rc::clone(&s), -4.));
+        assert_eq!(res[0], Intersection::new(Arc::clone(&s), -6.));
+        assert_eq!(res[1], Intersection::new(Arc::clone(&s), -4.));
     }
 
     #[test]
     fn intersect_inside_sphere() -> () {
         let r = Ray::new(Point::new(0., 0., 0.), Vector::new(0., 0., 1.));
-        let s = Rc::new(Shape::id_sphere());
-        let res = Intersection::intersections(Rc::clone(&s), &r);
+        let s = Arc::new(Shape::id_sphere());
+        let res = Intersection::intersections(Arc::clone(&s), &r);
         assert_eq!(res.len(), 2);
-        assert_eq!(res[0], Intersection::new(Rc::clone(&s), -1.));
-        assert_eq!(res[1], Intersection::new(Rc::clone(&s), 1.));
+        assert_eq!(res[0], Intersection::new(Arc::clone(&s), -1.));
+        assert_eq!(res[1], Intersection::new(Arc::clone(&s), 1.));
     }
 
     #[test]
     fn intersect_no_points() -> () {
         let r = Ray::new(Point::new(0., 2., -5.), Vector::new(0., 0., 1.));
-        let s = Rc::new(Shape::id_sphere());
+        let s = Arc::new(Shape::id_sphere());
         let res = Intersection::intersections(s, &r);
         assert_eq!(res.len(), 0);
     }
@@ -90,27 +99,27 @@ mod tests {
     #[test]
     fn intersect_same_point() -> () {
         let r = Ray::new(Point::new(0., 1., -5.), Vector::new(0., 0., 1.));
-        let s = Rc::new(Shape::id_sphere());
-        let res = Intersection::intersections(Rc::clone(&s), &r);
+        let s = Arc::new(Shape::id_sphere());
+        let res = Intersection::intersections(Arc::clone(&s), &r);
         assert_eq!(res.len(), 2);
-        assert_eq!(res[0], Intersection::new(Rc::clone(&s), 5.));
-        assert_eq!(res[1], Intersection::new(Rc::clone(&s), 5.));
+        assert_eq!(res[0], Intersection::new(Arc::clone(&s), 5.));
+        assert_eq!(res[1], Intersection::new(Arc::clone(&s), 5.));
     }
 
     #[test]
     fn intersect_2_points() -> () {
         let r = Ray::new(Point::new(0., 0., -5.), Vector::new(0., 0., 1.));
-        let s = Rc::new(Shape::id_sphere());
-        let res = Intersection::intersections(Rc::clone(&s), &r);
+        let s = Arc::new(Shape::id_sphere());
+        let res = Intersection::intersections(Arc::clone(&s), &r);
         assert_eq!(res.len(), 2);
-        assert_eq!(res[0], Intersection::new(Rc::clone(&s), 4.));
-        assert_eq!(res[1], Intersection::new(Rc::clone(&s), 6.));
+        assert_eq!(res[0], Intersection::new(Arc::clone(&s), 4.));
+        assert_eq!(res[1], Intersection::new(Arc::clone(&s), 6.));
     }
 
     #[test]
     fn intersect_translated_sphere() -> () {
         let r = Ray::new(Point::new(0., 0., -5.), Vector::new(0., 0., 1.));
-        let s = Rc::new(
+        let s = Arc::new(
             Shape::id_sphere()
                 .t(Matrix4x4::translation(5., 0., 0.))
                 .unwrap(),
@@ -122,10 +131,10 @@ mod tests {
     #[test]
     fn intersect_scaled_sphere() -> () {
         let r = Ray::new(Point::new(0., 0., -5.), Vector::new(0., 0., 1.));
-        let s = Rc::new(Shape::new_sphere(Matrix4x4::scaling(2., 2., 2.)).unwrap());
-        let res = Intersection::intersections(Rc::clone(&s), &r);
+        let s = Arc::new(Shape::new_sphere(Matrix4x4::scaling(2., 2., 2.)).unwrap());
+        let res = Intersection::intersections(Arc::clone(&s), &r);
         assert_eq!(res.len(), 2);
-        assert_eq!(res[0], Intersection::new(Rc::clone(&s), 3.));
-        assert_eq!(res[1], Intersection::new(Rc::clone(&s), 7.));
+        assert_eq!(res[0], Intersection::new(Arc::clone(&s), 3.));
+        assert_eq!(res[1], Intersection::new(Arc::clone(&s), 7.));
     }
 }
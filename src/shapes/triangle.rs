@@ -0,0 +1,130 @@
+use crate::{
+    math::{point::Point, vector::Vector},
+    model::ray::Ray,
+};
+
+use super::{bounded::Bounded, bounds::Bounds, intersect::Intersect, normal::Normal};
+
+#[derive(PartialEq, Debug, Copy, Clone)]
+pub struct Triangle {
+    pub p1: Point,
+    pub p2: Point,
+    pub p3: Point,
+    e1: Vector,
+    e2: Vector,
+    normal: Vector,
+}
+
+impl Triangle {
+    const EPS: f64 = 0.00001;
+
+    pub fn new(p1: Point, p2: Point, p3: Point) -> Self {
+        let e1 = p2 - p1;
+        let e2 = p3 - p1;
+        Self {
+            p1,
+            p2,
+            p3,
+            e1,
+            e2,
+            normal: e2.cross(e1).norm(),
+        }
+    }
+
+    // https://en.wikipedia.org/wiki/M%C3%B6ller%E2%80%93Trumbore_intersection_algorithm
+    pub fn moller_trumbore(&self, r: &Ray) -> Option<(f64, f64, f64)> {
+        let dir_cross_e2 = r.direction.cross(self.e2);
+        let det = self.e1.dot(dir_cross_e2);
+        // ray parallel to the triangle
+        if det.abs() < Self::EPS {
+            return None;
+        }
+
+        let f = 1. / det;
+        let p1_to_origin = r.origin - self.p1;
+        let u = f * p1_to_origin.dot(dir_cross_e2);
+        if !(0. ..=1.).contains(&u) {
+            return None;
+        }
+
+        let origin_cross_e1 = p1_to_origin.cross(self.e1);
+        let v = f * r.direction.dot(origin_cross_e1);
+        if v < 0. || u + v > 1. {
+            return None;
+        }
+
+        let t = f * self.e2.dot(origin_cross_e1);
+        Some((t, u, v))
+    }
+}
+
+impl Normal for Triangle {
+    fn normal_at(&self, _object_point: Point) -> Vector {
+        self.normal
+    }
+}
+
+impl Intersect for Triangle {
+    fn intersect(&self, r: &Ray) -> Vec<f64> {
+        self.moller_trumbore(r)
+            .map(|(t, _, _)| t)
+            .into_iter()
+            .collect()
+    }
+}
+
+impl Bounded for Triangle {
+    fn bounds(&self) -> Bounds {
+        Bounds::from_points(&[self.p1, self.p2, self.p3])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn t() -> Triangle {
+        Triangle::new(
+            Point::new(0., 1., 0.),
+            Point::new(-1., 0., 0.),
+            Point::new(1., 0., 0.),
+        )
+    }
+
+    #[test]
+    fn intersect() -> () {
+        let r = Ray::new(Point::new(0., 0.5, -2.), Vector::new(0., 0., 1.));
+        assert_eq!(t().intersect(&r), vec![2.]);
+    }
+
+    #[test]
+    fn no_intersect() -> () {
+        vec![
+            (Point::new(0., -1., -2.), Vector::new(0., 1., 0.)),
+            (Point::new(1., 1., -2.), Vector::new(0., 0., 1.)),
+            (Point::new(-1., 1., -2.), Vector::new(0., 0., 1.)),
+            (Point::new(0., -1., -2.), Vector::new(0., 0., 1.)),
+        ]
+        .iter()
+        .for_each(|(origin, direction)| {
+            let r = Ray::new(*origin, *direction);
+            assert_eq!(t().intersect(&r), vec![]);
+        });
+    }
+
+    #[test]
+    fn normal_at() -> () {
+        let tri = t();
+        assert_eq!(tri.normal_at(Point::new(0., 0.5, 0.)), tri.normal);
+        assert_eq!(tri.normal_at(Point::new(-0.5, 0.75, 0.)), tri.normal);
+        assert_eq!(tri.normal_at(Point::new(0.5, 0.25, 0.)), tri.normal);
+    }
+
+    #[test]
+    fn new() -> () {
+        let tri = t();
+        assert_eq!(tri.e1, Vector::new(-1., -1., 0.));
+        assert_eq!(tri.e2, Vector::new(1., -1., 0.));
+        assert_eq!(tri.normal, Vector::new(0., 0., -1.));
+    }
+}
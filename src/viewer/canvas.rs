@@ -1,15 +1,23 @@
-use std::fmt::{Display, Formatter};
+use std::{
+    fmt::{Display, Formatter},
+    sync::atomic::{AtomicUsize, Ordering},
+    thread,
+};
+
+use rand::Rng;
 
 use crate::{
     math::colour::Colour,
-    model::{camera::Camera, ray::Ray, world::World},
+    model::{camera::Camera, ray::Ray, render_settings::RenderSettings, sampling, world::World},
 };
 
 use super::drawable::Drawable;
 
 pub const SCALE: usize = 255;
 pub const PPM_MAX_LINE_LENGTH: usize = 70;
+pub const TILE_SIZE: usize = 16;
 
+#[derive(PartialEq, Debug, Clone)]
 pub struct Canvas {
     pub width: usize,
     pub height: usize,
@@ -29,16 +37,79 @@ impl Canvas {
         Canvas::new(width, height, Colour::BLACK)
     }
 
-    pub fn render(&mut self, c: &Camera, w: &World) {
+    // single threaded, see render_with for the tiled version
+    pub fn render(&mut self, c: &Camera, w: &World, s: &RenderSettings) {
         for y in 0..c.vsize {
             for x in 0..c.hsize {
-                let ray = Ray::for_pixel(c, x, y);
-                let colour = w.colour_at(&ray, 3);
+                let colour = Self::pixel_colour(c, w, x, y, s);
                 self.update(x, y, colour);
             }
         }
     }
 
+    // same result as render, the image is split in square tiles picked up by the threads
+    pub fn render_tiles(&mut self, c: &Camera, w: &World, threads: usize) {
+        self.render_with(c, w, &RenderSettings::default().threads(threads), |_, _| ());
+    }
+
+    // progress is told how many tiles are done out of the total each time one is finished
+    pub fn render_with<F: Fn(usize, usize) + Sync>(
+        &mut self,
+        c: &Camera,
+        w: &World,
+        s: &RenderSettings,
+        progress: F,
+    ) {
+        let tiles_x = c.hsize.div_ceil(TILE_SIZE);
+        let tiles = tiles_x * c.vsize.div_ceil(TILE_SIZE);
+        let next = AtomicUsize::new(0);
+        let finished = AtomicUsize::new(0);
+        let rendered: Vec<(usize, usize, Colour)> = thread::scope(|scope| {
+            let handles: Vec<_> = (0..s.threads.clamp(1, tiles.max(1)))
+                .map(|_| {
+                    scope.spawn(|| {
+                        let mut done = Vec::new();
+                        loop {
+                            let tile = next.fetch_add(1, Ordering::Relaxed);
+                            if tile >= tiles {
+                                break done;
+                            }
+                            let x0 = (tile % tiles_x) * TILE_SIZE;
+                            let y0 = (tile / tiles_x) * TILE_SIZE;
+                            for y in y0..(y0 + TILE_SIZE).min(c.vsize) {
+                                for x in x0..(x0 + TILE_SIZE).min(c.hsize) {
+                                    done.push((x, y, Self::pixel_colour(c, w, x, y, s)));
+                                }
+                            }
+                            progress(finished.fetch_add(1, Ordering::Relaxed) + 1, tiles);
+                        }
+                    })
+                })
+                .collect();
+            handles
+                .into_iter()
+                .flat_map(|h| h.join().expect("render thread panicked"))
+                .collect()
+        });
+        for (x, y, colour) in rendered {
+            self.update(x, y, colour);
+        }
+    }
+
+    // deterministic for a given seed, whichever thread or pass computes it
+    pub fn pixel_colour(c: &Camera, w: &World, x: usize, y: usize, s: &RenderSettings) -> Colour {
+        let mut rng = sampling::pixel_rng(s.seed, x, y);
+        s.sampling.colour(s.samples, &mut rng, |ox, oy, rng| {
+            // a pinhole camera doesn't need the lens so it doesn't use up random numbers
+            let (lu, lv) = if c.aperture > 0. {
+                (rng.gen(), rng.gen())
+            } else {
+                (0.5, 0.5)
+            };
+            w.sample_colour_at(&Ray::for_pixel_lens(c, x, y, ox, oy, lu, lv), s, rng)
+        })
+    }
+
     // no new canvas to avoid re-allocating storage
     pub fn update(&mut self, x: usize, y: usize, c: Colour) {
         let idx = self.idx(x, y);
@@ -51,6 +122,11 @@ impl Canvas {
         self.storage.get(self.idx(x, y)).copied()
     }
 
+    // row by row, starting from the top left corner
+    pub fn pixels(&self) -> impl Iterator<Item = Colour> + '_ {
+        self.storage.iter().copied()
+    }
+
     fn idx(&self, x: usize, y: usize) -> usize {
         self.width * y + x
     }
@@ -104,7 +180,10 @@ impl Display for Canvas {
 mod tests {
     use std::f64::consts::FRAC_PI_2;
 
-    use crate::math::{matrix::Matrix4x4, point::Point, round::Round, vector::Vector};
+    use crate::{
+        math::{matrix::Matrix4x4, point::Point, round::Round, vector::Vector},
+        model::{render_settings::Integrator, sampling::Sampling},
+    };
 
     use super::*;
 
@@ -118,13 +197,136 @@ mod tests {
             .transform(Matrix4x4::view_transform(eye, to, up))
             .unwrap();
         let mut canvas = Canvas::new(c.hsize, c.vsize, Colour::BLACK);
-        canvas.render(&c, &w);
+        canvas.render(&c, &w, &RenderSettings::default());
         let res = canvas.at(5, 5);
         assert!(res.is_some());
         let resp = res.unwrap();
         assert_eq!(resp.rounded(5), vec![0.38066, 0.47583, 0.2855])
     }
 
+    #[test]
+    fn render_tiles() -> () {
+        let w = World::default();
+        let eye = Point::new(0., 0., -5.);
+        let to = Point::ORIGIN;
+        let up = Vector::new(0., 1., 0.);
+        // not a multiple of the tile size to get partial tiles
+        let c = Camera::new(37, 21, FRAC_PI_2)
+            .transform(Matrix4x4::view_transform(eye, to, up))
+            .unwrap();
+        let mut serial = Canvas::black(c.hsize, c.vsize);
+        serial.render(&c, &w, &RenderSettings::default());
+        vec![1, 2, 3, 8, 100].iter().for_each(|threads| {
+            let mut tiled = Canvas::black(c.hsize, c.vsize);
+            tiled.render_tiles(&c, &w, *threads);
+            assert!(serial.storage == tiled.storage);
+        });
+    }
+
+    #[test]
+    fn render_with() -> () {
+        let w = World::default();
+        let eye = Point::new(0., 0., -5.);
+        let to = Point::ORIGIN;
+        let up = Vector::new(0., 1., 0.);
+        let c = Camera::new(20, 20, FRAC_PI_2)
+            .transform(Matrix4x4::view_transform(eye, to, up))
+            .unwrap();
+        let calls = AtomicUsize::new(0);
+        let mut canvas = Canvas::black(c.hsize, c.vsize);
+        let s = RenderSettings::default().samples(4).threads(2);
+        canvas.render_with(&c, &w, &s, |done, total| {
+            calls.fetch_add(1, Ordering::Relaxed);
+            assert!(done <= total);
+            assert_eq!(total, 4);
+        });
+        assert_eq!(calls.into_inner(), 4);
+        // the edge of the sphere is blended with the background
+        let mut single = Canvas::black(c.hsize, c.vsize);
+        single.render(&c, &w, &RenderSettings::default());
+        assert!(canvas.storage != single.storage);
+        assert_eq!(
+            canvas.at(10, 10).map(|c| c.rounded(1)),
+            single.at(10, 10).map(|c| c.rounded(1))
+        );
+    }
+
+    #[test]
+    fn render_with_seed() -> () {
+        let w = World::default();
+        let eye = Point::new(0., 0., -5.);
+        let to = Point::ORIGIN;
+        let up = Vector::new(0., 1., 0.);
+        let c = Camera::new(20, 20, FRAC_PI_2)
+            .transform(Matrix4x4::view_transform(eye, to, up))
+            .unwrap();
+        let render = |s: RenderSettings| {
+            let mut canvas = Canvas::black(c.hsize, c.vsize);
+            canvas.render_with(&c, &w, &s, |_, _| ());
+            canvas.storage
+        };
+        let s = RenderSettings::default()
+            .samples(4)
+            .sampling(Sampling::Jittered)
+            .seed(3);
+        let jittered = render(s.clone());
+        assert!(jittered == render(s.clone().threads(4)));
+        assert!(jittered != render(s.clone().seed(4)));
+        assert!(jittered != render(s.sampling(Sampling::Regular)));
+    }
+
+    #[test]
+    fn render_with_path_tracing() -> () {
+        let w = World::default();
+        let eye = Point::new(0., 0., -5.);
+        let to = Point::ORIGIN;
+        let up = Vector::new(0., 1., 0.);
+        let c = Camera::new(10, 10, FRAC_PI_2)
+            .transform(Matrix4x4::view_transform(eye, to, up))
+            .unwrap();
+        let render = |s: &RenderSettings| {
+            let mut canvas = Canvas::black(c.hsize, c.vsize);
+            canvas.render_with(&c, &w, s, |_, _| ());
+            canvas
+        };
+        let s = RenderSettings::default()
+            .samples(4)
+            .integrator(Integrator::Path);
+        let path = render(&s);
+        assert!(path == render(&s.clone().threads(3)));
+        assert!(path != render(&s.clone().integrator(Integrator::Whitted)));
+        // nothing to bounce off around the sphere
+        assert_eq!(path.at(0, 0), Some(Colour::BLACK));
+    }
+
+    #[test]
+    fn render_with_aperture() -> () {
+        let w = World::default();
+        let eye = Point::new(0., 0., -5.);
+        let to = Point::ORIGIN;
+        let up = Vector::new(0., 1., 0.);
+        let pinhole = Camera::new(20, 20, FRAC_PI_2)
+            .transform(Matrix4x4::view_transform(eye, to, up))
+            .unwrap();
+        let s = RenderSettings::default().samples(16).threads(2);
+        let render = |c: &Camera| {
+            let mut canvas = Canvas::black(c.hsize, c.vsize);
+            canvas.render_with(c, &w, &s, |_, _| ());
+            canvas
+        };
+        let sharp = render(&pinhole);
+        // the sphere is in focus, its outline gets blurred
+        let blurred = render(&pinhole.aperture(0.5).focal_distance(4.).unwrap());
+        assert!(sharp.storage != blurred.storage);
+        assert!(
+            blurred.storage == render(&pinhole.aperture(0.5).focal_distance(4.).unwrap()).storage
+        );
+        assert_eq!(
+            blurred.at(10, 10).map(|c| c.rounded(2)),
+            sharp.at(10, 10).map(|c| c.rounded(2))
+        );
+    }
+
     #[test]
     fn draw() -> () {
         let c = Canvas::new(1, 1, Colour::new(1.0, 0.8, 0.6));
@@ -0,0 +1,191 @@
+use std::{
+    f64::consts::FRAC_PI_2,
+    fmt::{Display, Formatter},
+};
+
+use crate::{
+    math::{matrix::Matrix4x4, point::Point, vector::Vector},
+    model::camera::Camera,
+};
+
+// free-fly controls, the view is kept as a position and two angles so that it never rolls
+#[derive(PartialEq, Debug, Copy, Clone)]
+pub struct FlyCamera {
+    pub eye: Point,
+    // around y, 0 looks towards +z and turning right towards +x is positive
+    pub yaw: f64,
+    // above the horizon, short of straight up or down where up would be undefined
+    pub pitch: f64,
+    pub fov: f64,
+}
+
+impl FlyCamera {
+    const MAX_PITCH: f64 = FRAC_PI_2 - 0.01;
+    pub const MIN_FOV: f64 = 0.05;
+    pub const MAX_FOV: f64 = 3.;
+
+    pub fn new(eye: Point, to: Point, fov: f64) -> Option<Self> {
+        let d = to - eye;
+        (d.len() > 0.).then(|| {
+            let d = d.norm();
+            Self {
+                eye,
+                yaw: d.x.atan2(d.z),
+                pitch: d.y.asin().clamp(-Self::MAX_PITCH, Self::MAX_PITCH),
+                fov: fov.clamp(Self::MIN_FOV, Self::MAX_FOV),
+            }
+        })
+    }
+
+    pub fn forward(&self) -> Vector {
+        let (sy, cy) = self.yaw.sin_cos();
+        let (sp, cp) = self.pitch.sin_cos();
+        Vector::new(cp * sy, sp, cp * cy)
+    }
+
+    // horizontal, flying sideways doesn't change the height
+    pub fn right(&self) -> Vector {
+        let (sy, cy) = self.yaw.sin_cos();
+        Vector::new(cy, 0., -sy)
+    }
+
+    // in radians, positive turns right and up
+    pub fn look(mut self, yaw: f64, pitch: f64) -> Self {
+        self.yaw += yaw;
+        self.pitch = (self.pitch + pitch).clamp(-Self::MAX_PITCH, Self::MAX_PITCH);
+        self
+    }
+
+    // forward along the view, right and up along the world's y
+    pub fn fly(mut self, forward: f64, right: f64, up: f64) -> Self {
+        self.eye = self.eye + self.forward() * forward + self.right() * right + Vector::Y * up;
+        self
+    }
+
+    // below 1 zooms in
+    pub fn zoom(mut self, factor: f64) -> Self {
+        self.fov = (self.fov * factor).clamp(Self::MIN_FOV, Self::MAX_FOV);
+        self
+    }
+
+    pub fn to(&self) -> Point {
+        self.eye + self.forward()
+    }
+
+    pub fn view_transform(&self) -> Matrix4x4 {
+        Matrix4x4::view_transform(self.eye, self.to(), Vector::Y)
+    }
+
+    // c with this view, keeping its resolution and lens
+    pub fn camera(&self, c: Camera) -> Camera {
+        c.field_of_view(self.fov)
+            .transform(self.view_transform())
+            .unwrap_or(c)
+    }
+}
+
+// the camera keys of a scene file
+impl Display for FlyCamera {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let to = self.to();
+        writeln!(f, "field-of-view: {:.5}", self.fov)?;
+        writeln!(
+            f,
+            "from: [{:.5}, {:.5}, {:.5}]",
+            self.eye.x, self.eye.y, self.eye.z
+        )?;
+        writeln!(f, "to: [{:.5}, {:.5}, {:.5}]", to.x, to.y, to.z)?;
+        write!(f, "up: [0, 1, 0]")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::f64::consts::{FRAC_PI_3, FRAC_PI_4, SQRT_2};
+
+    use crate::math::round::Round;
+
+    use super::*;
+
+    #[test]
+    fn new() -> () {
+        let eye = Point::new(0., 1.5, -7.);
+        let to = Point::new(0., 1., 0.);
+        let f = FlyCamera::new(eye, to, FRAC_PI_3).unwrap();
+        assert_eq!(f.forward().rounded(5), (to - eye).norm().rounded(5));
+        assert_eq!(f.to().rounded(5), (eye + (to - eye).norm()).rounded(5));
+        assert!(FlyCamera::new(eye, eye, FRAC_PI_3).is_none());
+        // straight down is kept just above the pole
+        let down = FlyCamera::new(eye, Point::new(0., 0., -7.), FRAC_PI_3).unwrap();
+        assert!(down.pitch > -FRAC_PI_2);
+    }
+
+    #[test]
+    fn look() -> () {
+        let f = FlyCamera::new(Point::ORIGIN, Point::new(0., 0., 1.), FRAC_PI_3).unwrap();
+        let s2 = SQRT_2 / 2.;
+        vec![
+            (
+                (FRAC_PI_2, 0.),
+                Vector::new(1., 0., 0.),
+                Vector::new(0., 0., -1.),
+            ),
+            (
+                (-FRAC_PI_4, 0.),
+                Vector::new(-s2, 0., s2),
+                Vector::new(s2, 0., s2),
+            ),
+            (
+                (0., FRAC_PI_4),
+                Vector::new(0., s2, s2),
+                Vector::new(1., 0., 0.),
+            ),
+        ]
+        .iter()
+        .for_each(|((yaw, pitch), forward, right)| {
+            let res = f.look(*yaw, *pitch);
+            assert_eq!(res.forward().rounded(5), forward.rounded(5));
+            assert_eq!(res.right().rounded(5), right.rounded(5));
+            assert_eq!(vec![res.forward().dot(res.right())].rounded(5), vec![0.]);
+        });
+        assert!(f.look(0., 10.).forward().y < 1.);
+    }
+
+    #[test]
+    fn fly() -> () {
+        let f = FlyCamera::new(Point::ORIGIN, Point::new(1., 0., 0.), FRAC_PI_3).unwrap();
+        let res = f.fly(2., 0.5, -1.);
+        assert_eq!(res.eye.rounded(5), Point::new(2., -1., -0.5).rounded(5));
+        assert_eq!((res.yaw, res.pitch), (f.yaw, f.pitch));
+        // forward follows the pitch, right stays horizontal
+        let up = f.look(0., FRAC_PI_2 / 3.).fly(2., 1., 0.);
+        assert_eq!(
+            up.eye.rounded(5),
+            Point::new(3f64.sqrt(), 1., -1.).rounded(5)
+        );
+    }
+
+    #[test]
+    fn zoom() -> () {
+        let f = FlyCamera::new(Point::ORIGIN, Point::new(1., 0., 0.), 1.).unwrap();
+        assert_eq!(f.zoom(0.5).fov, 0.5);
+        assert_eq!(f.zoom(0.).fov, FlyCamera::MIN_FOV);
+        assert_eq!(f.zoom(10.).fov, FlyCamera::MAX_FOV);
+        let c = f.zoom(0.5).camera(Camera::new(20, 10, 1.));
+        assert_eq!(
+            c,
+            Camera::new(20, 10, 0.5)
+                .transform(f.view_transform())
+                .unwrap()
+        );
+    }
+
+    #[test]
+    fn display() -> () {
+        let f = FlyCamera::new(Point::new(0., 1.5, -7.), Point::new(0., 1.5, 0.), 1.).unwrap();
+        assert_eq!(
+            f.to_string(),
+            "field-of-view: 1.00000\nfrom: [0.00000, 1.50000, -7.00000]\nto: [0.00000, 1.50000, -6.00000]\nup: [0, 1, 0]"
+        );
+    }
+}
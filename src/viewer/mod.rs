@@ -1,3 +1,7 @@
 pub mod canvas;
 pub mod drawable;
+pub mod fly;
+pub mod png;
+pub mod ppm;
+pub mod progressive;
 pub mod to_file;
@@ -0,0 +1,97 @@
+use std::io::{self, Write};
+
+use png::{BitDepth, ColorType, Encoder};
+
+use super::canvas::Canvas;
+
+#[derive(PartialEq, Debug, Copy, Clone)]
+pub enum PngDepth {
+    Eight,
+    Sixteen,
+}
+
+impl Canvas {
+    pub fn write_png<W: Write>(&self, w: W, depth: PngDepth) -> io::Result<()> {
+        let mut encoder = Encoder::new(w, self.width as u32, self.height as u32);
+        encoder.set_color(ColorType::Rgb);
+        let data: Vec<u8> = match depth {
+            PngDepth::Eight => {
+                encoder.set_depth(BitDepth::Eight);
+                self.pixels()
+                    .flat_map(|c| {
+                        let (r, g, b) = c.scale(u8::MAX);
+                        [r, g, b]
+                    })
+                    .collect()
+            }
+            // png stores samples in big endian
+            PngDepth::Sixteen => {
+                encoder.set_depth(BitDepth::Sixteen);
+                self.pixels()
+                    .flat_map(|c| {
+                        let (r, g, b) = c.scale(u16::MAX);
+                        [r, g, b]
+                    })
+                    .flat_map(u16::to_be_bytes)
+                    .collect()
+            }
+        };
+        let mut writer = encoder.write_header()?;
+        writer.write_image_data(&data)?;
+        writer.finish()?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use png::Decoder;
+
+    use crate::math::colour::Colour;
+
+    use super::*;
+
+    fn canvas() -> Canvas {
+        let mut c = Canvas::black(3, 2);
+        c.update(0, 0, Colour::new(1.5, 0., 0.));
+        c.update(1, 0, Colour::new(0., 0.5, 0.));
+        c.update(2, 1, Colour::new(-0.5, 0., 1.));
+        c
+    }
+
+    fn decode(bytes: &[u8]) -> (png::OutputInfo, Vec<u8>) {
+        let mut reader = Decoder::new(bytes).read_info().unwrap();
+        let mut buf = vec![0; reader.output_buffer_size()];
+        let info = reader.next_frame(&mut buf).unwrap();
+        buf.truncate(info.buffer_size());
+        (info, buf)
+    }
+
+    #[test]
+    fn write_png_8() -> () {
+        let mut bytes = Vec::new();
+        canvas().write_png(&mut bytes, PngDepth::Eight).unwrap();
+        let (info, data) = decode(&bytes);
+        assert_eq!((info.width, info.height), (3, 2));
+        assert_eq!(info.bit_depth, BitDepth::Eight);
+        assert_eq!(info.color_type, ColorType::Rgb);
+        assert_eq!(
+            data,
+            vec![255, 0, 0, 0, 128, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 255]
+        );
+    }
+
+    #[test]
+    fn write_png_16() -> () {
+        let mut bytes = Vec::new();
+        canvas().write_png(&mut bytes, PngDepth::Sixteen).unwrap();
+        let (info, data) = decode(&bytes);
+        assert_eq!((info.width, info.height), (3, 2));
+        assert_eq!(info.bit_depth, BitDepth::Sixteen);
+        assert_eq!(data.len(), 3 * 2 * 3 * 2);
+        assert_eq!(&data[0..6], &[255, 255, 0, 0, 0, 0]);
+        // 0.5 * 65535 rounded up
+        assert_eq!(&data[6..12], &[0, 0, 128, 0, 0, 0]);
+        assert_eq!(&data[30..36], &[0, 0, 0, 0, 255, 255]);
+    }
+}
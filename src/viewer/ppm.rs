@@ -0,0 +1,276 @@
+use std::{
+    fmt::{Display, Formatter},
+    fs,
+    io::{self, Write},
+};
+
+use crate::math::colour::Colour;
+
+use super::canvas::{Canvas, SCALE};
+
+#[derive(PartialEq, Debug, Clone)]
+pub enum PpmError {
+    UnknownMagic,
+    Missing(&'static str),
+    NotANumber(&'static str, String),
+    InvalidMaxval(usize),
+    SampleOutOfRange(usize, usize),
+    TooLarge(usize, usize),
+}
+
+impl Display for PpmError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PpmError::UnknownMagic => write!(f, "not a P3 or P6 ppm"),
+            PpmError::Missing(what) => write!(f, "missing {what}"),
+            PpmError::NotANumber(what, s) => write!(f, "{what} is not a number: {s}"),
+            PpmError::InvalidMaxval(m) => write!(f, "maxval should be in 1..=65535: {m}"),
+            PpmError::SampleOutOfRange(s, m) => write!(f, "sample {s} above maxval {m}"),
+            PpmError::TooLarge(w, h) => write!(f, "image too large: {w}x{h}"),
+        }
+    }
+}
+
+impl std::error::Error for PpmError {}
+
+// walks through the header and the P3 samples, skipping whitespace and comments
+struct Tokens<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Tokens<'a> {
+    fn skip(&mut self) {
+        while let Some(b) = self.bytes.get(self.pos) {
+            match b {
+                b'#' => {
+                    while self.bytes.get(self.pos).is_some_and(|b| *b != b'\n') {
+                        self.pos += 1;
+                    }
+                }
+                b if b.is_ascii_whitespace() => self.pos += 1,
+                _ => break,
+            }
+        }
+    }
+
+    fn next(&mut self) -> Option<&'a [u8]> {
+        self.skip();
+        let start = self.pos;
+        while self
+            .bytes
+            .get(self.pos)
+            .is_some_and(|b| !b.is_ascii_whitespace() && *b != b'#')
+        {
+            self.pos += 1;
+        }
+        (start < self.pos).then(|| &self.bytes[start..self.pos])
+    }
+
+    fn number(&mut self, what: &'static str) -> Result<usize, PpmError> {
+        let token = self.next().ok_or(PpmError::Missing(what))?;
+        let s = String::from_utf8_lossy(token);
+        s.parse()
+            .map_err(|_| PpmError::NotANumber(what, s.into_owned()))
+    }
+}
+
+impl Canvas {
+    // rows are written one at a time rather than building the whole file in memory
+    pub fn write_p6<W: Write>(&self, mut w: W) -> io::Result<()> {
+        write!(w, "P6\n{} {}\n{}\n", self.width, self.height, SCALE)?;
+        let mut row = Vec::with_capacity(self.width * 3);
+        for y in 0..self.height {
+            row.clear();
+            for x in 0..self.width {
+                let (r, g, b) = self.at(x, y).unwrap_or(Colour::BLACK).scale(SCALE as u8);
+                row.extend([r, g, b]);
+            }
+            w.write_all(&row)?;
+        }
+        Ok(())
+    }
+
+    pub fn from_ppm(bytes: &[u8]) -> Result<Canvas, PpmError> {
+        let mut tokens = Tokens { bytes, pos: 0 };
+        let binary = match tokens.next() {
+            Some(b"P3") => false,
+            Some(b"P6") => true,
+            _ => return Err(PpmError::UnknownMagic),
+        };
+        let width = tokens.number("width")?;
+        let height = tokens.number("height")?;
+        let maxval = tokens.number("maxval")?;
+        if maxval == 0 || maxval > u16::MAX as usize {
+            return Err(PpmError::InvalidMaxval(maxval));
+        }
+
+        let too_large = || PpmError::TooLarge(width, height);
+        let samples = width
+            .checked_mul(height)
+            .and_then(|n| n.checked_mul(3))
+            .ok_or_else(too_large)?;
+        let values: Vec<usize> = if binary {
+            // a single whitespace separates the header from the raster
+            let start = tokens.pos + 1;
+            let size = if maxval < 256 { 1 } else { 2 };
+            let end = samples
+                .checked_mul(size)
+                .and_then(|n| n.checked_add(start))
+                .ok_or_else(too_large)?;
+            let raster = bytes.get(start..end).ok_or(PpmError::Missing("samples"))?;
+            raster
+                .chunks_exact(size)
+                .map(|c| c.iter().fold(0, |acc, b| (acc << 8) | *b as usize))
+                .collect()
+        } else {
+            (0..samples)
+                .map(|_| tokens.number("sample"))
+                .collect::<Result<_, _>>()?
+        };
+
+        let mut canvas = Canvas::black(width, height);
+        for (i, rgb) in values.chunks_exact(3).enumerate() {
+            if let Some(s) = rgb.iter().find(|s| **s > maxval) {
+                return Err(PpmError::SampleOutOfRange(*s, maxval));
+            }
+            let f = |s: usize| s as f64 / maxval as f64;
+            canvas.update(
+                i % width,
+                i / width,
+                Colour::new(f(rgb[0]), f(rgb[1]), f(rgb[2])),
+            );
+        }
+        Ok(canvas)
+    }
+
+    pub fn from_ppm_file(path: &str) -> io::Result<Canvas> {
+        let bytes = fs::read(path)?;
+        Canvas::from_ppm(&bytes).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::math::round::Round;
+
+    use super::*;
+
+    fn canvas() -> Canvas {
+        let mut c = Canvas::black(5, 3);
+        c.update(0, 0, Colour::new(1.5, 0.0, 0.0));
+        c.update(2, 1, Colour::new(0.0, 0.5, 0.0));
+        c.update(4, 2, Colour::new(-0.5, 0.0, 1.0));
+        c
+    }
+
+    #[test]
+    fn write_p6() -> () {
+        let mut bytes = Vec::new();
+        canvas().write_p6(&mut bytes).unwrap();
+        assert!(bytes.starts_with(b"P6\n5 3\n255\n"));
+        let raster = &bytes[11..];
+        assert_eq!(raster.len(), 5 * 3 * 3);
+        assert_eq!(&raster[0..3], &[255, 0, 0]);
+        assert_eq!(&raster[21..24], &[0, 128, 0]);
+        assert_eq!(&raster[42..45], &[0, 0, 255]);
+    }
+
+    #[test]
+    fn from_ppm_p3() -> () {
+        let ppm = "P3
+# a comment
+4 3
+255
+255 127 0  0 127 255  127 255 0  255 255 255
+0 0 0  255 0 0  0 255 0  0 0 255 # trailing
+255 255 0  0 255 255  255 0 255  127 127 127
+";
+        let c = Canvas::from_ppm(ppm.as_bytes()).unwrap();
+        assert_eq!((c.width, c.height), (4, 3));
+        vec![
+            (0, 0, Colour::new(1., 0.49804, 0.)),
+            (1, 0, Colour::new(0., 0.49804, 1.)),
+            (3, 0, Colour::WHITE),
+            (1, 1, Colour::new(1., 0., 0.)),
+            (3, 1, Colour::new(0., 0., 1.)),
+            (3, 2, Colour::new(0.49804, 0.49804, 0.49804)),
+        ]
+        .iter()
+        .for_each(|(x, y, exp)| {
+            assert_eq!(c.at(*x, *y).unwrap().rounded(5), exp.rounded(5));
+        });
+    }
+
+    #[test]
+    fn from_ppm_maxval() -> () {
+        let ppm = "P3 2 1 100
+100 100 100  50 50 50";
+        let c = Canvas::from_ppm(ppm.as_bytes()).unwrap();
+        assert_eq!(c.at(0, 0), Some(Colour::WHITE));
+        assert_eq!(c.at(1, 0), Some(Colour::new(0.5, 0.5, 0.5)));
+    }
+
+    #[test]
+    fn from_ppm_p6_16_bits() -> () {
+        let mut ppm = b"P6\n# comment\n1 1\n65535\n".to_vec();
+        ppm.extend([0xff, 0xff, 0x80, 0x00, 0x00, 0x00]);
+        let c = Canvas::from_ppm(&ppm).unwrap();
+        assert_eq!(
+            c.at(0, 0).unwrap().rounded(5),
+            Colour::new(1., 0.50001, 0.).rounded(5)
+        );
+    }
+
+    #[test]
+    fn round_trip() -> () {
+        let c = canvas();
+        let mut p6 = Vec::new();
+        c.write_p6(&mut p6).unwrap();
+        let from_p6 = Canvas::from_ppm(&p6).unwrap();
+        let from_p3 = Canvas::from_ppm(c.to_string().as_bytes()).unwrap();
+        let mut again = Vec::new();
+        from_p6.write_p6(&mut again).unwrap();
+        assert_eq!(p6, again);
+        assert_eq!(from_p3.to_string(), c.to_string());
+
+        let mut ramp = String::from("P3 256 1 255\n");
+        (0..256).for_each(|i| ramp.push_str(&format!("{i} {i} {i}\n")));
+        let c = Canvas::from_ppm(ramp.as_bytes()).unwrap();
+        let mut p6 = Vec::new();
+        c.write_p6(&mut p6).unwrap();
+        assert!(p6[13..]
+            .iter()
+            .enumerate()
+            .all(|(i, b)| *b as usize == i / 3));
+    }
+
+    #[test]
+    fn from_ppm_errors() -> () {
+        vec![
+            ("P5 1 1 255 0", PpmError::UnknownMagic),
+            ("P3 1", PpmError::Missing("height")),
+            (
+                "P3 1 x 255",
+                PpmError::NotANumber("height", "x".to_string()),
+            ),
+            ("P3 1 1 0", PpmError::InvalidMaxval(0)),
+            ("P3 1 1 70000", PpmError::InvalidMaxval(70000)),
+            ("P3 1 1 255 0 0", PpmError::Missing("sample")),
+            ("P3 1 1 15 0 16 0", PpmError::SampleOutOfRange(16, 15)),
+            ("P6 2 1 255\n\0\0\0", PpmError::Missing("samples")),
+            (
+                "P6 99999999999 99999999999 255",
+                PpmError::TooLarge(99999999999, 99999999999),
+            ),
+            (
+                "P6 3074457345618258602 1 65535",
+                PpmError::TooLarge(3074457345618258602, 1),
+            ),
+        ]
+        .iter()
+        .for_each(|(ppm, exp)| {
+            assert_eq!(Canvas::from_ppm(ppm.as_bytes()).err(), Some(exp.clone()));
+        });
+    }
+}
@@ -0,0 +1,239 @@
+use std::{
+    sync::{
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+        Arc, Mutex,
+    },
+    thread::{self, JoinHandle},
+};
+
+use crate::model::{camera::Camera, render_settings::RenderSettings, world::World};
+
+use super::{canvas::Canvas, drawable::Drawable};
+
+// side of the blocks of the first pass, halved on every following pass down to pixels
+pub const PREVIEW_BLOCK: usize = 16;
+
+// renders on a background thread so that the window stays responsive, coarse blocks first
+// then refined, the last pass gives the same image as Canvas::render_with
+pub struct Progressive {
+    canvas: Arc<Mutex<Canvas>>,
+    cancelled: Arc<AtomicBool>,
+    // set by the render whenever pixels are updated, cleared when read
+    changed: Arc<AtomicBool>,
+    handle: Option<JoinHandle<()>>,
+}
+
+impl Progressive {
+    pub fn new(width: usize, height: usize) -> Self {
+        Self {
+            canvas: Arc::new(Mutex::new(Canvas::black(width, height))),
+            cancelled: Arc::new(AtomicBool::new(false)),
+            changed: Arc::new(AtomicBool::new(false)),
+            handle: None,
+        }
+    }
+
+    // the previous render is stopped first, its pixels stay until the new preview covers them
+    pub fn restart(&mut self, c: &Camera, w: &Arc<World>, s: &RenderSettings) {
+        self.cancel();
+        self.cancelled = Arc::new(AtomicBool::new(false));
+        let canvas = Arc::clone(&self.canvas);
+        let cancelled = Arc::clone(&self.cancelled);
+        let changed = Arc::clone(&self.changed);
+        let (c, w, s) = (*c, Arc::clone(w), s.clone());
+        self.handle = Some(thread::spawn(move || {
+            let mut block = PREVIEW_BLOCK;
+            while block > 0 && !cancelled.load(Ordering::Relaxed) {
+                Self::pass(&canvas, &c, &w, &s, block, &cancelled, &changed);
+                block /= 2;
+            }
+        }));
+    }
+
+    // the render threads check it on every pixel, a pixel is all there is to wait for
+    pub fn cancel(&mut self) {
+        self.cancelled.store(true, Ordering::Relaxed);
+        self.wait();
+    }
+
+    // lets the render in flight finish, down to the last pass
+    pub fn wait(&mut self) {
+        if let Some(handle) = self.handle.take() {
+            handle.join().expect("render thread panicked");
+        }
+    }
+
+    pub fn is_done(&self) -> bool {
+        self.handle.as_ref().is_none_or(|h| h.is_finished())
+    }
+
+    pub fn take_changed(&self) -> bool {
+        self.changed.swap(false, Ordering::Relaxed)
+    }
+
+    // copy of the pixels rendered so far
+    pub fn canvas(&self) -> Canvas {
+        self.canvas.lock().expect("canvas lock poisoned").clone()
+    }
+
+    // one pixel per block, the block is filled with its colour, row of blocks by row of blocks
+    // pixels already computed by the coarser passes are skipped
+    fn pass(
+        canvas: &Mutex<Canvas>,
+        c: &Camera,
+        w: &World,
+        s: &RenderSettings,
+        block: usize,
+        cancelled: &AtomicBool,
+        changed: &AtomicBool,
+    ) {
+        let rows = c.vsize.div_ceil(block);
+        let next = AtomicUsize::new(0);
+        let done_before = |x: usize, y: usize| {
+            block < PREVIEW_BLOCK && x.is_multiple_of(2 * block) && y.is_multiple_of(2 * block)
+        };
+        thread::scope(|scope| {
+            for _ in 0..s.threads.clamp(1, rows.max(1)) {
+                scope.spawn(|| loop {
+                    let row = next.fetch_add(1, Ordering::Relaxed);
+                    if row >= rows || cancelled.load(Ordering::Relaxed) {
+                        break;
+                    }
+                    let y = row * block;
+                    let colours: Vec<_> = (0..c.hsize)
+                        .step_by(block)
+                        .filter(|x| !done_before(*x, y))
+                        .take_while(|_| !cancelled.load(Ordering::Relaxed))
+                        .map(|x| (x, Canvas::pixel_colour(c, w, x, y, s)))
+                        .collect();
+                    if cancelled.load(Ordering::Relaxed) {
+                        break;
+                    }
+                    let mut canvas = canvas.lock().expect("canvas lock poisoned");
+                    for (x, colour) in colours {
+                        for by in y..(y + block).min(c.vsize) {
+                            for bx in x..(x + block).min(c.hsize) {
+                                canvas.update(bx, by, colour);
+                            }
+                        }
+                    }
+                    changed.store(true, Ordering::Relaxed);
+                });
+            }
+        });
+    }
+}
+
+impl Drawable for Progressive {
+    fn draw(&self, frame: &mut [u8]) {
+        self.canvas
+            .lock()
+            .expect("canvas lock poisoned")
+            .draw(frame);
+    }
+}
+
+impl Drop for Progressive {
+    fn drop(&mut self) {
+        self.cancel();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{f64::consts::FRAC_PI_2, time::Duration};
+
+    use crate::math::{matrix::Matrix4x4, point::Point, vector::Vector};
+
+    use super::*;
+
+    fn camera() -> Camera {
+        let eye = Point::new(0., 0., -5.);
+        let to = Point::ORIGIN;
+        let up = Vector::new(0., 1., 0.);
+        Camera::new(37, 21, FRAC_PI_2)
+            .transform(Matrix4x4::view_transform(eye, to, up))
+            .unwrap()
+    }
+
+    fn wait(p: &Progressive) {
+        while !p.is_done() {
+            thread::yield_now();
+        }
+    }
+
+    #[test]
+    fn restart_same_as_render_with() -> () {
+        let (c, w) = (camera(), Arc::new(World::default()));
+        let s = RenderSettings::default().samples(4).threads(3);
+        let mut p = Progressive::new(c.hsize, c.vsize);
+        assert!(p.is_done());
+        p.restart(&c, &w, &s);
+        wait(&p);
+        assert!(p.take_changed());
+        assert!(!p.take_changed());
+        let mut exp = Canvas::black(c.hsize, c.vsize);
+        exp.render_with(&c, &w, &s, |_, _| ());
+        assert_eq!(p.canvas(), exp);
+    }
+
+    #[test]
+    fn wait_finishes_render() -> () {
+        let (c, w) = (camera(), Arc::new(World::default()));
+        let s = RenderSettings::default().threads(2);
+        let mut p = Progressive::new(c.hsize, c.vsize);
+        p.restart(&c, &w, &s);
+        p.wait();
+        assert!(p.is_done());
+        let mut exp = Canvas::black(c.hsize, c.vsize);
+        exp.render_with(&c, &w, &s, |_, _| ());
+        assert_eq!(p.canvas(), exp);
+    }
+
+    #[test]
+    fn pass_fills_blocks() -> () {
+        let (c, w) = (camera(), World::default());
+        let s = RenderSettings::default();
+        let canvas = Mutex::new(Canvas::black(c.hsize, c.vsize));
+        let changed = AtomicBool::new(false);
+        Progressive::pass(
+            &canvas,
+            &c,
+            &w,
+            &s,
+            PREVIEW_BLOCK,
+            &AtomicBool::new(false),
+            &changed,
+        );
+        assert!(changed.load(Ordering::Relaxed));
+        let canvas = canvas.into_inner().unwrap();
+        // blocks on the edges are cut to the canvas
+        let sample = Canvas::pixel_colour(&c, &w, 16, 16, &s);
+        assert_eq!(canvas.at(16, 16), Some(sample));
+        assert_eq!(canvas.at(31, 20), Some(sample));
+        assert_eq!(canvas.at(36, 20), canvas.at(32, 16));
+    }
+
+    #[test]
+    fn cancel() -> () {
+        let (c, w) = (camera(), Arc::new(World::default()));
+        let mut p = Progressive::new(c.hsize, c.vsize);
+        p.restart(&c, &w, &RenderSettings::default().samples(16));
+        p.cancel();
+        assert!(p.is_done());
+        // nothing is left running to touch the canvas
+        let before = p.canvas();
+        thread::sleep(Duration::from_millis(50));
+        assert_eq!(p.canvas(), before);
+        // a cancelled pass doesn't touch the canvas
+        let canvas = Mutex::new(Canvas::black(c.hsize, c.vsize));
+        let s = RenderSettings::default();
+        let changed = AtomicBool::new(false);
+        Progressive::pass(&canvas, &c, &w, &s, 1, &AtomicBool::new(true), &changed);
+        assert!(!changed.load(Ordering::Relaxed));
+        assert_eq!(
+            canvas.into_inner().unwrap(),
+            Canvas::black(c.hsize, c.vsize)
+        );
+    }
+}
@@ -1,17 +1,101 @@
 use std::{
-    fmt::Display,
     fs::File,
-    io::{Result, Write},
+    io::{BufWriter, Error, ErrorKind, Result, Write},
+    path::Path,
 };
 
-pub trait ToFile {
-    fn to_file(&self, filename: &str) -> Result<()>;
+use super::{canvas::Canvas, png::PngDepth};
+
+#[derive(PartialEq, Debug, Copy, Clone)]
+pub enum ImageFormat {
+    // plain text ppm
+    P3,
+    // binary ppm
+    P6,
+    Png(PngDepth),
+}
+
+impl ImageFormat {
+    // png files are written with 8 bits per channel unless asked otherwise
+    pub fn from_path(path: &str) -> Option<Self> {
+        let ext = Path::new(path).extension()?.to_str()?.to_lowercase();
+        match ext.as_str() {
+            "ppm" => Some(ImageFormat::P3),
+            "png" => Some(ImageFormat::Png(PngDepth::Eight)),
+            _ => None,
+        }
+    }
 }
 
-impl<T: Display> ToFile for T {
+pub trait ToFile {
+    fn to_file_as(&self, path: &str, format: ImageFormat) -> Result<()>;
+
+    // the format is picked from the file extension
     fn to_file(&self, path: &str) -> Result<()> {
-        let mut file = File::create(path)?;
-        file.write_fmt(format_args!("{}", self))?;
-        Ok(())
+        let format = ImageFormat::from_path(path).ok_or_else(|| {
+            Error::new(
+                ErrorKind::InvalidInput,
+                format!("unsupported image extension: {path}"),
+            )
+        })?;
+        self.to_file_as(path, format)
+    }
+}
+
+impl ToFile for Canvas {
+    fn to_file_as(&self, path: &str, format: ImageFormat) -> Result<()> {
+        let mut file = BufWriter::new(File::create(path)?);
+        match format {
+            ImageFormat::P3 => file.write_fmt(format_args!("{}", self))?,
+            ImageFormat::P6 => self.write_p6(&mut file)?,
+            ImageFormat::Png(depth) => self.write_png(&mut file, depth)?,
+        }
+        file.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{env, fs};
+
+    use crate::math::colour::Colour;
+
+    use super::*;
+
+    #[test]
+    fn from_path() -> () {
+        vec![
+            ("render.ppm", Some(ImageFormat::P3)),
+            ("renders/render.PPM", Some(ImageFormat::P3)),
+            ("render.png", Some(ImageFormat::Png(PngDepth::Eight))),
+            ("render.jpg", None),
+            ("render", None),
+        ]
+        .iter()
+        .for_each(|(path, exp)| {
+            assert_eq!(ImageFormat::from_path(path), *exp);
+        });
+    }
+
+    #[test]
+    fn to_file() -> () {
+        let c = Canvas::new(2, 1, Colour::new(1., 0.8, 0.6));
+        let dir = env::temp_dir().join(format!("ray-to-file-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let ppm = dir.join("ray_to_file.ppm");
+        c.to_file(ppm.to_str().unwrap()).unwrap();
+        assert_eq!(fs::read_to_string(&ppm).unwrap(), c.to_string());
+        let png = dir.join("ray_to_file.png");
+        c.to_file(png.to_str().unwrap()).unwrap();
+        assert!(fs::read(&png).unwrap().starts_with(b"\x89PNG"));
+        let unknown = dir.join("ray_to_file.bmp");
+        let err = c.to_file(unknown.to_str().unwrap()).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidInput);
+        assert!(!unknown.exists());
+        c.to_file_as(ppm.to_str().unwrap(), ImageFormat::P6)
+            .unwrap();
+        let read = Canvas::from_ppm_file(ppm.to_str().unwrap()).unwrap();
+        assert_eq!(read.to_string(), c.to_string());
+        fs::remove_dir_all(&dir).unwrap();
     }
 }